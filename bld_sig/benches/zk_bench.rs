@@ -1,20 +1,23 @@
 use class_group::primitives::cl_dl_lcm::HSMCL;
-use class_group::pari_init;
 use curv::cryptographic_primitives::hashing::traits::Hash;
 use curv::arithmetic::traits::Samplable;
 use curv::elliptic::curves::traits::{ECPoint, ECScalar};
 use curv::{FE, GE};
+use bld_sig::pari;
 use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
 use bld_sig::protocols::asiaccs_zk::ZK_AsiaCCS_19;
 use bld_sig::protocols::ggm_zk::zkPoKEncProof_v0;
 use bld_sig::protocols::ggm_zk::zkPoKEncProof;
+use bld_sig::protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
 use bld_sig::protocols::blind_ecdsa::test_sign::test_sign_mpaillier;
 use bld_sig::protocols::blind_ecdsa::test_sign::test_sign_hsmcl;
 use bld_sig::protocols::blind_ecdsa::test_sign::test_sign_hsmcl_ggm;
 use criterion::criterion_main;
 
-// three pleaces need to be set: lib.rs, man.rs, zk_bench.rs
+// two places need to be set: lib.rs, zk_bench.rs
 const SECURITY_PARAMETER: usize = 80;
+// number of proofs in the batch verification benchmarks
+const BATCH_SIZE: usize = 8;
 
 mod bench {
     use criterion::{criterion_group, Criterion};
@@ -25,9 +28,9 @@ mod bench {
     pub fn blind_sign_by_modified_paillier_2048(c: &mut Criterion) {
         c.bench_function("blind_sign_by_modified_paillier", move |b| {
             let bitsize = 2048;
-            let message = HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&FE::q()); 
+            let message: &[u8] = b"111";
             b.iter(||
-                test_sign_mpaillier(&bitsize, &message)     
+                test_sign_mpaillier(&bitsize, message)     
             )
         });
     }
@@ -35,9 +38,9 @@ mod bench {
     pub fn blind_sign_by_hsmcl_112_sec(c: &mut Criterion) {
         c.bench_function("blind_sign_by_hsmcl_112_sec", move |b| {
             let lam = 1348;
-            let message = HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&FE::q()); 
+            let message: &[u8] = b"111";
             b.iter(||
-                test_sign_hsmcl(&lam, &message)     
+                test_sign_hsmcl(&lam, message)     
             )
         });
     }
@@ -45,9 +48,9 @@ mod bench {
     pub fn blind_sign_by_hsmcl_128_sec(c: &mut Criterion) {
         c.bench_function("blind_sign_by_hsmcl_128_sec", move |b| {
             let lam = 1827;
-            let message = HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&FE::q()); 
+            let message: &[u8] = b"111";
             b.iter(||
-                test_sign_hsmcl(&lam, &message)     
+                test_sign_hsmcl(&lam, message)     
             )
         });
     }
@@ -55,9 +58,9 @@ mod bench {
     pub fn blind_sign_by_hsmcl_ggm_nizk_112_sec(c: &mut Criterion) {
         c.bench_function("blind_sign_by_hsmcl_ggm_nizk_112_sec", move |b| {
             let lam = 1348;
-            let message = HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&FE::q()); 
+            let message: &[u8] = b"111";
             b.iter(||
-                test_sign_hsmcl_ggm(&lam, &message)     
+                test_sign_hsmcl_ggm(&lam, message)     
             )
         });
     }
@@ -65,9 +68,9 @@ mod bench {
     pub fn blind_sign_by_hsmcl_ggm_nizk_128_sec(c: &mut Criterion) {
         c.bench_function("blind_sign_by_hsmcl_ggm_nizk_128_sec", move |b| {
             let lam = 1827;
-            let message = HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&FE::q()); 
+            let message: &[u8] = b"111";
             b.iter(||
-                test_sign_hsmcl_ggm(&lam, &message)     
+                test_sign_hsmcl_ggm(&lam, message)     
             )
         });
     }
@@ -229,7 +232,7 @@ mod bench {
 
     pub fn hsmcl_nizk_prove_112(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_prove_112", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -262,7 +265,7 @@ mod bench {
             b.iter(||
                 zkPoKEncProof::prove(
                     g.clone(), 
-                    hsmcl.clone(), 
+                    &hsmcl,
                     h.clone(),
                     Kx.clone(),
                     r1.clone(),
@@ -283,7 +286,7 @@ mod bench {
 
     pub fn hsmcl_nizk_prove_128(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_prove_128", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -315,7 +318,7 @@ mod bench {
             b.iter(||
                 zkPoKEncProof::prove(
                     g.clone(), 
-                    hsmcl.clone(), 
+                    &hsmcl,
                     h.clone(),
                     Kx.clone(),
                     r1.clone(),
@@ -336,7 +339,7 @@ mod bench {
 
     pub fn hsmcl_nizk_verify_112(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_verify_112", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -367,7 +370,7 @@ mod bench {
             let minus_B = &minus_one * &B; // = -B
             let proof = zkPoKEncProof::prove(
                 g.clone(), 
-                hsmcl.clone(), 
+                &hsmcl,
                 h.clone(),
                 Kx.clone(),
                 r1.clone(),
@@ -388,7 +391,7 @@ mod bench {
 
     pub fn hsmcl_nizk_verify_128(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_verify_128", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -418,7 +421,7 @@ mod bench {
             let minus_B = &minus_one * &B; // = -B
             let proof = zkPoKEncProof::prove(
                 g.clone(), 
-                hsmcl.clone(), 
+                &hsmcl,
                 h.clone(),
                 Kx.clone(),
                 r1.clone(),
@@ -438,7 +441,7 @@ mod bench {
     } 
     pub fn hsmcl_nizk_prove_112_v0(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_prove_112_v0", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -458,7 +461,6 @@ mod bench {
             let Enc_Kx = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &Kx, &r2);
             let y1 = Enc_Kx.c1.clone();
             let y2 = Enc_Kx.c2.clone();
-            let g = GE::generator(); // ECC generator
             let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
             let two_pow_exp = BigInt::ui_pow_ui(2, exp);
             let B = &two_pow_exp * &hsmcl.pk.stilde;
@@ -467,8 +469,7 @@ mod bench {
 
             b.iter(||
                 zkPoKEncProof_v0::prove(
-                    g.clone(), 
-                    hsmcl.clone(), 
+                    &hsmcl,
                     h.clone(),
                     Kx.clone(),
                     r1.clone(),
@@ -487,7 +488,7 @@ mod bench {
 
     pub fn hsmcl_nizk_prove_128_v0(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_prove_128_v0", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -506,7 +507,6 @@ mod bench {
             let Enc_Kx = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &Kx, &r2);
             let y1 = Enc_Kx.c1.clone();
             let y2 = Enc_Kx.c2.clone();
-            let g = GE::generator(); // ECC generator
             let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
             let two_pow_exp = BigInt::ui_pow_ui(2, exp);
             let B = &two_pow_exp * &hsmcl.pk.stilde;
@@ -515,8 +515,7 @@ mod bench {
 
             b.iter(||
                 zkPoKEncProof_v0::prove(
-                    g.clone(), 
-                    hsmcl.clone(), 
+                    &hsmcl,
                     h.clone(),
                     Kx.clone(),
                     r1.clone(),
@@ -535,7 +534,7 @@ mod bench {
 
     pub fn hsmcl_nizk_verify_112_v0(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_verify_112_v0", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -555,15 +554,13 @@ mod bench {
             let Enc_Kx = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &Kx, &r2);
             let y1 = Enc_Kx.c1.clone();
             let y2 = Enc_Kx.c2.clone();
-            let g = GE::generator(); // ECC generator
             let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
             let two_pow_exp = BigInt::ui_pow_ui(2, exp);
             let B = &two_pow_exp * &hsmcl.pk.stilde;
             let minus_one = BigInt::from(-1);
             let minus_B = &minus_one * &B; // = -B
             let proof = zkPoKEncProof_v0::prove(
-                g.clone(), 
-                hsmcl.clone(), 
+                &hsmcl,
                 h.clone(),
                 Kx.clone(),
                 r1.clone(),
@@ -582,7 +579,7 @@ mod bench {
 
     pub fn hsmcl_nizk_verify_128_v0(c: &mut Criterion) {
         c.bench_function("hsmcl_nizk_verify_128_v0", move |b| {
-            unsafe { pari_init(10000000000, 2) };
+            let _pari = pari::enter().unwrap();
             let q = str::parse(
                 "115792089237316195423570985008687907852837564279074904382605163141518161494337",
             )
@@ -601,15 +598,13 @@ mod bench {
             let Enc_Kx = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &Kx, &r2);
             let y1 = Enc_Kx.c1.clone();
             let y2 = Enc_Kx.c2.clone();
-            let g = GE::generator(); // ECC generator
             let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
             let two_pow_exp = BigInt::ui_pow_ui(2, exp);
             let B = &two_pow_exp * &hsmcl.pk.stilde;
             let minus_one = BigInt::from(-1);
             let minus_B = &minus_one * &B; // = -B
             let proof = zkPoKEncProof_v0::prove(
-                g.clone(), 
-                hsmcl.clone(), 
+                &hsmcl,
                 h.clone(),
                 Kx.clone(),
                 r1.clone(),
@@ -625,6 +620,297 @@ mod bench {
             b.iter(||proof.verify());
         });
     } 
+
+    // fixed-base tables are built once outside the timed loop, compare with
+    // the corresponding benchmarks without the _precomp suffix
+    pub fn AsiaCCS_nizk_prove_2048_precomp(c: &mut Criterion) {
+        c.bench_function("AsiaCCS_nizk_prove_2048_precomp", move |b| {
+            let key = Pallier_AsiaCCS_19::keygen(2048 as usize);
+            let pp = PrecomputedPaillier::from_key(&key);
+            let r = HSha256::create_hash(&[&BigInt::from(123)]).mod_floor(&key.q);
+            let message = HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&key.q);
+            let ciphertext = pp.encrypt(&message, &r);
+            b.iter(||
+                ZK_AsiaCCS_19::prove_precomputed(
+                    &pp,
+                    ciphertext.clone(),
+                    message.clone(), 
+                    r.clone(),
+                )       
+            )
+        });
+    }
+
+    pub fn AsiaCCS_nizk_verify_2048_precomp(c: &mut Criterion) {
+        c.bench_function("AsiaCCS_nizk_verify_2048_precomp", move |b| {
+            let key = Pallier_AsiaCCS_19::keygen(2048 as usize);
+            let pp = PrecomputedPaillier::from_key(&key);
+            let r = HSha256::create_hash(&[&BigInt::from(123)]).mod_floor(&key.q);
+            let message = HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&key.q);
+            let ciphertext = pp.encrypt(&message, &r);
+            let proof = ZK_AsiaCCS_19::prove_precomputed(
+                &pp,
+                ciphertext.clone(),
+                message.clone(), 
+                r.clone(),
+            );
+            b.iter(||proof.verify_precomputed(&pp));
+        });
+    }
+
+    pub fn hsmcl_nizk_prove_112_precomp(c: &mut Criterion) {
+        c.bench_function("hsmcl_nizk_prove_112_precomp", move |b| {
+            let _pari = pari::enter().unwrap();
+            let q = str::parse(
+                "115792089237316195423570985008687907852837564279074904382605163141518161494337",
+            )
+            .unwrap();
+            let seed = str::parse(
+                "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
+            ).unwrap();
+
+            let hsmcl = HSMCL::keygen_with_setup(&q, &1348, &seed);
+            let ppk = PrecomputedPK::new(&hsmcl.pk);
+            let h = HSha256::create_hash(&[&BigInt::from(111)]);
+            let Kx = HSha256::create_hash(&[&BigInt::from(222)]);
+            let r1 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+            let r2 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+            let Enc_h = ppk.encrypt_predefined_randomness(&h, &r1);
+            let x1 = Enc_h.c1.clone();
+            let x2 = Enc_h.c2.clone();
+            let Enc_Kx = ppk.encrypt_predefined_randomness(&Kx, &r2);
+            let y1 = Enc_Kx.c1.clone();
+            let y2 = Enc_Kx.c2.clone();
+            let SK_fe: FE = FE::new_random();
+            let SK = SK_fe.to_big_int();
+            let g = GE::generator(); // ECC generator
+            let PK = g.clone() * SK_fe;
+            let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
+            let two_pow_exp = BigInt::ui_pow_ui(2, exp);
+            let B = &two_pow_exp * &hsmcl.pk.stilde;
+            let minus_one = BigInt::from(-1);
+            let minus_B = &minus_one * &B; // = -B
+
+            b.iter(||
+                zkPoKEncProof::prove_precomputed(
+                    g.clone(), 
+                    &hsmcl,
+                    &ppk,
+                    h.clone(),
+                    Kx.clone(),
+                    r1.clone(),
+                    r2.clone(),
+                    x1.clone(),
+                    x2.clone(),
+                    y1.clone(),
+                    y2.clone(),
+                    PK.clone(),
+                    SK.clone(),
+                    B.clone(),
+                    minus_B.clone(),
+                    seed.clone(),
+                )
+            )
+        });
+    }
+
+    pub fn hsmcl_nizk_verify_112_precomp(c: &mut Criterion) {
+        c.bench_function("hsmcl_nizk_verify_112_precomp", move |b| {
+            let _pari = pari::enter().unwrap();
+            let q = str::parse(
+                "115792089237316195423570985008687907852837564279074904382605163141518161494337",
+            )
+            .unwrap();
+            let seed = str::parse(
+                "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
+            ).unwrap();
+
+            let hsmcl = HSMCL::keygen_with_setup(&q, &1348, &seed);
+            let ppk = PrecomputedPK::new(&hsmcl.pk);
+            let h = HSha256::create_hash(&[&BigInt::from(111)]);
+            let Kx = HSha256::create_hash(&[&BigInt::from(222)]);
+            let r1 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+            let r2 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+            let Enc_h = ppk.encrypt_predefined_randomness(&h, &r1);
+            let x1 = Enc_h.c1.clone();
+            let x2 = Enc_h.c2.clone();
+            let Enc_Kx = ppk.encrypt_predefined_randomness(&Kx, &r2);
+            let y1 = Enc_Kx.c1.clone();
+            let y2 = Enc_Kx.c2.clone();
+            let SK_fe: FE = FE::new_random();
+            let SK = SK_fe.to_big_int();
+            let g = GE::generator(); // ECC generator
+            let PK = g.clone() * SK_fe;
+            let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
+            let two_pow_exp = BigInt::ui_pow_ui(2, exp);
+            let B = &two_pow_exp * &hsmcl.pk.stilde;
+            let minus_one = BigInt::from(-1);
+            let minus_B = &minus_one * &B; // = -B
+            let proof = zkPoKEncProof::prove_precomputed(
+                g.clone(), 
+                &hsmcl,
+                &ppk,
+                h.clone(),
+                Kx.clone(),
+                r1.clone(),
+                r2.clone(),
+                x1.clone(),
+                x2.clone(),
+                y1.clone(),
+                y2.clone(),
+                PK.clone(),
+                SK.clone(),
+                B.clone(),
+                minus_B.clone(),
+                seed.clone(),
+            );
+            b.iter(||proof.verify_precomputed(&ppk));
+        });
+    }
+
+    // BATCH_SIZE proofs checked one by one vs. with verify_batch
+    pub fn AsiaCCS_nizk_verify_2048_each(c: &mut Criterion) {
+        c.bench_function("AsiaCCS_nizk_verify_2048_each", move |b| {
+            let key = Pallier_AsiaCCS_19::keygen(2048 as usize);
+            let pp = PrecomputedPaillier::from_key(&key);
+            let proofs = (0..BATCH_SIZE)
+                .map(|i| {
+                    let r = HSha256::create_hash(&[&BigInt::from(123 + i as i32)]).mod_floor(&key.q);
+                    let message = HSha256::create_hash(&[&BigInt::from(111 + i as i32)]).mod_floor(&key.q);
+                    let ciphertext = pp.encrypt(&message, &r);
+                    ZK_AsiaCCS_19::prove_precomputed(&pp, ciphertext, message, r)
+                })
+                .collect::<Vec<ZK_AsiaCCS_19>>();
+            let proof_refs = proofs.iter().collect::<Vec<&ZK_AsiaCCS_19>>();
+            b.iter(|| proofs.iter().all(|proof| proof.verify()));
+        });
+    }
+
+    pub fn AsiaCCS_nizk_verify_2048_batch(c: &mut Criterion) {
+        c.bench_function("AsiaCCS_nizk_verify_2048_batch", move |b| {
+            let key = Pallier_AsiaCCS_19::keygen(2048 as usize);
+            let pp = PrecomputedPaillier::from_key(&key);
+            let proofs = (0..BATCH_SIZE)
+                .map(|i| {
+                    let r = HSha256::create_hash(&[&BigInt::from(123 + i as i32)]).mod_floor(&key.q);
+                    let message = HSha256::create_hash(&[&BigInt::from(111 + i as i32)]).mod_floor(&key.q);
+                    let ciphertext = pp.encrypt(&message, &r);
+                    ZK_AsiaCCS_19::prove_precomputed(&pp, ciphertext, message, r)
+                })
+                .collect::<Vec<ZK_AsiaCCS_19>>();
+            let proof_refs = proofs.iter().collect::<Vec<&ZK_AsiaCCS_19>>();
+            b.iter(|| ZK_AsiaCCS_19::verify_batch(&proof_refs));
+        });
+    }
+
+    pub fn hsmcl_nizk_verify_112_each(c: &mut Criterion) {
+        c.bench_function("hsmcl_nizk_verify_112_each", move |b| {
+            let _pari = pari::enter().unwrap();
+            let q = str::parse(
+                "115792089237316195423570985008687907852837564279074904382605163141518161494337",
+            )
+            .unwrap();
+            let seed = str::parse(
+                "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
+            ).unwrap();
+            let hsmcl = HSMCL::keygen_with_setup(&q, &1348, &seed);
+            let ppk = PrecomputedPK::new(&hsmcl.pk);
+            let g = GE::generator(); // ECC generator
+            let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
+            let two_pow_exp = BigInt::ui_pow_ui(2, exp);
+            let B = &two_pow_exp * &hsmcl.pk.stilde;
+            let minus_one = BigInt::from(-1);
+            let minus_B = &minus_one * &B; // = -B
+            let proofs = (0..BATCH_SIZE)
+                .map(|i| {
+                    let h = HSha256::create_hash(&[&BigInt::from(111 + i as i32)]);
+                    let Kx = HSha256::create_hash(&[&BigInt::from(222 + i as i32)]);
+                    let r1 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+                    let r2 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+                    let Enc_h = ppk.encrypt_predefined_randomness(&h, &r1);
+                    let Enc_Kx = ppk.encrypt_predefined_randomness(&Kx, &r2);
+                    let SK_fe: FE = FE::new_random();
+                    let SK = SK_fe.to_big_int();
+                    let PK = g.clone() * SK_fe;
+                    zkPoKEncProof::prove_precomputed(
+                        g.clone(), 
+                        &hsmcl,
+                        &ppk,
+                        h,
+                        Kx,
+                        r1,
+                        r2,
+                        Enc_h.c1.clone(),
+                        Enc_h.c2.clone(),
+                        Enc_Kx.c1.clone(),
+                        Enc_Kx.c2.clone(),
+                        PK,
+                        SK,
+                        B.clone(),
+                        minus_B.clone(),
+                        seed.clone(),
+                    )
+                })
+                .collect::<Vec<zkPoKEncProof>>();
+            let proof_refs = proofs.iter().collect::<Vec<&zkPoKEncProof>>();
+            b.iter(|| proofs.iter().all(|proof| proof.verify().is_ok()));
+        });
+    }
+
+    pub fn hsmcl_nizk_verify_112_batch(c: &mut Criterion) {
+        c.bench_function("hsmcl_nizk_verify_112_batch", move |b| {
+            let _pari = pari::enter().unwrap();
+            let q = str::parse(
+                "115792089237316195423570985008687907852837564279074904382605163141518161494337",
+            )
+            .unwrap();
+            let seed = str::parse(
+                "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
+            ).unwrap();
+            let hsmcl = HSMCL::keygen_with_setup(&q, &1348, &seed);
+            let ppk = PrecomputedPK::new(&hsmcl.pk);
+            let g = GE::generator(); // ECC generator
+            let exp = (SECURITY_PARAMETER as u32) + 80 + 2; // epsilon_d = 80
+            let two_pow_exp = BigInt::ui_pow_ui(2, exp);
+            let B = &two_pow_exp * &hsmcl.pk.stilde;
+            let minus_one = BigInt::from(-1);
+            let minus_B = &minus_one * &B; // = -B
+            let proofs = (0..BATCH_SIZE)
+                .map(|i| {
+                    let h = HSha256::create_hash(&[&BigInt::from(111 + i as i32)]);
+                    let Kx = HSha256::create_hash(&[&BigInt::from(222 + i as i32)]);
+                    let r1 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+                    let r2 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+                    let Enc_h = ppk.encrypt_predefined_randomness(&h, &r1);
+                    let Enc_Kx = ppk.encrypt_predefined_randomness(&Kx, &r2);
+                    let SK_fe: FE = FE::new_random();
+                    let SK = SK_fe.to_big_int();
+                    let PK = g.clone() * SK_fe;
+                    zkPoKEncProof::prove_precomputed(
+                        g.clone(), 
+                        &hsmcl,
+                        &ppk,
+                        h,
+                        Kx,
+                        r1,
+                        r2,
+                        Enc_h.c1.clone(),
+                        Enc_h.c2.clone(),
+                        Enc_Kx.c1.clone(),
+                        Enc_Kx.c2.clone(),
+                        PK,
+                        SK,
+                        B.clone(),
+                        minus_B.clone(),
+                        seed.clone(),
+                    )
+                })
+                .collect::<Vec<zkPoKEncProof>>();
+            let proof_refs = proofs.iter().collect::<Vec<&zkPoKEncProof>>();
+            b.iter(|| zkPoKEncProof::verify_batch(&proof_refs));
+        });
+    }
+
     criterion_group! {
         name = benchmarks;
         config = Criterion::default().sample_size(10);
@@ -653,6 +939,30 @@ mod bench {
         self::blind_sign_by_hsmcl_128_sec,
 
     }
+
+    criterion_group! {
+        name = precomp_benchmarks;
+        config = Criterion::default().sample_size(10);
+        targets = 
+        self::AsiaCCS_nizk_prove_2048,
+        self::AsiaCCS_nizk_prove_2048_precomp,
+        self::AsiaCCS_nizk_verify_2048,
+        self::AsiaCCS_nizk_verify_2048_precomp,
+        self::hsmcl_nizk_prove_112,
+        self::hsmcl_nizk_prove_112_precomp,
+        self::hsmcl_nizk_verify_112,
+        self::hsmcl_nizk_verify_112_precomp,
+    }
+
+    criterion_group! {
+        name = batch_benchmarks;
+        config = Criterion::default().sample_size(10);
+        targets = 
+        self::AsiaCCS_nizk_verify_2048_each,
+        self::AsiaCCS_nizk_verify_2048_batch,
+        self::hsmcl_nizk_verify_112_each,
+        self::hsmcl_nizk_verify_112_batch,
+    }
 }
 
-criterion_main!(bench::benchmarks);
\ No newline at end of file
+criterion_main!(bench::benchmarks, bench::precomp_benchmarks, bench::batch_benchmarks);
\ No newline at end of file
@@ -0,0 +1,72 @@
+// Signer service: answers blind signing sessions over TCP with the key
+// written by `bld-sig signer keygen`.
+//
+//   server --key signer.key --listen 127.0.0.1:7878 --session-timeout 300 --require-committed
+
+use std::fs;
+use std::process;
+use std::time::Duration;
+
+use bld_sig::net::server::{Server, ServerConfig};
+use bld_sig::protocols::blind_ecdsa::signer::Signer_SigningKey;
+use clap::{App, Arg};
+use tokio::net::TcpListener;
+
+// a positive number of seconds
+fn secs(name: &str, value: &str) -> Duration {
+    match value.parse() {
+        Ok(s) if s > 0 => Duration::from_secs(s),
+        _ => fail(&format!("invalid --{} {}", name, value)),
+    }
+}
+
+fn fail(e: &str) -> ! {
+    eprintln!("server: {}", e);
+    process::exit(1);
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = App::new("server")
+        .about("Blind ECDSA signer service")
+        .arg(Arg::with_name("key").long("key").value_name("FILE").required(true).help("Signing key"))
+        .arg(Arg::with_name("listen").long("listen").value_name("ADDR").default_value("127.0.0.1:7878"))
+        .arg(
+            Arg::with_name("session-timeout")
+                .long("session-timeout")
+                .value_name("SECS")
+                .default_value("300")
+                .help("Drop sessions not finished within this time"),
+        )
+        .arg(
+            Arg::with_name("io-timeout")
+                .long("io-timeout")
+                .value_name("SECS")
+                .default_value("60")
+                .help("Close connections idle for this long"),
+        )
+        .arg(
+            Arg::with_name("require-committed")
+                .long("require-committed")
+                .help("Only run sessions with the committed round 1"),
+        )
+        .get_matches();
+
+    let path = matches.value_of("key").unwrap();
+    let key: Signer_SigningKey = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| serde_json::from_str(&data).map_err(|e| e.to_string()))
+        .unwrap_or_else(|e| fail(&format!("{}: {}", path, e)));
+    let config = ServerConfig {
+        session_timeout: secs("session-timeout", matches.value_of("session-timeout").unwrap()),
+        io_timeout: secs("io-timeout", matches.value_of("io-timeout").unwrap()),
+        require_committed: matches.is_present("require-committed"),
+        ..ServerConfig::default()
+    };
+    let addr = matches.value_of("listen").unwrap();
+    let listener = TcpListener::bind(addr).await.unwrap_or_else(|e| fail(&format!("{}: {}", addr, e)));
+    eprintln!("server: listening on {}", listener.local_addr().unwrap());
+    Server::new(key, config)
+        .serve(listener, |e| eprintln!("server: accept: {}", e))
+        .await;
+}
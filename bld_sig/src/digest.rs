@@ -0,0 +1,91 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use crypto::sha3::Sha3;
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::FE;
+use serde::{Deserialize, Serialize};
+
+// Message digests for the value h that gets blind-signed. The message is
+// hashed as raw bytes and the digest converted with bits2int and reduced
+// mod q, as in plain ECDSA, so that (r, s) verifies under the usual rules of
+// the chosen ecosystem.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MessageDigest {
+    Sha256,
+    DoubleSha256, // Bitcoin
+    Keccak256,    // Ethereum
+}
+
+impl MessageDigest {
+    pub fn digest(&self, msg: &[u8]) -> Vec<u8> {
+        match self {
+            MessageDigest::Sha256 => hash(Sha256::new(), msg),
+            MessageDigest::DoubleSha256 => hash(Sha256::new(), &hash(Sha256::new(), msg)),
+            MessageDigest::Keccak256 => hash(Sha3::keccak256(), msg),
+        }
+    }
+
+    // h = bits2int(digest(msg)) mod q
+    pub fn hash_to_scalar(&self, msg: &[u8]) -> BigInt {
+        bits2int_mod_q(&self.digest(msg))
+    }
+}
+
+impl Default for MessageDigest {
+    fn default() -> Self {
+        MessageDigest::Sha256
+    }
+}
+
+impl fmt::Display for MessageDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MessageDigest::Sha256 => "sha256",
+            MessageDigest::DoubleSha256 => "sha256d",
+            MessageDigest::Keccak256 => "keccak256",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for MessageDigest {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(MessageDigest::Sha256),
+            "sha256d" => Ok(MessageDigest::DoubleSha256),
+            "keccak256" => Ok(MessageDigest::Keccak256),
+            _ => Err(format!("unknown digest {}, expected sha256, sha256d or keccak256", s)),
+        }
+    }
+}
+
+// leftmost qlen bits of the digest as an integer (SEC 1, section 4.1.3 step 5)
+pub fn bits2int(digest: &[u8]) -> BigInt {
+    let qlen = FE::q().bit_length();
+    let n = BigInt::from(digest);
+    let dlen = digest.len() * 8;
+    if dlen > qlen {
+        n >> (dlen - qlen)
+    } else {
+        n
+    }
+}
+
+pub fn bits2int_mod_q(digest: &[u8]) -> BigInt {
+    bits2int(digest).mod_floor(&FE::q())
+}
+
+fn hash<D: Digest>(mut hasher: D, msg: &[u8]) -> Vec<u8> {
+    hasher.input(msg);
+    let mut out = vec![0u8; hasher.output_bytes()];
+    hasher.result(&mut out);
+    out
+}
@@ -1,6 +1,14 @@
 #![feature(test)]
 // #![feature(globs)]
 pub mod protocols;
+pub mod digest;
+pub mod secret;
+pub mod signature;
+pub mod pari;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+#[cfg(feature = "net")]
+pub mod net;
 
 extern crate test;
 extern crate class_group;
@@ -50,6 +58,7 @@ pub enum Error {
     InvalidCom,
     InvalidSig,
     InvalidProof,
+    Pari, // PARI could not be started, see pari.rs
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -1,100 +1,344 @@
-#![feature(test)]
-// #![feature(globs)]
-pub mod protocols;
-
-extern crate test;
-extern crate class_group;
-extern crate paillier;
-extern crate libc;
-#[macro_use]
-extern crate serde_derive;
-extern crate serde_json;
-extern crate criterion;
-
-use serde::{Deserialize, Serialize};
-use class_group::primitives::cl_dl_lcm::jacobi;
-use class_group::primitives::cl_dl_lcm::HSMCL;
-use class_group::primitives::cl_dl_lcm::PK;
-use class_group::primitives::cl_dl_lcm::next_probable_small_prime;
-use class_group::primitives::cl_dl_lcm::next_probable_prime;
-use class_group::bn_to_gen;
-use class_group::isprime;
-use class_group::pari_init;
-use class_group::primitives::is_prime;
-use class_group::BinaryQF;
-use class_group::primitives::numerical_log;
-use class_group::primitives::prng;
-use curv::arithmetic::traits::Modulo;
-use curv::cryptographic_primitives::hashing::traits::Hash;
-use curv::arithmetic::traits::Samplable;
-use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
-use curv::elliptic::curves::traits::{ECPoint, ECScalar};
-use curv::BigInt;
-use curv::{FE, GE};
-use std::os::raw::c_int;
-use paillier::keygen::PrimeSampable;
-use protocols::mpaillier::Pallier_AsiaCCS_19;
-use protocols::asiaccs_zk::ZK_AsiaCCS_19;
-use protocols::ggm_zk::zkPoKEncProof_v0;
-use protocols::ggm_zk::zkPoKEncProof;
-
-pub const SECURITY_PARAMETER: usize = 80;
-pub const C: usize = 1;
-
-#[derive(Debug)]
-pub struct ProofError;
-
-#[derive(Copy, PartialEq, Eq, Clone, Debug)]
-pub enum Error {
-    InvalidKey,
-    InvalidCom,
-    InvalidSig,
-    InvalidProof,
-}
-
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Response {
-    pub m2: BigInt,
-    pub r2: BigInt,
+// bld-sig: runs the blind signing protocol one step at a time, passing the
+// per-round JSON messages through files.
+//
+//   bld-sig signer keygen --key signer.key --pubkey signer.pub
+//   bld-sig signer nonce --key signer.key --backend hsmcl-ggm --state signer.state --out round1.json
+//   bld-sig user request --pubkey signer.pub --round1 round1.json --msg msg.txt --state user.state --out round2.json
+//   bld-sig signer respond --key signer.key --state signer.state --round2 round2.json --receipt receipt.json --out round3.json
+//   bld-sig user finalize --state user.state --round3 round3.json --opening opening.json --out sig.json
+//   bld-sig verify --pubkey signer.pub --msg msg.txt --sig sig.json
+//   bld-sig signer check-opening --receipt receipt.json --opening opening.json --sig sig.json
+//
+// The opening shows the signer which session a signature came from, so the
+// user sends it only once the signature is public, if at all.
+//
+// Round 1 can run committed instead, so that neither side picks K1 alone:
+//
+//   bld-sig signer nonce --committed --key signer.key --state signer.state --out commit.json
+//   bld-sig user contribute --commit commit.json --state user.committed --out contribution.json
+//   bld-sig signer reveal --state signer.state --contribution contribution.json --out reveal.json
+//   bld-sig user request --committed user.committed --reveal reveal.json --pubkey signer.pub --msg msg.txt --state user.state --out round2.json
+//
+// after which respond and finalize run as above.
+//
+// Messages are written to stdout when --out is omitted. State files hold
+// secrets and are removed once the step that consumes them has run. Key and
+// state files are created readable by the owner only.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::process;
+
+use bld_sig::digest::MessageDigest;
+use bld_sig::protocols::blind_ecdsa::session::{
+    Backend, KOpening, Round1, Round1Commit, Round1Contribution, Round1Reveal, Round2, Round3, SessionReceipt, SignerSession, UserCommitted, UserSession,
+};
+use bld_sig::protocols::blind_ecdsa::signer::Signer_SigningKey;
+use bld_sig::signature::{verify, Signature};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use curv::GE;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&data).map_err(|e| format!("{}: {}", path, e))
 }
 
+// writes to path, or to stdout without one
+fn write_json<T: Serialize>(path: Option<&str>, value: &T) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    match path {
+        Some(path) => fs::write(path, data + "\n").map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            println!("{}", data);
+            Ok(())
+        }
+    }
+}
 
-fn main() {
+// writes to path readable by the owner only, also when path already exists
+fn write_secret_json<T: Serialize>(path: &str, value: &T) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let err = |e: std::io::Error| format!("{}: {}", path, e);
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path).map_err(err)?;
+    // the mode above only applies to new files
+    #[cfg(unix)]
+    file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600)).map_err(err)?;
+    file.write_all((data + "\n").as_bytes()).map_err(err)
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn remove_file(path: &str) -> Result<(), String> {
+    fs::remove_file(path).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn digest_arg(m: &ArgMatches) -> Result<MessageDigest, String> {
+    m.value_of("digest").unwrap().parse()
+}
+
+fn signer_keygen(m: &ArgMatches) -> Result<(), String> {
+    let key = Signer_SigningKey::keygen();
+    write_json(m.value_of("pubkey"), &key.public_key)?;
+    write_secret_json(m.value_of("key").unwrap(), &key)
+}
 
-    let bitsize: usize = 2048;
-    let message = BigInt::from(1234);
-    let r = BigInt::from(1222);
-    let key = Pallier_AsiaCCS_19::keygen(bitsize);
-    let ciphertext = Pallier_AsiaCCS_19::encrypt(
-        &message,
-        &r,
-        &key.N, 
-        &key.N_square, 
-        &key.g, 
-        &key.q
-    );
-    let m_recover = Pallier_AsiaCCS_19::decrypt(
-        &ciphertext, 
-        key.clone()
-    );
-    println!("{}", m_recover);
+fn signer_nonce(m: &ArgMatches) -> Result<(), String> {
+    let key: Signer_SigningKey = read_json(m.value_of("key").unwrap())?;
+    let backend: Backend = m.value_of("backend").unwrap().parse()?;
+    if m.is_present("committed") {
+        let (session, commit) = SignerSession::start_committed(&key, backend);
+        write_secret_json(m.value_of("state").unwrap(), &session)?;
+        return write_json(m.value_of("out"), &commit);
+    }
+    let (session, round1) = SignerSession::start(&key, backend);
+    write_secret_json(m.value_of("state").unwrap(), &session)?;
+    write_json(m.value_of("out"), &round1)
+}
 
-    let proof = ZK_AsiaCCS_19::prove(
-        key.N.clone(),
-        key.N_square.clone(),
-        key.q.clone(),
-        key.g.clone(),
-        ciphertext.clone(),
-        message.clone(), 
-        r.clone(),
-    );
+// the state is kept, with k1 moved to k1 + t, for respond
+fn signer_reveal(m: &ArgMatches) -> Result<(), String> {
+    let state = m.value_of("state").unwrap();
+    let mut session: SignerSession = read_json(state)?;
+    let contribution: Round1Contribution = read_json(m.value_of("contribution").unwrap())?;
+    let reveal = session.reveal(&contribution).map_err(|e| format!("contribution rejected: {:?}", e))?;
+    write_secret_json(state, &session)?;
+    write_json(m.value_of("out"), &reveal)
+}
 
-    let flag = proof.verify();
-    assert_eq!(flag, true);
+fn signer_respond(m: &ArgMatches) -> Result<(), String> {
+    let key: Signer_SigningKey = read_json(m.value_of("key").unwrap())?;
+    let state = m.value_of("state").unwrap();
+    let session: SignerSession = read_json(state)?;
+    let round2: Round2 = read_json(m.value_of("round2").unwrap())?;
+    let receipt = session.receipt(&round2).map_err(|e| format!("request rejected: {:?}", e))?;
+    // the nonce is spent whether or not the request is valid
+    remove_file(state)?;
+    let round3 = match m.is_present("prove") {
+        true => session.respond_with_proof(&key, &round2),
+        false => session.respond(&key, &round2),
+    };
+    let round3 = round3.map_err(|e| format!("request rejected: {:?}", e))?;
+    write_json(Some(m.value_of("receipt").unwrap()), &receipt)?;
+    write_json(m.value_of("out"), &round3)
+}
 
-    println!("Hello Ecdsa!")
+fn signer_check_opening(m: &ArgMatches) -> Result<(), String> {
+    let receipt: SessionReceipt = read_json(m.value_of("receipt").unwrap())?;
+    let opening: KOpening = read_json(m.value_of("opening").unwrap())?;
+    let sig: Signature = read_json(m.value_of("sig").unwrap())?;
+    receipt.verify_opening(&opening, &sig).map_err(|e| format!("opening rejected: {:?}", e))?;
+    println!("OK");
+    Ok(())
 }
 
+fn user_contribute(m: &ArgMatches) -> Result<(), String> {
+    let commit: Round1Commit = read_json(m.value_of("commit").unwrap())?;
+    let (state, contribution) = UserCommitted::contribute(&commit);
+    write_secret_json(m.value_of("state").unwrap(), &state)?;
+    write_json(m.value_of("out"), &contribution)
+}
 
+fn user_request(m: &ArgMatches) -> Result<(), String> {
+    let public_key: GE = read_json(m.value_of("pubkey").unwrap())?;
+    let msg = read_file(m.value_of("msg").unwrap())?;
+    let bitsize = |backend: Backend| match m.value_of("bitsize") {
+        Some(b) => b.parse().map_err(|_| format!("invalid bitsize {}", b)),
+        None => Ok(backend.default_bitsize()),
+    };
+    let res = match m.value_of("reveal") {
+        Some(reveal) => {
+            let state: UserCommitted = read_json(m.value_of("committed").unwrap())?;
+            let reveal: Round1Reveal = read_json(reveal)?;
+            state.request(&public_key, &reveal, &msg, digest_arg(m)?, bitsize(reveal.round1.backend)?)
+        }
+        None => {
+            let round1: Round1 = read_json(m.value_of("round1").unwrap())?;
+            UserSession::request(&public_key, &round1, &msg, digest_arg(m)?, bitsize(round1.backend)?)
+        }
+    };
+    let (session, round2) = res.map_err(|e| format!("round 1 rejected: {:?}", e))?;
+    if let Some(committed) = m.value_of("committed") {
+        remove_file(committed)?;
+    }
+    write_secret_json(m.value_of("state").unwrap(), &session)?;
+    write_json(m.value_of("out"), &round2)
+}
 
+fn user_finalize(m: &ArgMatches) -> Result<(), String> {
+    let state = m.value_of("state").unwrap();
+    let session: UserSession = read_json(state)?;
+    let round3: Round3 = read_json(m.value_of("round3").unwrap())?;
+    let opening = session.K_opening().clone();
+    let sig = match m.is_present("require-proof") {
+        true => session.finalize_with_proof(&round3),
+        false => session.finalize(&round3),
+    };
+    let sig = sig.map_err(|e| format!("invalid signature: {:?}", e))?;
+    remove_file(state)?;
+    if let Some(path) = m.value_of("opening") {
+        write_json(Some(path), &opening)?;
+    }
+    write_json(m.value_of("out"), &sig)?;
+    if let Some(path) = m.value_of("der") {
+        let der = sig.to_der().map_err(|e| format!("invalid signature: {:?}", e))?;
+        fs::write(path, der).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(())
+}
 
+fn verify_sig(m: &ArgMatches) -> Result<(), String> {
+    let public_key: GE = read_json(m.value_of("pubkey").unwrap())?;
+    let msg = read_file(m.value_of("msg").unwrap())?;
+    let sig: Signature = read_json(m.value_of("sig").unwrap())?;
+    let h = digest_arg(m)?.hash_to_scalar(&msg);
+    verify(&public_key, &h, &sig).map_err(|_| "signature does not verify".to_string())?;
+    println!("OK");
+    Ok(())
+}
+
+fn main() {
+    let file = |name: &'static str, help: &'static str| Arg::with_name(name).long(name).value_name("FILE").help(help);
+    let digest = Arg::with_name("digest")
+        .long("digest")
+        .possible_values(&["sha256", "sha256d", "keccak256"])
+        .default_value("sha256")
+        .help("Message digest");
+
+    let matches = App::new("bld-sig")
+        .about("Blind ECDSA signing over secp256k1")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("signer")
+                .about("Signer side")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("keygen")
+                        .about("Generate the signing key")
+                        .arg(file("key", "Signing key output (secret)").required(true))
+                        .arg(file("pubkey", "Public key output")),
+                )
+                .subcommand(
+                    SubCommand::with_name("nonce")
+                        .about("Round 1: start a session and send K1")
+                        .arg(file("key", "Signing key").required(true))
+                        .arg(
+                            Arg::with_name("backend")
+                                .long("backend")
+                                .possible_values(&["mpaillier", "hsmcl", "hsmcl-ggm"])
+                                .default_value("hsmcl-ggm")
+                                .help("Encryption scheme for the request"),
+                        )
+                        .arg(file("state", "Session state output (secret)").required(true))
+                        .arg(file("out", "Round 1 message output, or the commitment with --committed"))
+                        .arg(Arg::with_name("committed").long("committed").help("Commit to K1 and open it after the user's contribution")),
+                )
+                .subcommand(
+                    SubCommand::with_name("reveal")
+                        .about("Committed round 1: open the commitment to K1")
+                        .arg(file("state", "Session state, updated").required(true))
+                        .arg(file("contribution", "User's contribution").required(true))
+                        .arg(file("out", "Opening output")),
+                )
+                .subcommand(
+                    SubCommand::with_name("respond")
+                        .about("Round 3: check the request and send the partial signature")
+                        .arg(file("key", "Signing key").required(true))
+                        .arg(file("state", "Session state, removed afterwards").required(true))
+                        .arg(file("round2", "Round 2 message").required(true))
+                        .arg(file("receipt", "Session receipt output, to check the user's opening with").required(true))
+                        .arg(file("out", "Round 3 message output"))
+                        .arg(Arg::with_name("prove").long("prove").help("Prove that the answer was computed as prescribed")),
+                )
+                .subcommand(
+                    SubCommand::with_name("check-opening")
+                        .about("Check that a published signature came from an answered session")
+                        .arg(file("receipt", "Session receipt").required(true))
+                        .arg(file("opening", "User's opening").required(true))
+                        .arg(file("sig", "Signature").required(true)),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("user")
+                .about("User side")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("contribute")
+                        .about("Committed round 1: answer the signer's commitment with fresh randomness")
+                        .arg(file("commit", "Signer's commitment").required(true))
+                        .arg(file("state", "Contribution state output").required(true))
+                        .arg(file("out", "Contribution output")),
+                )
+                .subcommand(
+                    SubCommand::with_name("request")
+                        .about("Round 2: blind the message and send the encrypted request")
+                        .arg(file("pubkey", "Signer's public key").required(true))
+                        .arg(file("round1", "Round 1 message").required_unless("reveal"))
+                        .arg(file("reveal", "Signer's opening, after a committed round 1").conflicts_with("round1").requires("committed"))
+                        .arg(file("committed", "Contribution state, removed afterwards").requires("reveal"))
+                        .arg(file("msg", "Message to sign").required(true))
+                        .arg(digest.clone())
+                        .arg(
+                            Arg::with_name("bitsize")
+                                .long("bitsize")
+                                .value_name("BITS")
+                                .help("Paillier modulus or HSM-CL discriminant size"),
+                        )
+                        .arg(file("state", "Session state output (secret)").required(true))
+                        .arg(file("out", "Round 2 message output")),
+                )
+                .subcommand(
+                    SubCommand::with_name("finalize")
+                        .about("Unblind the partial signature into (r, s)")
+                        .arg(file("state", "Session state, removed afterwards").required(true))
+                        .arg(file("round3", "Round 3 message").required(true))
+                        .arg(file("out", "Signature output"))
+                        .arg(file("der", "DER encoded signature output"))
+                        .arg(file("opening", "Opening output, to send once the signature is public"))
+                        .arg(
+                            Arg::with_name("require-proof")
+                                .long("require-proof")
+                                .help("Reject an answer without the signer's evaluation proof"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("verify")
+                .about("Verify a signature")
+                .arg(file("pubkey", "Signer's public key").required(true))
+                .arg(file("msg", "Signed message").required(true))
+                .arg(file("sig", "Signature").required(true))
+                .arg(digest),
+        )
+        .get_matches();
+
+    let result = match matches.subcommand() {
+        ("signer", Some(m)) => match m.subcommand() {
+            ("keygen", Some(m)) => signer_keygen(m),
+            ("nonce", Some(m)) => signer_nonce(m),
+            ("reveal", Some(m)) => signer_reveal(m),
+            ("respond", Some(m)) => signer_respond(m),
+            ("check-opening", Some(m)) => signer_check_opening(m),
+            _ => unreachable!(),
+        },
+        ("user", Some(m)) => match m.subcommand() {
+            ("contribute", Some(m)) => user_contribute(m),
+            ("request", Some(m)) => user_request(m),
+            ("finalize", Some(m)) => user_finalize(m),
+            _ => unreachable!(),
+        },
+        ("verify", Some(m)) => verify_sig(m),
+        _ => unreachable!(),
+    };
+    if let Err(e) = result {
+        eprintln!("bld-sig: {}", e);
+        process::exit(1);
+    }
+}
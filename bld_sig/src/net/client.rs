@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use curv::GE;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use crate::*;
+use digest::MessageDigest;
+use protocols::blind_ecdsa::session::{Backend, KOpening, Round1, Round1Commit, Round1Contribution, Round1Reveal, Round2, Round3, UserCommitted};
+use signature::Signature;
+use super::frame::{read_frame, write_frame};
+use super::{ClientMsg, NetError, ServerMsg};
+
+// The user side of a session with a signer service. sign() runs all rounds;
+// start() and finish() are the two exchanges it is made of, or commit(),
+// contribute() and finish() with a committed round 1.
+pub struct Client {
+    stream: TcpStream,
+    io_timeout: Duration, // covers the signer's proof checks
+    committed: bool,      // sign() runs the committed round 1
+}
+
+impl Client {
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, NetError> {
+        Ok(Client {
+            stream: TcpStream::connect(addr).await?,
+            io_timeout: Duration::from_secs(60),
+            committed: false,
+        })
+    }
+
+    pub fn set_io_timeout(&mut self, io_timeout: Duration) {
+        self.io_timeout = io_timeout;
+    }
+
+    pub fn set_committed(&mut self, committed: bool) {
+        self.committed = committed;
+    }
+
+    // only for display: the key a signature is checked against must come
+    // from a trusted source, not from the signer being asked
+    pub async fn public_key(&mut self) -> Result<GE, NetError> {
+        match self.exchange(&ClientMsg::PublicKey).await? {
+            ServerMsg::PublicKey(pk) => Ok(pk),
+            _ => Err(NetError::Unexpected),
+        }
+    }
+
+    pub async fn start(&mut self, backend: Backend) -> Result<Round1, NetError> {
+        match self.exchange(&ClientMsg::Start(backend)).await? {
+            ServerMsg::Round1(round1) => Ok(round1),
+            _ => Err(NetError::Unexpected),
+        }
+    }
+
+    pub async fn commit(&mut self, backend: Backend) -> Result<Round1Commit, NetError> {
+        match self.exchange(&ClientMsg::Commit(backend)).await? {
+            ServerMsg::Round1Commit(commit) => Ok(commit),
+            _ => Err(NetError::Unexpected),
+        }
+    }
+
+    pub async fn contribute(&mut self, contribution: Round1Contribution) -> Result<Round1Reveal, NetError> {
+        match self.exchange(&ClientMsg::Contribute(contribution)).await? {
+            ServerMsg::Round1Reveal(reveal) => Ok(reveal),
+            _ => Err(NetError::Unexpected),
+        }
+    }
+
+    pub async fn finish(&mut self, round2: Round2) -> Result<Round3, NetError> {
+        match self.exchange(&ClientMsg::Sign(round2)).await? {
+            ServerMsg::Round3(round3) => Ok(round3),
+            _ => Err(NetError::Unexpected),
+        }
+    }
+
+    // shows the signer that sig came from the opening's session; only once
+    // sig is public, as it links the two
+    pub async fn open(&mut self, opening: KOpening, sig: Signature) -> Result<(), NetError> {
+        match self.exchange(&ClientMsg::Open(opening, sig)).await? {
+            ServerMsg::Opened => Ok(()),
+            _ => Err(NetError::Unexpected),
+        }
+    }
+
+    // a signature on msg valid under public_key, which the signer never sees
+    pub async fn sign(
+        &mut self,
+        public_key: &GE,
+        msg: &[u8],
+        digest: MessageDigest,
+        backend: Backend,
+        bitsize: usize,
+    ) -> Result<Signature, NetError> {
+        let (sig, _) = self.sign_with_opening(public_key, msg, digest, backend, bitsize).await?;
+        Ok(sig)
+    }
+
+    // as sign, also returning the opening for a later open()
+    pub async fn sign_with_opening(
+        &mut self,
+        public_key: &GE,
+        msg: &[u8],
+        digest: MessageDigest,
+        backend: Backend,
+        bitsize: usize,
+    ) -> Result<(Signature, KOpening), NetError> {
+        let cancel = CancellationToken::new(); // dropping the future cancels
+        let (session, round2) = match self.committed {
+            true => {
+                let commit = self.commit(backend).await?;
+                let (state, contribution) = UserCommitted::contribute(&commit);
+                let reveal = self.contribute(contribution).await?;
+                nonblocking::request_committed(state, public_key, &reveal, msg, digest, bitsize, &cancel).await?
+            }
+            false => {
+                let round1 = self.start(backend).await?;
+                nonblocking::request(public_key, &round1, msg, digest, bitsize, &cancel).await?
+            }
+        };
+        let opening = session.K_opening().clone();
+        let round3 = self.finish(round2).await?;
+        let sig = nonblocking::finalize(session, &round3, &cancel).await?;
+        Ok((sig, opening))
+    }
+
+    async fn exchange(&mut self, msg: &ClientMsg) -> Result<ServerMsg, NetError> {
+        write_frame(&mut self.stream, msg).await?;
+        let reply = match time::timeout(self.io_timeout, read_frame(&mut self.stream)).await {
+            Err(_) => return Err(NetError::Timeout),
+            Ok(frame) => frame?,
+        };
+        match reply {
+            Some(ServerMsg::Error(e)) => Err(NetError::Server(e)),
+            Some(reply) => Ok(reply),
+            None => Err(NetError::Io(std::io::ErrorKind::UnexpectedEof.into())),
+        }
+    }
+}
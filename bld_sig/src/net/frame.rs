@@ -0,0 +1,43 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use super::NetError;
+
+// A step-2 request with HSM-CL proofs is a few hundred kilobytes, far below this
+pub const MAX_FRAME_LEN: usize = 16 << 20;
+
+pub async fn write_frame<W, T>(w: &mut W, msg: &T) -> Result<(), NetError>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let data = serde_json::to_vec(msg)?;
+    if data.len() > MAX_FRAME_LEN {
+        return Err(NetError::FrameTooLarge(data.len()));
+    }
+    w.write_all(&(data.len() as u32).to_be_bytes()).await?;
+    w.write_all(&data).await?;
+    w.flush().await?;
+    Ok(())
+}
+
+// None on a clean end of stream before the length
+pub async fn read_frame<R, T>(r: &mut R) -> Result<Option<T>, NetError>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut len = [0u8; 4];
+    match r.read_exact(&mut len).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(NetError::FrameTooLarge(len));
+    }
+    let mut data = vec![0u8; len];
+    r.read_exact(&mut data).await?;
+    Ok(Some(serde_json::from_slice(&data)?))
+}
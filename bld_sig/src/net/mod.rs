@@ -0,0 +1,90 @@
+use std::fmt;
+use std::io;
+
+use curv::GE;
+use serde::{Deserialize, Serialize};
+use crate::*;
+use nonblocking::AsyncError;
+use protocols::blind_ecdsa::session::{Backend, KOpening, Round1, Round1Commit, Round1Contribution, Round1Reveal, Round2, Round3};
+use signature::Signature;
+
+pub mod client;
+pub mod frame;
+pub mod server;
+
+// The signer as a long-lived service. Client and server exchange JSON
+// messages over TCP, each framed by its length as a 4-byte big endian
+// integer. A session is started with Start and finished with Sign, on the
+// same or on another connection; the server keys sessions by session id.
+// Commit and Contribute run the committed round 1 instead of Start: the
+// server answers Commit with its commitment to K1 and Contribute with the
+// opening, after which Sign goes on as before.
+// Once the signature is public the user may send Open, which the server
+// checks against the receipt it kept when answering Sign.
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ClientMsg {
+    PublicKey,
+    Start(Backend),
+    Commit(Backend),
+    Contribute(Round1Contribution),
+    Sign(Round2),
+    Open(KOpening, Signature),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ServerMsg {
+    PublicKey(GE),
+    Round1(Round1),
+    Round1Commit(Round1Commit),
+    Round1Reveal(Round1Reveal),
+    Round3(Round3),
+    Opened, // the signature came from the session of the opening
+    Error(String),
+}
+
+#[derive(Debug)]
+pub enum NetError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    FrameTooLarge(usize),
+    Timeout,
+    Server(String),     // the server rejected the message
+    Unexpected,         // a reply of the wrong kind
+    Protocol(Error),    // a step of the session failed
+}
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NetError::Io(e) => write!(f, "io: {}", e),
+            NetError::Json(e) => write!(f, "json: {}", e),
+            NetError::FrameTooLarge(len) => write!(f, "frame of {} bytes is too large", len),
+            NetError::Timeout => write!(f, "timed out"),
+            NetError::Server(e) => write!(f, "server: {}", e),
+            NetError::Unexpected => write!(f, "unexpected reply"),
+            NetError::Protocol(e) => write!(f, "protocol: {:?}", e),
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(e: io::Error) -> Self {
+        NetError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for NetError {
+    fn from(e: serde_json::Error) -> Self {
+        NetError::Json(e)
+    }
+}
+
+impl From<AsyncError> for NetError {
+    fn from(e: AsyncError) -> Self {
+        match e {
+            AsyncError::Protocol(e) => NetError::Protocol(e),
+            e => NetError::Io(io::Error::new(io::ErrorKind::Other, e.to_string())),
+        }
+    }
+}
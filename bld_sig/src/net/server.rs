@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use crate::*;
+use nonblocking::AsyncError;
+use protocols::blind_ecdsa::session::{Backend, KOpening, Round1Contribution, Round2, SessionReceipt, SignerSession};
+use protocols::blind_ecdsa::signer::Signer_SigningKey;
+use signature::Signature;
+use super::frame::{read_frame, write_frame};
+use super::{ClientMsg, NetError, ServerMsg};
+
+// lower bound on the period of the expiry sweep, which is half the session
+// timeout, so that a zero timeout does not make it spin (or panic)
+const MIN_SWEEP_INTERVAL: Duration = Duration::from_millis(10);
+
+// pause after a failed accept, e.g. when out of file descriptors
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub session_timeout: Duration, // sessions not finished by then are dropped
+    pub io_timeout: Duration,      // idle connections are closed after this
+    pub max_sessions: usize,
+    pub receipt_timeout: Duration, // receipts not opened by then are dropped
+    pub require_committed: bool,   // refuse Start, only Commit opens a session
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            session_timeout: Duration::from_secs(300),
+            io_timeout: Duration::from_secs(60),
+            max_sessions: 10000,
+            receipt_timeout: Duration::from_secs(7 * 24 * 3600),
+            require_committed: false,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Server {
+    key: Arc<Signer_SigningKey>,
+    sessions: Arc<Mutex<HashMap<String, (SignerSession, Instant)>>>,
+    receipts: Arc<Mutex<HashMap<String, (SessionReceipt, Instant)>>>, // of answered sessions
+    config: ServerConfig,
+}
+
+impl Server {
+    pub fn new(key: Signer_SigningKey, config: ServerConfig) -> Self {
+        Server {
+            key: Arc::new(key),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            receipts: Arc::new(Mutex::new(HashMap::new())),
+            config,
+        }
+    }
+
+    // number of open sessions
+    pub fn session_count(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+
+    // number of answered sessions whose opening has not arrived yet
+    pub fn receipt_count(&self) -> usize {
+        self.receipts.lock().unwrap().len()
+    }
+
+    // accepts connections until the task is dropped. Failed accepts go to
+    // on_accept_error and are retried, they do not stop the service.
+    pub async fn serve<F>(self, listener: TcpListener, on_accept_error: F)
+    where
+        F: Fn(&io::Error) + Send + 'static,
+    {
+        let reaper = self.clone();
+        tokio::spawn(async move {
+            let mut interval = time::interval((reaper.config.session_timeout / 2).max(MIN_SWEEP_INTERVAL));
+            loop {
+                interval.tick().await;
+                reaper.drop_expired();
+            }
+        });
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    on_accept_error(&e);
+                    time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let server = self.clone();
+            tokio::spawn(async move {
+                // a broken connection only ends that connection
+                let _ = server.handle_connection(stream).await;
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> Result<(), NetError> {
+        loop {
+            let msg: ClientMsg = match time::timeout(self.config.io_timeout, read_frame(&mut stream)).await {
+                Err(_) => return Err(NetError::Timeout),
+                Ok(frame) => match frame? {
+                    Some(msg) => msg,
+                    None => return Ok(()),
+                },
+            };
+            let reply = self.handle(msg).await;
+            write_frame(&mut stream, &reply).await?;
+        }
+    }
+
+    pub async fn handle(&self, msg: ClientMsg) -> ServerMsg {
+        match msg {
+            ClientMsg::PublicKey => ServerMsg::PublicKey(self.key.public_key.clone()),
+            ClientMsg::Start(backend) => self.start(backend, false),
+            ClientMsg::Commit(backend) => self.start(backend, true),
+            ClientMsg::Contribute(contribution) => self.contribute(&contribution),
+            ClientMsg::Sign(round2) => self.sign(round2).await,
+            ClientMsg::Open(opening, sig) => self.open(&opening, &sig),
+        }
+    }
+
+    fn start(&self, backend: Backend, committed: bool) -> ServerMsg {
+        if !committed && self.config.require_committed {
+            return ServerMsg::Error("committed round 1 required".to_string());
+        }
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.len() >= self.config.max_sessions {
+            return ServerMsg::Error("too many open sessions".to_string());
+        }
+        match committed {
+            true => {
+                let (session, commit) = SignerSession::start_committed(&self.key, backend);
+                sessions.insert(commit.session_id.clone(), (session, Instant::now()));
+                ServerMsg::Round1Commit(commit)
+            }
+            false => {
+                let (session, round1) = SignerSession::start(&self.key, backend);
+                sessions.insert(round1.session_id.clone(), (session, Instant::now()));
+                ServerMsg::Round1(round1)
+            }
+        }
+    }
+
+    // the session stays open for Sign; reveal() takes one contribution only
+    fn contribute(&self, contribution: &Round1Contribution) -> ServerMsg {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = match sessions.get_mut(&contribution.session_id) {
+            Some((session, started)) if started.elapsed() < self.config.session_timeout => session,
+            _ => return ServerMsg::Error("unknown or expired session".to_string()),
+        };
+        match session.reveal(contribution) {
+            Ok(reveal) => ServerMsg::Round1Reveal(reveal),
+            Err(e) => ServerMsg::Error(format!("contribution rejected: {:?}", e)),
+        }
+    }
+
+    async fn sign(&self, round2: Round2) -> ServerMsg {
+        // the session is removed whether or not the request is valid, k1 is spent
+        let session = self.sessions.lock().unwrap().remove(&round2.session_id);
+        let session = match session {
+            Some((session, started)) if started.elapsed() < self.config.session_timeout => session,
+            _ => return ServerMsg::Error("unknown or expired session".to_string()),
+        };
+        let receipt = match session.receipt(&round2) {
+            Ok(receipt) => receipt,
+            Err(e) => return ServerMsg::Error(format!("request rejected: {:?}", e)),
+        };
+        match nonblocking::respond(session, &self.key, &round2, &CancellationToken::new()).await {
+            Ok(round3) => {
+                self.receipts.lock().unwrap().insert(receipt.session_id.clone(), (receipt, Instant::now()));
+                ServerMsg::Round3(round3)
+            }
+            Err(AsyncError::Protocol(e)) => ServerMsg::Error(format!("request rejected: {:?}", e)),
+            Err(e) => ServerMsg::Error(e.to_string()),
+        }
+    }
+
+    // a receipt is spent by a valid opening only, so that anyone who learns
+    // the session id cannot discard it with a bad one
+    fn open(&self, opening: &KOpening, sig: &Signature) -> ServerMsg {
+        let mut receipts = self.receipts.lock().unwrap();
+        let res = match receipts.get(&opening.session_id) {
+            Some((receipt, _)) => receipt.verify_opening(opening, sig),
+            None => return ServerMsg::Error("unknown or expired receipt".to_string()),
+        };
+        match res {
+            Ok(()) => {
+                receipts.remove(&opening.session_id);
+                ServerMsg::Opened
+            }
+            Err(e) => ServerMsg::Error(format!("opening rejected: {:?}", e)),
+        }
+    }
+
+    fn drop_expired(&self) {
+        let timeout = self.config.session_timeout;
+        self.sessions.lock().unwrap().retain(|_, (_, started)| started.elapsed() < timeout);
+        let timeout = self.config.receipt_timeout;
+        self.receipts.lock().unwrap().retain(|_, (_, answered)| answered.elapsed() < timeout);
+    }
+}
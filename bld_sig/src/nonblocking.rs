@@ -0,0 +1,111 @@
+use std::fmt;
+
+use curv::GE;
+use tokio_util::sync::CancellationToken;
+use crate::*;
+use digest::MessageDigest;
+use protocols::blind_ecdsa::session::{Round1, Round1Reveal, Round2, Round3, SignerSession, UserCommitted, UserSession};
+use protocols::blind_ecdsa::signer::Signer_SigningKey;
+use signature::Signature;
+
+// Async versions of the session steps for use inside a tokio runtime. Key
+// generation, proving and proof checking take from tens of milliseconds to
+// seconds, so each step runs on tokio's blocking pool instead of the reactor.
+// Class group work inside a step holds the PARI lock (see pari.rs), so HSM-CL
+// steps take turns while modified Paillier steps run in parallel.
+//
+// Every step takes a CancellationToken. Once it is cancelled the step returns
+// Cancelled at once; work already running on the pool cannot be interrupted
+// and finishes in the background, its result discarded. Dropping the future
+// has the same effect. A cancelled step still consumes its session.
+
+#[derive(Debug, PartialEq)]
+pub enum AsyncError {
+    Cancelled,
+    Panicked,
+    Protocol(Error),
+}
+
+impl fmt::Display for AsyncError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsyncError::Cancelled => write!(f, "cancelled"),
+            AsyncError::Panicked => write!(f, "blocking task panicked"),
+            AsyncError::Protocol(e) => write!(f, "protocol: {:?}", e),
+        }
+    }
+}
+
+// runs f on the blocking pool, or returns Cancelled as soon as cancel fires
+pub async fn run_blocking<F, T>(f: F, cancel: &CancellationToken) -> Result<T, AsyncError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    if cancel.is_cancelled() {
+        return Err(AsyncError::Cancelled);
+    }
+    let task = tokio::task::spawn_blocking(f);
+    tokio::select! {
+        _ = cancel.cancelled() => Err(AsyncError::Cancelled),
+        res = task => res.map_err(|_| AsyncError::Panicked),
+    }
+}
+
+// user, round 2: check K1, encrypt and prove
+pub async fn request(
+    public_key: &GE,
+    round1: &Round1,
+    msg: &[u8],
+    digest: MessageDigest,
+    bitsize: usize,
+    cancel: &CancellationToken,
+) -> Result<(UserSession, Round2), AsyncError> {
+    let public_key = public_key.clone();
+    let round1 = round1.clone();
+    let msg = msg.to_vec();
+    run_blocking(move || UserSession::request(&public_key, &round1, &msg, digest, bitsize), cancel)
+        .await?
+        .map_err(AsyncError::Protocol)
+}
+
+// user, round 2 after a committed round 1: check the opening and K1, then
+// as request on K1'
+pub async fn request_committed(
+    state: UserCommitted,
+    public_key: &GE,
+    reveal: &Round1Reveal,
+    msg: &[u8],
+    digest: MessageDigest,
+    bitsize: usize,
+    cancel: &CancellationToken,
+) -> Result<(UserSession, Round2), AsyncError> {
+    let public_key = public_key.clone();
+    let reveal = reveal.clone();
+    let msg = msg.to_vec();
+    run_blocking(move || state.request(&public_key, &reveal, &msg, digest, bitsize), cancel)
+        .await?
+        .map_err(AsyncError::Protocol)
+}
+
+// signer, round 3: check the request and evaluate
+pub async fn respond(
+    session: SignerSession,
+    key: &Signer_SigningKey,
+    round2: &Round2,
+    cancel: &CancellationToken,
+) -> Result<Round3, AsyncError> {
+    let key = key.clone();
+    let round2 = round2.clone();
+    run_blocking(move || session.respond(&key, &round2), cancel)
+        .await?
+        .map_err(AsyncError::Protocol)
+}
+
+// user: decrypt, unblind and check the signature
+pub async fn finalize(session: UserSession, round3: &Round3, cancel: &CancellationToken) -> Result<Signature, AsyncError> {
+    let round3 = round3.clone();
+    run_blocking(move || session.finalize(&round3), cancel)
+        .await?
+        .map_err(AsyncError::Protocol)
+}
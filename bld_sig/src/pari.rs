@@ -0,0 +1,118 @@
+use std::cell::Cell;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, Once};
+
+use class_group::pari_init;
+
+// PARI, which class_group uses for the class group arithmetic, keeps one
+// global stack. It is set up here once per process, on first use, with the
+// stack size given to configure() or DEFAULT_STACK_SIZE. PARI is not thread
+// safe either, so every entry point of this crate that runs class group code
+// starts with enter(), which starts PARI and holds lock() until it returns.
+// The lock nests on one thread, so entry points can call each other. Code
+// that uses class_group directly, or runs the SigmaProtocol rounds of the
+// class group proofs by hand, takes lock() itself.
+
+// enough for the 1348-bit discriminants of the HSM-CL keys and proofs
+pub const DEFAULT_STACK_SIZE: usize = 100_000_000;
+pub const MIN_STACK_SIZE: usize = 1_000_000;
+
+// primes precomputed by PARI, only small ones are needed
+const MAX_PRIME: u64 = 2;
+
+#[derive(Copy, PartialEq, Eq, Clone, Debug)]
+pub enum PariError {
+    StackTooSmall(usize),
+    AlreadyInitialized(usize), // with this stack size
+    InitFailed,                // an earlier start panicked
+}
+
+static INIT: Once = Once::new();
+static STACK_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_STACK_SIZE);
+static CONFIG: Mutex<()> = Mutex::new(()); // orders configure() against the first init()
+static LOCK: Mutex<()> = Mutex::new(());
+
+thread_local! {
+    // number of PariGuards alive on this thread
+    static DEPTH: Cell<usize> = Cell::new(0);
+}
+
+// sets the stack size; fails once PARI is running with another size
+pub fn configure(stack_size: usize) -> Result<(), PariError> {
+    if stack_size < MIN_STACK_SIZE {
+        return Err(PariError::StackTooSmall(stack_size));
+    }
+    let _config = CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+    if INIT.is_completed() {
+        let current = STACK_SIZE.load(Ordering::SeqCst);
+        return match current == stack_size {
+            true => Ok(()),
+            false => Err(PariError::AlreadyInitialized(current)),
+        };
+    }
+    STACK_SIZE.store(stack_size, Ordering::SeqCst);
+    Ok(())
+}
+
+// starts PARI if it is not running yet; cheap after the first call
+pub fn init() -> Result<(), PariError> {
+    if INIT.is_completed() {
+        return Ok(());
+    }
+    // a start that panicked poisons INIT, after which call_once panics too
+    let started = panic::catch_unwind(|| {
+        INIT.call_once(|| {
+            let _config = CONFIG.lock().unwrap_or_else(|e| e.into_inner());
+            let stack_size = STACK_SIZE.load(Ordering::SeqCst);
+            // only reached once, before any other PARI call of this crate
+            unsafe { pari_init(stack_size, MAX_PRIME as _) };
+        })
+    });
+    started.map_err(|_| PariError::InitFailed)
+}
+
+pub fn is_initialized() -> bool {
+    INIT.is_completed()
+}
+
+// stack size PARI runs with, or will run with
+pub fn stack_size() -> usize {
+    STACK_SIZE.load(Ordering::SeqCst)
+}
+
+// Held while class group code runs. Only the outermost guard of a thread
+// holds LOCK, the ones inside it just count.
+pub struct PariGuard {
+    _lock: Option<MutexGuard<'static, ()>>,
+}
+
+impl Drop for PariGuard {
+    fn drop(&mut self) {
+        DEPTH.with(|d| d.set(d.get() - 1));
+    }
+}
+
+// serializes class group work across threads
+pub fn lock() -> PariGuard {
+    let depth = DEPTH.with(|d| d.get());
+    let lock = match depth {
+        0 => Some(LOCK.lock().unwrap_or_else(|e| e.into_inner())),
+        _ => None,
+    };
+    DEPTH.with(|d| d.set(depth + 1));
+    PariGuard { _lock: lock }
+}
+
+// init() and lock(), the first step of every class group entry point
+pub fn enter() -> Result<PariGuard, PariError> {
+    init()?;
+    Ok(lock())
+}
+
+// enter() for entry points without an error to return, such as key
+// generation and proving. It only fails after an earlier start panicked.
+// Entry points that return a Result use enter() and Error::Pari instead.
+pub(crate) fn enter_or_panic() -> PariGuard {
+    enter().expect("PARI could not be started")
+}
@@ -1,9 +1,13 @@
 use crate::*;
 use serde::{Deserialize, Serialize};
-use curv::cryptographic_primitives::hashing::traits::Hash;
 use curv::arithmetic::traits::Samplable;
-use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
 use curv::BigInt;
+use protocols::batch::{batch_weight, bisect, group_by_key};
+use protocols::multiexp::multi_exp_mod;
+use protocols::precomp::PrecomputedPaillier;
+use protocols::rng::{os_rng, sample_below};
+use protocols::sigma::{fs_challenge, fs_prove, fs_verify, Repeat, SigmaProtocol};
+use rand::{CryptoRng, RngCore};
 
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,97 +30,194 @@ impl ZK_AsiaCCS_19{
             m: BigInt,     
             r: BigInt, 
         ) -> Self{
-        unsafe { pari_init(10000000000, 2) };
-        let repeat = SECURITY_PARAMETER / C;
-        let C1_and_fs_and_m2_r2_vec = (0..repeat)
-            .map(|_| {
-                let m1 = BigInt::sample_below(&q);
-                let r1 = BigInt::sample_below(&N_square);
-                let gm1 = g.powm(&m1, &N_square);
-                let r1N = r1.powm(&N, &N_square);
-                let gm1r1N = &gm1 * &r1N;
-                let C1 = gm1r1N.mod_floor(&N_square); // C'
-                // fiat-shamir transform
-                let fs = HSha256::create_hash(&[
-                    &C1,
-                ]);
-                (C1, fs, m1, r1)
-            })
-            .collect::<Vec<(BigInt, BigInt, BigInt, BigInt)>>();
-
-        let C1_vec = (0..repeat)
-            .map(|i| C1_and_fs_and_m2_r2_vec[i].0.clone())
-            .collect::<Vec<BigInt>>();
-        let fiat_shamir_vec = (0..repeat)
-            .map(|i| &C1_and_fs_and_m2_r2_vec[i].1)
-            .collect::<Vec<&BigInt>>();
-        let m1_vec = (0..repeat)
-            .map(|i| C1_and_fs_and_m2_r2_vec[i].2.clone())
-            .collect::<Vec<BigInt>>();
-        let r1_vec = (0..repeat)
-            .map(|i| C1_and_fs_and_m2_r2_vec[i].3.clone())
-            .collect::<Vec<BigInt>>();
-
-        // using Fiat Shamir transform
-        let k = HSha256::create_hash(&fiat_shamir_vec);
-        let two: i32 = 2;
-        let two_pow_ten = two.pow(C as u32);
-        let ten_1_bits_string = BigInt::from(two_pow_ten - 1);
-        
-        let m2r2_vec = (0..repeat)
-            .map(|i| {        
-                let k_slice_i = (k.clone() >> (i * C)) & ten_1_bits_string.clone(); // output 0 or 1
-                let m2_ = &m1_vec[i] + &k_slice_i * &m;
-                let r2_ = &r1_vec[i] * &r.powm(&k_slice_i, &N_square);
-                let m2 = m2_.mod_floor(&q);
-                let r2 = r2_.mod_floor(&N_square);
-                Response { m2, r2 }
-            })
-            .collect::<Vec<Response>>();
+        let pp = PrecomputedPaillier::plain(&N, &N_square, &g, &q);
+        Self::prove_precomputed(&pp, ciphertext, m, r)
+    }
+
+    // g^m1 is read from the fixed-base table of pp
+    pub fn prove_precomputed(
+            pp: &PrecomputedPaillier,
+            ciphertext: BigInt, 
+            m: BigInt,     
+            r: BigInt, 
+        ) -> Self{
+        Self::prove_precomputed_with_rng(pp, ciphertext, m, r, &mut os_rng())
+    }
+
+    pub fn prove_precomputed_with_rng<R: RngCore + CryptoRng>(
+            pp: &PrecomputedPaillier,
+            ciphertext: BigInt, 
+            m: BigInt,     
+            r: BigInt, 
+            rng: &mut R,
+        ) -> Self{
+        let rounds = Self::sigma(pp, &ciphertext);
+        let (C1_vec, m2r2_vec) = fs_prove(&rounds, &(m, r), rng);
 
         ZK_AsiaCCS_19{
             C: ciphertext,
-            N: N,
-            N_square: N_square,
-            g: g,
+            N: pp.N.clone(),
+            N_square: pp.N_square.clone(),
+            g: pp.g.clone(),
             C1_vec: C1_vec,
             Response_vec: m2r2_vec,
         }
     }
 
     pub fn verify(&self) -> bool {
-        let mut flag = true;
-        unsafe { pari_init(10000000000, 2) };
+        let pp = PrecomputedPaillier::plain(&self.N, &self.N_square, &self.g, &FE::q());
+        self.verify_precomputed(&pp)
+    }
+
+    // g^m2 is read from the fixed-base table of pp, which must be built for (N, g)
+    pub fn verify_precomputed(&self, pp: &PrecomputedPaillier) -> bool {
+        if !pp.matches(&self.N, &self.g) {
+            return false;
+        }
+        if !self.well_formed() {
+            return false;
+        }
         
-        // reconstruct k
+        fs_verify(&Self::sigma(pp, &self.C), &self.C1_vec, &self.Response_vec)
+    }
+
+    // Verifies many proofs at once, returns the indices of the invalid proofs on failure
+    pub fn verify_batch(proofs: &[&ZK_AsiaCCS_19]) -> Result<(), Vec<usize>> {
+        Self::verify_batch_with_rng(proofs, &mut os_rng())
+    }
+
+    // rng draws the weights
+    pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(proofs: &[&ZK_AsiaCCS_19], rng: &mut R) -> Result<(), Vec<usize>> {
+        bisect(proofs, &mut |subset: &[&ZK_AsiaCCS_19]| Self::batch_check(subset, rng))
+    }
+
+    // All rounds of all proofs under the same (N, g) are combined with random odd weights rho:
+    // prod C'^rho * prod C^(sum rho * b) == g^(sum rho * m2) * (prod r2^rho)^N mod N^2
+    fn batch_check<R: RngCore + CryptoRng>(proofs: &[&ZK_AsiaCCS_19], rng: &mut R) -> bool {
         let repeat = SECURITY_PARAMETER / C;
-        let fs_vec = (0..repeat)
-            .map(|i| HSha256::create_hash(&[&self.C1_vec[i]]))
-            .collect::<Vec<BigInt>>();
-        let fs_t_vec = (0..repeat)
-            .map(|i| &fs_vec[i])
-            .collect::<Vec<&BigInt>>();
-        let mut flag = true;
-        let k = HSha256::create_hash(&fs_t_vec[..]);
-
-        let two: i32 = 2;
-        let two_pow_ten = two.pow(C as u32);
-        let ten_1_bits_string = BigInt::from(two_pow_ten - 1);
-        for i in 0..repeat {
-            let k_slice_i = (k.clone() >> (i * C)) & ten_1_bits_string.clone();
-            let Cb = self.C.powm(&k_slice_i, &self.N_square);
-            let CbC1 = &Cb * &self.C1_vec[i]; // C^b * C'
-            let eq_left = CbC1.mod_floor(&self.N_square);
-            let gm2 = &self.g.powm(&self.Response_vec[i].m2, &self.N_square);
-            let r2N = &self.Response_vec[i].r2.powm(&self.N, &self.N_square);
-            let gm2r2N = gm2 * r2N;
-            let eq_right = gm2r2N.mod_floor(&self.N_square);
-
-            if &eq_left != &eq_right {
-                flag = false;
-            };
-            assert_eq!(flag, true);
+        for group in group_by_key(proofs, |p| (p.N.clone(), p.g.clone())) {
+            let N = &proofs[group[0]].N;
+            let N_square = &proofs[group[0]].N_square;
+            let g = &proofs[group[0]].g;
+            let mut left_bases: Vec<&BigInt> = Vec::new();
+            let mut left_exps: Vec<BigInt> = Vec::new();
+            let mut r2_bases: Vec<&BigInt> = Vec::new();
+            let mut r2_exps: Vec<BigInt> = Vec::new();
+            let mut g_exp = BigInt::zero();
+            for &j in group.iter() {
+                let proof = proofs[j];
+                if &proof.N_square != N_square || !proof.well_formed() {
+                    return false;
+                }
+                let pp = PrecomputedPaillier::plain(N, N_square, g, &FE::q());
+                let rounds = Self::sigma(&pp, &proof.C);
+                let k = fs_challenge(&rounds, &proof.C1_vec);
+                let mut C_exp = BigInt::zero();
+                for i in 0..repeat {
+                    let k_slice_i = rounds.round_challenge(&k, i);
+                    let rho = batch_weight(rng);
+                    C_exp = C_exp + &rho * &k_slice_i;
+                    g_exp = g_exp + &rho * &proof.Response_vec[i].m2;
+                    left_bases.push(&proof.C1_vec[i]);
+                    left_exps.push(rho.clone());
+                    r2_bases.push(&proof.Response_vec[i].r2);
+                    r2_exps.push(rho);
+                }
+                left_bases.push(&proof.C);
+                left_exps.push(C_exp);
+            }
+
+            // all exponents are non-negative, so multi_exp_mod never returns None
+            let left_exps_ref = left_exps.iter().collect::<Vec<&BigInt>>();
+            let r2_exps_ref = r2_exps.iter().collect::<Vec<&BigInt>>();
+            let eq_left = multi_exp_mod(&left_bases, &left_exps_ref, N_square).unwrap();
+            let r2rho = multi_exp_mod(&r2_bases, &r2_exps_ref, N_square).unwrap();
+            let gm2 = g.powm(&g_exp, N_square);
+            let r2rhoN = r2rho.powm(N, N_square);
+            let gm2r2rhoN = &gm2 * &r2rhoN;
+            let eq_right = gm2r2rhoN.mod_floor(N_square);
+            if eq_left != eq_right {
+                return false;
+            }
         }
-        flag
+        true
+    }
+
+    // the statement as SECURITY_PARAMETER / C runs of PaillierEnc
+    pub fn sigma<'a>(pp: &'a PrecomputedPaillier, ciphertext: &'a BigInt) -> Repeat<PaillierEnc<'a>> {
+        Repeat::new(PaillierEnc { pp, C: ciphertext }, SECURITY_PARAMETER / C)
+    }
+
+    // one commitment and one response per round, and N^2 consistent with N
+    // m2 is reduced mod q by the prover; a negative m2 would need g^-1 mod N^2
+    fn well_formed(&self) -> bool {
+        let repeat = SECURITY_PARAMETER / C;
+        let q = FE::q();
+        self.C1_vec.len() == repeat
+            && self.Response_vec.len() == repeat
+            && self.Response_vec.iter().all(|z| z.m2 >= BigInt::zero() && z.m2 < q)
+            && self.N > BigInt::one()
+            && self.N_square == &self.N * &self.N
+    }
+}
+// One round of ZK_AsiaCCS_19: knowledge of (m, r) with C = g^m * r^N mod N^2,
+// for challenges of C bits
+pub struct PaillierEnc<'a> {
+    pub pp: &'a PrecomputedPaillier,
+    pub C: &'a BigInt,
+}
+
+impl SigmaProtocol for PaillierEnc<'_> {
+    type Witness = (BigInt, BigInt); // (m, r)
+    type Commitment = BigInt;
+    type Randomness = (BigInt, BigInt);
+    type Response = Response;
+
+    fn challenge_bound(&self) -> BigInt {
+        BigInt::from(2).pow(C as u32)
     }
-}
\ No newline at end of file
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        vec![self.pp.N.clone(), self.pp.g.clone(), self.C.clone()]
+    }
+
+    fn commitment_hash_input(&self, C1: &BigInt) -> Vec<BigInt> {
+        vec![C1.clone()]
+    }
+
+    // C' = g^m1 * r1^N mod N^2
+    fn commit<R: RngCore + CryptoRng>(&self, _w: &(BigInt, BigInt), rng: &mut R) -> (BigInt, (BigInt, BigInt)) {
+        let N = &self.pp.N;
+        let N_square = &self.pp.N_square;
+        let m1 = sample_below(rng, &self.pp.q);
+        let r1 = sample_below(rng, N_square);
+        let gm1 = self.pp.g_exp(&m1);
+        let r1N = r1.powm(N, N_square);
+        let gm1r1N = &gm1 * &r1N;
+        let C1 = gm1r1N.mod_floor(N_square);
+        (C1, (m1, r1))
+    }
+
+    // m2 = m1 + b * m mod q and r2 = r1 * r^b mod N^2
+    fn respond(&self, w: &(BigInt, BigInt), state: (BigInt, BigInt), b: &BigInt) -> Response {
+        let (m, r) = w;
+        let (m1, r1) = state;
+        let m2 = (m1 + b * m).mod_floor(&self.pp.q);
+        let r2 = (r1 * r.powm(b, &self.pp.N_square)).mod_floor(&self.pp.N_square);
+        Response { m2, r2 }
+    }
+
+    fn verify(&self, C1: &BigInt, b: &BigInt, z: &Response) -> bool {
+        let N_square = &self.pp.N_square;
+        // C^b * C' == g^m2 * r2^N is checked as C' == g^m2 * r2^N * C^(-b), with g^m2
+        // from the fixed-base table and r2^N * C^(-b) as one multi-exponentiation
+        let minus_b = BigInt::zero() - b;
+        let r2NCb = match multi_exp_mod(&[&z.r2, self.C], &[&self.pp.N, &minus_b], N_square) {
+            Some(r2NCb) => r2NCb,
+            None => return false, // C is not invertible mod N^2
+        };
+        let gm2 = self.pp.g_exp(&z.m2);
+        let gm2r2NCb = &gm2 * &r2NCb;
+        C1.mod_floor(N_square) == gm2r2NCb.mod_floor(N_square)
+    }
+}
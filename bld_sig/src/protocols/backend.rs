@@ -0,0 +1,262 @@
+use std::fmt;
+
+use class_group::primitives::cl_dl_lcm::{Ciphertext, HSMCL};
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use crate::*;
+use crate::Error::{self, InvalidKey, InvalidProof, InvalidSig};
+use protocols::asiaccs_zk::ZK_AsiaCCS_19;
+use protocols::blind_ecdsa::user::{s4_verify_eval_hsmcl, s4_verify_eval_mpaillier};
+use protocols::eval_zk::{EvalProofHsmcl, EvalProofPaillier};
+use protocols::hsmcl_zk::{CLDLProof_modified, Witness};
+use protocols::mpaillier::Pallier_AsiaCCS_19;
+use protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
+use protocols::range_zk::{PaillierRangeProof, RANGE_SLACK};
+use protocols::rng::{hsmcl_keygen_with_setup, sample_below, sample_scalar};
+use protocols::validate::{valid_ciphertext, valid_pk};
+use rand::{CryptoRng, RngCore};
+use secret::HsmclKey;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// What the blind signature needs from an encryption scheme with plaintexts
+// in Z_q: the user encrypts h and Kx and proves it knows the plaintexts, the
+// signer evaluates (Enc(h) + sk * Enc(Kx)) * k1^-1 and may prove that it did,
+// and the user decrypts. Implemented by the key pair type of each scheme,
+// which only names it: keygen returns SecretKey, which zeroizes the secret
+// key on drop. The protocol written against it is in blind_ecdsa/generic.rs.
+pub trait AdditivelyHomomorphicBackend: Sized {
+    type SecretKey;
+    // a deserialized key has no precomputed tables
+    type PublicKey: Clone + fmt::Debug + Serialize + DeserializeOwned;
+    type Ciphertext: Clone + fmt::Debug + Serialize + DeserializeOwned;
+    type Randomness;
+    // knowledge of the plaintext (below q) of one ciphertext
+    type EncProof: Clone + fmt::Debug + Serialize + DeserializeOwned;
+    // c3 = c1^a * c2^b with a * K1 = G and b * K1 = pk, see eval_zk.rs
+    type EvalProof: Clone + fmt::Debug + Serialize + DeserializeOwned;
+
+    // bitsize: Paillier modulus size, or HSM-CL discriminant size lambda
+    fn keygen<R: RngCore + CryptoRng>(bitsize: usize, rng: &mut R) -> Self::SecretKey;
+    fn public_key(key: &Self::SecretKey) -> Self::PublicKey;
+    fn encrypt<R: RngCore + CryptoRng>(pk: &Self::PublicKey, m: &BigInt, rng: &mut R) -> (Self::Ciphertext, Self::Randomness);
+    fn add(pk: &Self::PublicKey, a: &Self::Ciphertext, b: &Self::Ciphertext) -> Result<Self::Ciphertext, Error>;
+    fn scalar_mul(pk: &Self::PublicKey, c: &Self::Ciphertext, k: &BigInt) -> Result<Self::Ciphertext, Error>;
+    fn rerandomize<R: RngCore + CryptoRng>(pk: &Self::PublicKey, c: &Self::Ciphertext, rng: &mut R) -> Result<Self::Ciphertext, Error>;
+    // plaintext mod q; c may come from the other party
+    fn decrypt(key: &Self::SecretKey, c: &Self::Ciphertext) -> Result<BigInt, Error>;
+
+    fn prove_enc<R: RngCore + CryptoRng>(pk: &Self::PublicKey, c: &Self::Ciphertext, m: &BigInt, r: &Self::Randomness, rng: &mut R) -> Self::EncProof;
+    // the proof must be for c under pk
+    fn verify_enc(pk: &Self::PublicKey, c: &Self::Ciphertext, proof: &Self::EncProof) -> Result<(), Error>;
+    fn prove_eval<R: RngCore + CryptoRng>(
+        pk: &Self::PublicKey,
+        c1: &Self::Ciphertext,
+        c2: &Self::Ciphertext,
+        c3: &Self::Ciphertext,
+        a: &BigInt,
+        b: &BigInt,
+        rng: &mut R,
+    ) -> Result<Self::EvalProof, Error>;
+    fn verify_eval(
+        pk: &Self::PublicKey,
+        c1: &Self::Ciphertext,
+        c2: &Self::Ciphertext,
+        c3: &Self::Ciphertext,
+        K1: &GE,
+        public_key: &GE,
+        proof: &Self::EvalProof,
+    ) -> Result<(), Error>;
+}
+
+impl AdditivelyHomomorphicBackend for Pallier_AsiaCCS_19 {
+    type SecretKey = Pallier_AsiaCCS_19; // p and t are secret-wrapped
+    type PublicKey = PrecomputedPaillier;
+    type Ciphertext = BigInt;
+    type Randomness = BigInt;
+    type EncProof = (ZK_AsiaCCS_19, PaillierRangeProof);
+    type EvalProof = EvalProofPaillier;
+
+    fn keygen<R: RngCore + CryptoRng>(bitsize: usize, rng: &mut R) -> Pallier_AsiaCCS_19 {
+        Pallier_AsiaCCS_19::keygen_with_rng(bitsize, rng)
+    }
+
+    fn public_key(key: &Pallier_AsiaCCS_19) -> PrecomputedPaillier {
+        PrecomputedPaillier::from_key(key)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(pk: &PrecomputedPaillier, m: &BigInt, rng: &mut R) -> (BigInt, BigInt) {
+        let r: FE = sample_scalar(rng); // as in the step-2 requests
+        let r = r.to_big_int();
+        (pk.encrypt(m, &r), r)
+    }
+
+    fn add(pk: &PrecomputedPaillier, a: &BigInt, b: &BigInt) -> Result<BigInt, Error> {
+        Ok((a * b).mod_floor(&pk.N_square))
+    }
+
+    fn scalar_mul(pk: &PrecomputedPaillier, c: &BigInt, k: &BigInt) -> Result<BigInt, Error> {
+        Ok(c.powm(k, &pk.N_square))
+    }
+
+    // c * r^N
+    fn rerandomize<R: RngCore + CryptoRng>(pk: &PrecomputedPaillier, c: &BigInt, rng: &mut R) -> Result<BigInt, Error> {
+        let r = sample_below(rng, &pk.N);
+        Ok((c * &r.powm(&pk.N, &pk.N_square)).mod_floor(&pk.N_square))
+    }
+
+    fn decrypt(key: &Pallier_AsiaCCS_19, c: &BigInt) -> Result<BigInt, Error> {
+        Ok(Pallier_AsiaCCS_19::decrypt(c, key.clone()))
+    }
+
+    fn prove_enc<R: RngCore + CryptoRng>(pk: &PrecomputedPaillier, c: &BigInt, m: &BigInt, r: &BigInt, rng: &mut R) -> Self::EncProof {
+        let p = ZK_AsiaCCS_19::prove_precomputed_with_rng(pk, c.clone(), m.clone(), r.clone(), rng);
+        let range = PaillierRangeProof::prove_with_rng(pk, c.clone(), m.clone(), r.clone(), RANGE_SLACK, rng);
+        (p, range)
+    }
+
+    fn verify_enc(pk: &PrecomputedPaillier, c: &BigInt, proof: &Self::EncProof) -> Result<(), Error> {
+        let (p, range) = proof;
+        if &p.C != c || &range.C != c || !pk.matches(&range.N, &range.g) {
+            return Err(InvalidProof);
+        }
+        match p.verify_precomputed(pk) && range.verify_precomputed(pk, RANGE_SLACK) {
+            true => Ok(()),
+            false => Err(InvalidProof),
+        }
+    }
+
+    fn prove_eval<R: RngCore + CryptoRng>(
+        pk: &PrecomputedPaillier,
+        c1: &BigInt,
+        c2: &BigInt,
+        c3: &BigInt,
+        a: &BigInt,
+        b: &BigInt,
+        rng: &mut R,
+    ) -> Result<EvalProofPaillier, Error> {
+        EvalProofPaillier::prove_with_rng(c1, c2, c3, &pk.N, &pk.N_square, a, b, rng)
+    }
+
+    fn verify_eval(
+        pk: &PrecomputedPaillier,
+        c1: &BigInt,
+        c2: &BigInt,
+        c3: &BigInt,
+        K1: &GE,
+        public_key: &GE,
+        proof: &EvalProofPaillier,
+    ) -> Result<(), Error> {
+        s4_verify_eval_mpaillier(proof, c1, c2, c3, &pk.N, K1, public_key)
+    }
+}
+
+// challenge size (in bits) of CLDLProof_modified, as in the step-2 requests
+const CLDL_C: usize = 10;
+
+// seed of the public class group setup: digits of pi
+fn hsmcl_setup_seed() -> BigInt {
+    str::parse(
+        "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
+    ).unwrap()
+}
+
+impl AdditivelyHomomorphicBackend for HSMCL {
+    type SecretKey = HsmclKey;
+    type PublicKey = PrecomputedPK;
+    type Ciphertext = Ciphertext;
+    type Randomness = BigInt;
+    type EncProof = CLDLProof_modified;
+    type EvalProof = EvalProofHsmcl;
+
+    fn keygen<R: RngCore + CryptoRng>(lam: usize, rng: &mut R) -> HsmclKey {
+        HsmclKey::from_hsmcl(hsmcl_keygen_with_setup(rng, &FE::q(), &lam, &hsmcl_setup_seed()))
+    }
+
+    fn public_key(key: &HsmclKey) -> PrecomputedPK {
+        PrecomputedPK::new(&key.pk)
+    }
+
+    fn encrypt<R: RngCore + CryptoRng>(pk: &PrecomputedPK, m: &BigInt, rng: &mut R) -> (Ciphertext, BigInt) {
+        let r = sample_below(rng, &(&pk.pk.stilde * BigInt::from(2).pow(80)));
+        (pk.encrypt_predefined_randomness(m, &r), r)
+    }
+
+    fn add(_pk: &PrecomputedPK, a: &Ciphertext, b: &Ciphertext) -> Result<Ciphertext, Error> {
+        let _pari = pari::enter().map_err(|_| Error::Pari)?;
+        Ok(HSMCL::eval_sum(a, b))
+    }
+
+    fn scalar_mul(_pk: &PrecomputedPK, c: &Ciphertext, k: &BigInt) -> Result<Ciphertext, Error> {
+        let _pari = pari::enter().map_err(|_| Error::Pari)?;
+        Ok(HSMCL::eval_scal(c, k))
+    }
+
+    // c + Enc(0; r), i.e. (c1 * gq^r, c2 * h^r)
+    fn rerandomize<R: RngCore + CryptoRng>(pk: &PrecomputedPK, c: &Ciphertext, rng: &mut R) -> Result<Ciphertext, Error> {
+        let r = sample_below(rng, &(&pk.pk.stilde * BigInt::from(2).pow(80)));
+        let _pari = pari::enter().map_err(|_| Error::Pari)?;
+        Ok(Ciphertext {
+            c1: c.c1.compose(&pk.gq_exp(&r)).reduce(),
+            c2: c.c2.compose(&pk.h_exp(&r)).reduce(),
+        })
+    }
+
+    // pari would decrypt any pair of forms, so c is checked first
+    fn decrypt(key: &HsmclKey, c: &Ciphertext) -> Result<BigInt, Error> {
+        if !valid_ciphertext(c, &key.pk) {
+            return Err(InvalidSig);
+        }
+        let _pari = pari::enter().map_err(|_| Error::Pari)?;
+        Ok(key.decrypt(c))
+    }
+
+    // also shows that m is the discrete log of m * G, which the proof carries
+    fn prove_enc<R: RngCore + CryptoRng>(pk: &PrecomputedPK, c: &Ciphertext, m: &BigInt, r: &BigInt, rng: &mut R) -> CLDLProof_modified {
+        let m_fe: FE = ECScalar::from(m);
+        let Q = GE::generator() * m_fe;
+        let w = Witness { x: m.clone(), r: r.clone() };
+        CLDLProof_modified::prove_precomputed_with_rng(w, pk, c.clone(), Q, hsmcl_setup_seed(), CLDL_C, rng)
+    }
+
+    fn verify_enc(pk: &PrecomputedPK, c: &Ciphertext, proof: &CLDLProof_modified) -> Result<(), Error> {
+        if !valid_pk(&pk.pk) {
+            return Err(InvalidKey); // pk comes with the request
+        }
+        if proof.ciphertext.c1 != c.c1 || proof.ciphertext.c2 != c.c2 || !valid_ciphertext(c, &pk.pk) {
+            return Err(InvalidProof);
+        }
+        if !pk.matches(&proof.pk) {
+            return Err(InvalidKey);
+        }
+        proof.verify_precomputed(pk, CLDL_C).map_err(|_| InvalidProof)
+    }
+
+    fn prove_eval<R: RngCore + CryptoRng>(
+        _pk: &PrecomputedPK,
+        c1: &Ciphertext,
+        c2: &Ciphertext,
+        c3: &Ciphertext,
+        a: &BigInt,
+        b: &BigInt,
+        rng: &mut R,
+    ) -> Result<EvalProofHsmcl, Error> {
+        EvalProofHsmcl::prove_with_rng(c1, c2, c3, a, b, rng)
+    }
+
+    fn verify_eval(
+        pk: &PrecomputedPK,
+        c1: &Ciphertext,
+        c2: &Ciphertext,
+        c3: &Ciphertext,
+        K1: &GE,
+        public_key: &GE,
+        proof: &EvalProofHsmcl,
+    ) -> Result<(), Error> {
+        if ![c1, c2, c3].iter().all(|c| valid_ciphertext(c, &pk.pk)) {
+            return Err(InvalidProof);
+        }
+        s4_verify_eval_hsmcl(proof, c1, c2, c3, K1, public_key)
+    }
+}
@@ -0,0 +1,81 @@
+use crate::*;
+use curv::BigInt;
+use protocols::rng::sample_bits;
+use rand::{CryptoRng, RngCore};
+
+// Batch verification helpers. A batch check combines the verification
+// equations of many proofs with random SECURITY_PARAMETER-bit weights (small
+// exponent test). If an equation is off by an element d, the combined check
+// sees d^w for the random weight w. For d of large order this is 1 with
+// probability about 2^-SECURITY_PARAMETER, but both Z*_{N^2} and the class
+// group have elements of order 2 (-1, ambiguous forms), and d^w = 1 for every
+// even w. The weights are therefore odd, which catches such a d always as
+// long as it sits in one equation. Each equation gets its own weight, so every
+// element the prover chooses (commitments and responses, which appear in one
+// equation each) gets one odd weight. An element of the statement can appear
+// in several equations and then gets the sum of their weights: C of
+// ZK_AsiaCCS_19 gets sum rho_i * b_i, which is even for an even number of odd
+// challenges b_i. C * d then passes the combined check, as it passes the
+// rounds with even b_i on their own; in Z*_{N^2} this is harmless, since any d
+// of order 2 is d^N for odd N and C * d encrypts the plaintext of C.
+// Discrepancies of small odd order, which in Z*_{N^2} the owner of the key can
+// build from the factorization of N, are caught with probability 1 - 1/p for
+// the smallest prime p of their order. When the combined check fails, bisect
+// splits the set until the invalid proofs are isolated.
+
+// random odd SECURITY_PARAMETER-bit weight
+pub fn batch_weight<R: RngCore + CryptoRng>(rng: &mut R) -> BigInt {
+    sample_bits(rng, SECURITY_PARAMETER) | BigInt::one()
+}
+
+// Ok(()) if check accepts all items, otherwise Err with the indices of the
+// items rejected on their own
+pub fn bisect<T, F>(items: &[&T], check: &mut F) -> Result<(), Vec<usize>>
+where
+    F: FnMut(&[&T]) -> bool,
+{
+    let mut bad = Vec::new();
+    bisect_rec(items, 0, check, &mut bad);
+    match bad.is_empty() {
+        true => Ok(()),
+        false => Err(bad),
+    }
+}
+
+fn bisect_rec<T, F>(items: &[&T], offset: usize, check: &mut F, bad: &mut Vec<usize>)
+where
+    F: FnMut(&[&T]) -> bool,
+{
+    if items.is_empty() || check(items) {
+        return;
+    }
+    if items.len() == 1 {
+        bad.push(offset);
+        return;
+    }
+    let mid = items.len() / 2;
+    bisect_rec(&items[..mid], offset, check, bad);
+    bisect_rec(&items[mid..], offset + mid, check, bad);
+}
+
+// indices of items grouped by equal key, groups in order of first appearance.
+// Equations can only be combined between proofs under the same public key.
+pub fn group_by_key<T, K, G>(items: &[&T], key: G) -> Vec<Vec<usize>>
+where
+    K: PartialEq,
+    G: Fn(&T) -> K,
+{
+    let mut keys: Vec<K> = Vec::new();
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    for (i, item) in items.iter().enumerate() {
+        let k = key(*item);
+        match keys.iter().position(|other| other == &k) {
+            Some(pos) => groups[pos].push(i),
+            None => {
+                keys.push(k);
+                groups.push(vec![i]);
+            }
+        }
+    }
+    groups
+}
@@ -0,0 +1,211 @@
+use class_group::primitives::cl_dl_lcm::{Ciphertext, HSMCL};
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::{FE, GE};
+use serde::{Deserialize, Serialize};
+use crate::*;
+use crate::Error::{self, InvalidKey};
+use protocols::backend::AdditivelyHomomorphicBackend;
+use protocols::mpaillier::Pallier_AsiaCCS_19;
+use protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
+use protocols::range_zk::RANGE_SLACK;
+use rand::{CryptoRng, RngCore};
+use secret::HsmclKey;
+use super::signer::{s3_check_hsmcl, s3_check_hsmcl_ggm, s3_check_mpaillier};
+use super::user::{User_Enc_and_NIZK_hsmcl_GGM, User_Enc_and_NIZK_hsmcl_version, User_Enc_and_NIZK_mpaillier_version};
+
+// Steps 2 to 4 of the blind signature written once for any
+// AdditivelyHomomorphicBackend. Step 1 and the unblinding are the same as in
+// the backend-specific versions in signer.rs and user.rs. session.rs runs
+// every backend through the functions below.
+
+// A step-2 request: Enc(h) and Enc(Kx) under a key of the user's, with proofs
+// that the user knows both plaintexts and that they are below q
+pub trait BlindRequest: Sized {
+    type Backend: AdditivelyHomomorphicBackend;
+
+    // also returns the decryption key, which the user keeps for step 4
+    fn create<R: RngCore + CryptoRng>(
+        h: &BigInt,
+        Kx: &BigInt,
+        bitsize: usize,
+        rng: &mut R,
+    ) -> (Self, <Self::Backend as AdditivelyHomomorphicBackend>::SecretKey);
+    // the signer's checks before step 3
+    fn verify(&self) -> Result<(), Error>;
+    // the key the ciphertexts are under, as the request states it
+    fn public_key(&self) -> <Self::Backend as AdditivelyHomomorphicBackend>::PublicKey;
+    // Enc(h) and Enc(Kx)
+    fn ciphertexts(&self) -> (&<Self::Backend as AdditivelyHomomorphicBackend>::Ciphertext, &<Self::Backend as AdditivelyHomomorphicBackend>::Ciphertext);
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct User_Enc_and_NIZK<B: AdditivelyHomomorphicBackend> {
+    pub pk: B::PublicKey,
+    pub Enc_h: B::Ciphertext,
+    pub Enc_Kx: B::Ciphertext,
+    pub p1: B::EncProof, // for Enc(h)
+    pub p2: B::EncProof, // for Enc(Kx)
+}
+
+impl<B: AdditivelyHomomorphicBackend> User_Enc_and_NIZK<B> {
+    // also returns the decryption key, which the user keeps for step 4
+    pub fn s2_encrypt_and_nizk<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, bitsize: usize, rng: &mut R) -> (Self, B::SecretKey) {
+        let key = B::keygen(bitsize, rng);
+        let pk = B::public_key(&key);
+        let (Enc_h, r1) = B::encrypt(&pk, h, rng);
+        let (Enc_Kx, r2) = B::encrypt(&pk, Kx, rng);
+        let p1 = B::prove_enc(&pk, &Enc_h, h, &r1, rng);
+        let p2 = B::prove_enc(&pk, &Enc_Kx, Kx, &r2, rng);
+        let request = Self {
+            pk,
+            Enc_h,
+            Enc_Kx,
+            p1,
+            p2,
+        };
+        (request, key)
+    }
+}
+
+impl<B: AdditivelyHomomorphicBackend> BlindRequest for User_Enc_and_NIZK<B> {
+    type Backend = B;
+
+    fn create<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, bitsize: usize, rng: &mut R) -> (Self, B::SecretKey) {
+        Self::s2_encrypt_and_nizk(h, Kx, bitsize, rng)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        B::verify_enc(&self.pk, &self.Enc_h, &self.p1)?;
+        B::verify_enc(&self.pk, &self.Enc_Kx, &self.p2)
+    }
+
+    fn public_key(&self) -> B::PublicKey {
+        self.pk.clone()
+    }
+
+    fn ciphertexts(&self) -> (&B::Ciphertext, &B::Ciphertext) {
+        (&self.Enc_h, &self.Enc_Kx)
+    }
+}
+
+// The hand-written requests of user.rs. Their proofs are checked as in
+// signer.rs: the range proofs and the joint GGM proof do not split into one
+// EncProof per ciphertext.
+
+impl BlindRequest for User_Enc_and_NIZK_mpaillier_version {
+    type Backend = Pallier_AsiaCCS_19;
+
+    fn create<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, bitsize: usize, rng: &mut R) -> (Self, Pallier_AsiaCCS_19) {
+        Self::s2_mpaillier_encrypt_and_nizk_with_key(h, Kx, &bitsize, rng)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        s3_check_mpaillier(self, RANGE_SLACK)
+    }
+
+    fn public_key(&self) -> PrecomputedPaillier {
+        PrecomputedPaillier::plain(&self.p1.N, &self.p1.N_square, &self.p1.g, &FE::q())
+    }
+
+    fn ciphertexts(&self) -> (&BigInt, &BigInt) {
+        (&self.C1, &self.C2)
+    }
+}
+
+impl BlindRequest for User_Enc_and_NIZK_hsmcl_version {
+    type Backend = HSMCL;
+
+    fn create<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, lam: usize, rng: &mut R) -> (Self, HsmclKey) {
+        Self::s2_hsmcl_encrypt_and_ggm_nizk_with_key(h, Kx, &lam, rng)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        s3_check_hsmcl(self)
+    }
+
+    fn public_key(&self) -> PrecomputedPK {
+        PrecomputedPK::plain(&self.p1.pk)
+    }
+
+    fn ciphertexts(&self) -> (&Ciphertext, &Ciphertext) {
+        (&self.Enc_h, &self.Enc_Kx)
+    }
+}
+
+impl BlindRequest for User_Enc_and_NIZK_hsmcl_GGM {
+    type Backend = HSMCL;
+
+    fn create<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, lam: usize, rng: &mut R) -> (Self, HsmclKey) {
+        Self::s2_hsmcl_encrypt_and_ggm_nizk_with_key(h, Kx, &lam, rng)
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        s3_check_hsmcl_ggm(self)
+    }
+
+    fn public_key(&self) -> PrecomputedPK {
+        PrecomputedPK::plain(&self.proof.pk)
+    }
+
+    fn ciphertexts(&self) -> (&Ciphertext, &Ciphertext) {
+        (&self.Enc_h, &self.Enc_Kx)
+    }
+}
+
+// Step 3 on checked ciphertexts: (c1 + sk * c2) * k1^-1
+pub fn s3_eval<B: AdditivelyHomomorphicBackend>(pk: &B::PublicKey, c1: &B::Ciphertext, c2: &B::Ciphertext, k1: &BigInt, sk: &BigInt) -> Result<B::Ciphertext, Error> {
+    let q = FE::q();
+    let k1_inv = k1.invert(&q).ok_or(InvalidKey)?;
+    let c = B::add(pk, c1, &B::scalar_mul(pk, c2, &sk.mod_floor(&q))?)?;
+    B::scalar_mul(pk, &c, &k1_inv)
+}
+
+// Step 3: (Enc(h) + sk * Enc(Kx)) * k1^-1, after checking the request's proofs
+pub fn s3_partial_sig<B, Q>(request: &Q, k1: &BigInt, sk: &BigInt) -> Result<B::Ciphertext, Error>
+where
+    B: AdditivelyHomomorphicBackend,
+    Q: BlindRequest<Backend = B>,
+{
+    request.verify()?;
+    let (Enc_h, Enc_Kx) = request.ciphertexts();
+    s3_eval::<B>(&request.public_key(), Enc_h, Enc_Kx, k1, sk)
+}
+
+// optional proof that c3 came from s3_partial_sig with the k1 of K1 and sk of the public key
+pub fn s3_prove_eval<B, Q, R>(request: &Q, k1: &BigInt, sk: &BigInt, c3: &B::Ciphertext, rng: &mut R) -> Result<B::EvalProof, Error>
+where
+    B: AdditivelyHomomorphicBackend,
+    Q: BlindRequest<Backend = B>,
+    R: RngCore + CryptoRng,
+{
+    let (a, b) = eval_exponents(k1, sk)?;
+    let (Enc_h, Enc_Kx) = request.ciphertexts();
+    B::prove_eval(&request.public_key(), Enc_h, Enc_Kx, c3, &a, &b, rng)
+}
+
+// exponents of Enc(h) and Enc(Kx) in step 3: k1^-1 and (sk mod q) * k1^-1, the latter not reduced
+pub(crate) fn eval_exponents(k1: &BigInt, sk: &BigInt) -> Result<(BigInt, BigInt), Error> {
+    let q = FE::q();
+    let a = k1.invert(&q).ok_or(InvalidKey)?;
+    let b = &sk.mod_floor(&q) * &a;
+    Ok((a, b))
+}
+
+pub fn s4_verify_eval<B, Q>(request: &Q, c3: &B::Ciphertext, K1: &GE, public_key: &GE, proof: &B::EvalProof) -> Result<(), Error>
+where
+    B: AdditivelyHomomorphicBackend,
+    Q: BlindRequest<Backend = B>,
+{
+    let (Enc_h, Enc_Kx) = request.ciphertexts();
+    B::verify_eval(&request.public_key(), Enc_h, Enc_Kx, c3, K1, public_key, proof)
+}
+
+// Step 4: s = k2^-1 * Dec(c) mod q
+pub fn s4_sig<B: AdditivelyHomomorphicBackend>(k2: &BigInt, c: &B::Ciphertext, key: &B::SecretKey) -> Result<BigInt, Error> {
+    let q = FE::q();
+    let dec = B::decrypt(key, c)?;
+    let k2_inv = k2.invert(&q).ok_or(InvalidKey)?;
+    Ok(BigInt::mod_mul(&dec, &k2_inv, &q))
+}
@@ -1,3 +1,6 @@
+pub mod nonce;
 pub mod signer;
 pub mod user;
+pub mod generic;
+pub mod session;
 pub mod test_sign;
@@ -0,0 +1,88 @@
+use crypto::digest::Digest;
+use crypto::hmac::Hmac;
+use crypto::mac::Mac;
+use crypto::sha2::Sha256;
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::FE;
+use crate::*;
+use protocols::rng::os_rng;
+use rand::{CryptoRng, RngCore};
+
+// domain separation between the signer's and the user's nonce share
+pub const K1_LABEL: &[u8] = b"bld-sig k1";
+pub const K2_LABEL: &[u8] = b"bld-sig k2";
+
+fn hmac_sha256(key: &[u8], data: &[&[u8]]) -> [u8; 32] {
+    let mut hmac = Hmac::new(Sha256::new(), key);
+    for d in data {
+        hmac.input(d);
+    }
+    let mut out = [0u8; 32];
+    hmac.raw_result(&mut out);
+    out
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input(data);
+    let mut out = [0u8; 32];
+    hasher.result(&mut out);
+    out
+}
+
+// int2octets for the 256-bit group order of secp256k1
+fn int2octets(x: &BigInt) -> Vec<u8> {
+    let bytes = BigInt::to_vec(x);
+    let mut out = vec![0u8; 32 - bytes.len()];
+    out.extend_from_slice(&bytes);
+    out
+}
+
+// RFC 6979 section 3.2 with HMAC-SHA256: x is the private key, h1 the 32-byte
+// message hash and extra the additional data k' of section 3.6 (empty for the
+// plain deterministic variant)
+pub fn rfc6979_nonce(x: &BigInt, h1: &[u8; 32], extra: &[u8]) -> BigInt {
+    let q = FE::q();
+    let x_octets = int2octets(&x.mod_floor(&q));
+    let h1_octets = int2octets(&BigInt::from(&h1[..]).mod_floor(&q));
+    let mut v = [1u8; 32];
+    let mut k = [0u8; 32];
+    k = hmac_sha256(&k, &[&v[..], &[0x00], &x_octets[..], &h1_octets[..], extra]);
+    v = hmac_sha256(&k, &[&v[..]]);
+    k = hmac_sha256(&k, &[&v[..], &[0x01], &x_octets[..], &h1_octets[..], extra]);
+    v = hmac_sha256(&k, &[&v[..]]);
+    loop {
+        // qlen = hlen = 256, so one block is a candidate and bits2int is a plain read
+        v = hmac_sha256(&k, &[&v[..]]);
+        let candidate = BigInt::from(&v[..]);
+        if candidate >= BigInt::one() && candidate < q {
+            return candidate;
+        }
+        k = hmac_sha256(&k, &[&v[..], &[0x00]]);
+        v = hmac_sha256(&k, &[&v[..]]);
+    }
+}
+
+// Hedged nonce: RFC 6979 with x = secret, h1 = SHA-256(session_id) and
+// k' = label || randomness. A repeated or weak RNG output still gives distinct
+// nonces for distinct sessions, and fresh randomness keeps the nonce
+// unpredictable to anyone who learns the secret.
+pub fn derive_nonce(secret: &BigInt, session_id: &[u8], label: &[u8], randomness: &[u8]) -> FE {
+    let h1 = sha256(session_id);
+    let mut extra = label.to_vec();
+    extra.extend_from_slice(randomness);
+    ECScalar::from(&rfc6979_nonce(secret, &h1, &extra))
+}
+
+// 32 bytes from the OS RNG for derive_nonce
+pub fn fresh_randomness() -> Vec<u8> {
+    fresh_randomness_with_rng(&mut os_rng())
+}
+
+pub fn fresh_randomness_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Vec<u8> {
+    let mut randomness = vec![0u8; 32];
+    rng.fill_bytes(&mut randomness);
+    randomness
+}
@@ -0,0 +1,545 @@
+use std::fmt;
+use std::str::FromStr;
+
+use class_group::primitives::cl_dl_lcm::{Ciphertext, HSMCL};
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::cryptographic_primitives::proofs::sigma_dlog::DLogProof;
+use curv::GE;
+use serde::{Deserialize, Serialize};
+use crate::*;
+use crate::Error::{self, InvalidCom, InvalidKey, InvalidProof, InvalidSig};
+use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
+use curv::cryptographic_primitives::commitments::traits::Commitment;
+use digest::MessageDigest;
+use protocols::dlog::DLogProofWithBase;
+use protocols::eval_zk::{EvalProofHsmcl, EvalProofPaillier};
+use protocols::mpaillier::Pallier_AsiaCCS_19;
+use protocols::rng::{os_rng, sample_bits};
+use rand::{CryptoRng, RngCore};
+use secret::HsmclKey;
+use signature::{verify, Signature};
+use super::generic::{s3_partial_sig, s3_prove_eval, s4_sig, s4_verify_eval, BlindRequest};
+use super::signer::*;
+use super::user::*;
+
+// The blind signing rounds as messages that can be written to disk or sent
+// over the wire, and the state each party keeps between its two steps:
+//   round 1, signer -> user: K1 = k1 * G and a proof of knowledge of k1
+//   round 2, user -> signer: Enc(h), Enc(Kx) and their proofs
+//   round 3, signer -> user: Enc(k1^-1 * (h + Kx * sk))
+// after which the user decrypts and unblinds to (r, s) = (Kx, s).
+//
+// Round 1 can instead run as commit, contribute, reveal: the signer commits to
+// K1 and its proof, the user answers with fresh randomness rho, and the
+// signer opens the commitment. Both then use K1' = K1 + t * G with
+// t = H(session id, commitment, rho), which neither side can steer.
+//
+// Round 2 also carries a commitment to K = k2 * K1. K fixes r, so the user
+// opens it, with a proof of knowledge of k2, only once the signature is
+// public; the signer checks the opening against the SessionReceipt it kept.
+
+// Paillier modulus size and HSM-CL discriminant size, both for 112-bit security
+pub const MPAILLIER_BITSIZE: usize = 2048;
+pub const HSMCL_LAM: usize = 1348;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Backend {
+    Mpaillier,
+    Hsmcl,
+    HsmclGgm,
+}
+
+impl Backend {
+    // whether its steps run class group code, which takes turns on the PARI
+    // lock when several threads sign at once
+    pub fn uses_class_group(&self) -> bool {
+        *self != Backend::Mpaillier
+    }
+
+    pub fn default_bitsize(&self) -> usize {
+        match self {
+            Backend::Mpaillier => MPAILLIER_BITSIZE,
+            Backend::Hsmcl | Backend::HsmclGgm => HSMCL_LAM,
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Backend::Mpaillier => "mpaillier",
+            Backend::Hsmcl => "hsmcl",
+            Backend::HsmclGgm => "hsmcl-ggm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mpaillier" => Ok(Backend::Mpaillier),
+            "hsmcl" => Ok(Backend::Hsmcl),
+            "hsmcl-ggm" => Ok(Backend::HsmclGgm),
+            _ => Err(format!("unknown backend {}, expected mpaillier, hsmcl or hsmcl-ggm", s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round1 {
+    pub session_id: String,
+    pub backend: Backend,
+    pub K1: GE,
+    pub K1_proof: DLogProof,
+}
+
+// hardened round 1: signer -> user
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round1Commit {
+    pub session_id: String,
+    pub backend: Backend,
+    pub commitment: BigInt, // to K1 and K1_proof
+}
+
+// hardened round 1: user -> signer
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round1Contribution {
+    pub session_id: String,
+    pub rho: BigInt,
+}
+
+// hardened round 1: signer -> user, opens the commitment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round1Reveal {
+    pub round1: Round1,
+    pub blind_factor: BigInt,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum SignRequest {
+    Mpaillier(User_Enc_and_NIZK_mpaillier_version),
+    Hsmcl(User_Enc_and_NIZK_hsmcl_version),
+    HsmclGgm(User_Enc_and_NIZK_hsmcl_GGM),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round2 {
+    pub session_id: String,
+    pub request: SignRequest,
+    pub K_commitment: BigInt, // to K, opened by a KOpening
+}
+
+// user -> signer once the signature is published: opens Round2's K_commitment
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KOpening {
+    pub session_id: String,
+    pub proof: DLogProofWithBase, // for k2 with K = k2 * K1
+    pub blind_factor: BigInt,
+}
+
+// what the signer keeps of a session to check a later KOpening
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionReceipt {
+    pub session_id: String,
+    pub K1: GE,
+    pub K_commitment: BigInt,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PartialSig {
+    Mpaillier(BigInt),
+    Hsmcl(Ciphertext),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum EvalProof {
+    Mpaillier(EvalProofPaillier),
+    Hsmcl(EvalProofHsmcl),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Round3 {
+    pub session_id: String,
+    pub partial_sig: PartialSig,
+    #[serde(default)]
+    pub eval_proof: Option<EvalProof>, // see respond_with_proof
+}
+
+// Signer state between rounds 1 and 3. respond() consumes it, so k1 is used
+// for at most one request: two answers under the same k1 reveal sk.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignerSession {
+    pub session_id: String,
+    pub backend: Backend,
+    nonce: Signer_EcKeyPair,
+    #[serde(default)]
+    pending: Option<Round1Reveal>, // until the user's contribution arrives
+}
+
+impl SignerSession {
+    pub fn start(key: &Signer_SigningKey, backend: Backend) -> (Self, Round1) {
+        Self::start_with_rng(key, backend, &mut os_rng())
+    }
+
+    pub fn start_with_rng<R: RngCore + CryptoRng>(key: &Signer_SigningKey, backend: Backend, rng: &mut R) -> (Self, Round1) {
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let sk = key.expose_secret().to_big_int();
+        let nonce = Signer_EcKeyPair::s1_generate_K1_hedged_with_rng(&sk, session_id.as_bytes(), rng);
+        let round1 = Round1 {
+            session_id: session_id.clone(),
+            backend,
+            K1: nonce.public_share,
+            K1_proof: nonce.s1_prove_K1(),
+        };
+        let session = Self {
+            session_id,
+            backend,
+            nonce,
+            pending: None,
+        };
+        (session, round1)
+    }
+
+    pub fn start_committed(key: &Signer_SigningKey, backend: Backend) -> (Self, Round1Commit) {
+        Self::start_committed_with_rng(key, backend, &mut os_rng())
+    }
+
+    pub fn start_committed_with_rng<R: RngCore + CryptoRng>(key: &Signer_SigningKey, backend: Backend, rng: &mut R) -> (Self, Round1Commit) {
+        let (mut session, round1) = Self::start_with_rng(key, backend, rng);
+        let blind_factor = sample_bits(rng, 256);
+        let commitment = HashCommitment::create_commitment_with_user_defined_randomness(&round1_hash(&round1), &blind_factor);
+        let commit = Round1Commit {
+            session_id: session.session_id.clone(),
+            backend,
+            commitment,
+        };
+        session.pending = Some(Round1Reveal { round1, blind_factor });
+        (session, commit)
+    }
+
+    // opens the commitment and moves to k1 + t. Accepts one contribution only,
+    // counted once the tweak has been applied.
+    pub fn reveal(&mut self, contribution: &Round1Contribution) -> Result<Round1Reveal, Error> {
+        if contribution.session_id != self.session_id {
+            return Err(InvalidProof);
+        }
+        let reveal = self.pending.as_ref().ok_or(InvalidCom)?;
+        let commitment = HashCommitment::create_commitment_with_user_defined_randomness(&round1_hash(&reveal.round1), &reveal.blind_factor);
+        // pending stays in place if the tweak fails, so the user can retry
+        let t = nonce_tweak(&self.session_id, &commitment, &contribution.rho)?;
+        self.nonce = self.nonce.s1_tweak_K1(&t);
+        Ok(self.pending.take().unwrap())
+    }
+
+    // to keep before respond(), for checking the user's KOpening later
+    pub fn receipt(&self, round2: &Round2) -> Result<SessionReceipt, Error> {
+        if round2.session_id != self.session_id {
+            return Err(InvalidProof);
+        }
+        if self.pending.is_some() {
+            return Err(InvalidCom); // K1 is not final yet
+        }
+        Ok(SessionReceipt {
+            session_id: self.session_id.clone(),
+            K1: self.nonce.public_share,
+            K_commitment: round2.K_commitment.clone(),
+        })
+    }
+
+    pub fn respond(self, key: &Signer_SigningKey, round2: &Round2) -> Result<Round3, Error> {
+        self.respond_inner(key, round2, false)
+    }
+
+    // also proves that the answer is the prescribed function of the request,
+    // which protects blindness against a signer that deviates in round 3
+    pub fn respond_with_proof(self, key: &Signer_SigningKey, round2: &Round2) -> Result<Round3, Error> {
+        self.respond_inner(key, round2, true)
+    }
+
+    fn respond_inner(self, key: &Signer_SigningKey, round2: &Round2, prove: bool) -> Result<Round3, Error> {
+        if round2.session_id != self.session_id {
+            return Err(InvalidProof);
+        }
+        if self.pending.is_some() {
+            return Err(InvalidCom); // committed round 1 not finished
+        }
+        let k1 = self.nonce.expose_secret().to_big_int();
+        let sk = key.expose_secret().to_big_int();
+        let rng = &mut os_rng();
+        let (partial_sig, eval_proof) = match (self.backend, &round2.request) {
+            (Backend::Mpaillier, SignRequest::Mpaillier(req)) => {
+                let c3 = s3_partial_sig(req, &k1, &sk)?;
+                let proof = match prove {
+                    true => Some(EvalProof::Mpaillier(s3_prove_eval(req, &k1, &sk, &c3, rng)?)),
+                    false => None,
+                };
+                (PartialSig::Mpaillier(c3), proof)
+            }
+            (Backend::Hsmcl, SignRequest::Hsmcl(req)) => respond_hsmcl(req, &k1, &sk, prove, rng)?,
+            (Backend::HsmclGgm, SignRequest::HsmclGgm(req)) => respond_hsmcl(req, &k1, &sk, prove, rng)?,
+            _ => return Err(InvalidProof), // request for another backend
+        };
+        Ok(Round3 {
+            session_id: self.session_id,
+            partial_sig,
+            eval_proof,
+        })
+    }
+}
+
+// step 3 for both HSM-CL requests, which share PartialSig::Hsmcl and EvalProof::Hsmcl
+fn respond_hsmcl<Q: BlindRequest<Backend = HSMCL>, R: RngCore + CryptoRng>(
+    request: &Q,
+    k1: &BigInt,
+    sk: &BigInt,
+    prove: bool,
+    rng: &mut R,
+) -> Result<(PartialSig, Option<EvalProof>), Error> {
+    let c3 = s3_partial_sig(request, k1, sk)?;
+    let proof = match prove {
+        true => Some(EvalProof::Hsmcl(s3_prove_eval(request, k1, sk, &c3, rng)?)),
+        false => None,
+    };
+    Ok((PartialSig::Hsmcl(c3), proof))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum DecryptionKey {
+    Mpaillier(Pallier_AsiaCCS_19),
+    Hsmcl(HsmclKey),
+}
+
+// User state between rounds 2 and 3
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserSession {
+    pub session_id: String,
+    pub backend: Backend,
+    pub public_key: GE,
+    pub h: BigInt,
+    K1: GE,
+    nonce: User_EcKeyPair,
+    K_opening: KOpening,
+    key: DecryptionKey,
+    request: SignRequest, // to check an evaluation proof against
+}
+
+impl UserSession {
+    pub fn request(public_key: &GE, round1: &Round1, msg: &[u8], digest: MessageDigest, bitsize: usize) -> Result<(Self, Round2), Error> {
+        Self::request_with_rng(public_key, round1, msg, digest, bitsize, &mut os_rng())
+    }
+
+    pub fn request_with_rng<R: RngCore + CryptoRng>(
+        public_key: &GE,
+        round1: &Round1,
+        msg: &[u8],
+        digest: MessageDigest,
+        bitsize: usize,
+        rng: &mut R,
+    ) -> Result<(Self, Round2), Error> {
+        User_EcKeyPair::s2_verify_K1(&round1.K1, &round1.K1_proof)?;
+        Ok(Self::request_for_K1(public_key, round1, &round1.K1, msg, digest, bitsize, rng))
+    }
+
+    // round 2 on K1, which the caller has checked
+    fn request_for_K1<R: RngCore + CryptoRng>(
+        public_key: &GE,
+        round1: &Round1,
+        K1: &GE,
+        msg: &[u8],
+        digest: MessageDigest,
+        bitsize: usize,
+        rng: &mut R,
+    ) -> (Self, Round2) {
+        let nonce = User_EcKeyPair::s2_generate_K_with_rng(K1, rng);
+        let h = digest.hash_to_scalar(msg);
+        let Kx = nonce.Kx.clone();
+        let (request, key) = match round1.backend {
+            Backend::Mpaillier => {
+                let (req, key) = User_Enc_and_NIZK_mpaillier_version::create(&h, &Kx, bitsize, rng);
+                (SignRequest::Mpaillier(req), DecryptionKey::Mpaillier(key))
+            }
+            Backend::Hsmcl => {
+                let (req, key) = User_Enc_and_NIZK_hsmcl_version::create(&h, &Kx, bitsize, rng);
+                (SignRequest::Hsmcl(req), DecryptionKey::Hsmcl(key))
+            }
+            Backend::HsmclGgm => {
+                let (req, key) = User_Enc_and_NIZK_hsmcl_GGM::create(&h, &Kx, bitsize, rng);
+                (SignRequest::HsmclGgm(req), DecryptionKey::Hsmcl(key))
+            }
+        };
+        let K_opening = KOpening {
+            session_id: round1.session_id.clone(),
+            proof: nonce.s2_prove_K_with_rng(K1, rng),
+            blind_factor: sample_bits(rng, 256),
+        };
+        let round2 = Round2 {
+            session_id: round1.session_id.clone(),
+            request: request.clone(),
+            K_commitment: K_opening.commitment(),
+        };
+        let session = Self {
+            session_id: round1.session_id.clone(),
+            backend: round1.backend,
+            public_key: public_key.clone(),
+            h,
+            K1: K1.clone(),
+            nonce,
+            K_opening,
+            key,
+            request,
+        };
+        (session, round2)
+    }
+
+    // not to be sent before the signature is published: K links the two
+    pub fn K_opening(&self) -> &KOpening {
+        &self.K_opening
+    }
+
+    // unblinds the signer's answer into a low-s signature valid under public_key.
+    // An evaluation proof, if the signer sent one, is checked before decrypting.
+    pub fn finalize(self, round3: &Round3) -> Result<Signature, Error> {
+        if round3.session_id != self.session_id {
+            return Err(InvalidProof);
+        }
+        if let Some(proof) = &round3.eval_proof {
+            self.verify_eval(proof, &round3.partial_sig)?;
+        }
+        let k2 = self.nonce.expose_secret().to_big_int();
+        let s = match (&self.key, &round3.partial_sig) {
+            (DecryptionKey::Mpaillier(key), PartialSig::Mpaillier(c)) => s4_sig::<Pallier_AsiaCCS_19>(&k2, c, key)?,
+            (DecryptionKey::Hsmcl(key), PartialSig::Hsmcl(c)) => s4_sig::<HSMCL>(&k2, c, key)?, // rejects c that is not in the key's group
+            _ => return Err(InvalidKey),
+        };
+        let sig = Signature::new(self.nonce.Kx.clone(), s).normalize_s();
+        verify(&self.public_key, &self.h, &sig)?;
+        Ok(sig)
+    }
+
+    // as finalize, for a user that insists on the evaluation proof
+    pub fn finalize_with_proof(self, round3: &Round3) -> Result<Signature, Error> {
+        if round3.eval_proof.is_none() {
+            return Err(InvalidProof);
+        }
+        self.finalize(round3)
+    }
+
+    fn verify_eval(&self, proof: &EvalProof, partial_sig: &PartialSig) -> Result<(), Error> {
+        match (proof, &self.request, partial_sig) {
+            (EvalProof::Mpaillier(proof), SignRequest::Mpaillier(req), PartialSig::Mpaillier(c3)) => s4_verify_eval(req, c3, &self.K1, &self.public_key, proof),
+            (EvalProof::Hsmcl(proof), SignRequest::Hsmcl(req), PartialSig::Hsmcl(c3)) => s4_verify_eval(req, c3, &self.K1, &self.public_key, proof),
+            (EvalProof::Hsmcl(proof), SignRequest::HsmclGgm(req), PartialSig::Hsmcl(c3)) => s4_verify_eval(req, c3, &self.K1, &self.public_key, proof),
+            _ => Err(InvalidProof),
+        }
+    }
+}
+
+// User state during a committed round 1
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserCommitted {
+    pub commit: Round1Commit,
+    rho: BigInt,
+}
+
+impl UserCommitted {
+    pub fn contribute(commit: &Round1Commit) -> (Self, Round1Contribution) {
+        Self::contribute_with_rng(commit, &mut os_rng())
+    }
+
+    pub fn contribute_with_rng<R: RngCore + CryptoRng>(commit: &Round1Commit, rng: &mut R) -> (Self, Round1Contribution) {
+        let rho = sample_bits(rng, 256);
+        let contribution = Round1Contribution {
+            session_id: commit.session_id.clone(),
+            rho: rho.clone(),
+        };
+        let state = Self {
+            commit: commit.clone(),
+            rho,
+        };
+        (state, contribution)
+    }
+
+    // checks the opening and K1's proof, then runs round 2 on K1'
+    pub fn request(self, public_key: &GE, reveal: &Round1Reveal, msg: &[u8], digest: MessageDigest, bitsize: usize) -> Result<(UserSession, Round2), Error> {
+        self.request_with_rng(public_key, reveal, msg, digest, bitsize, &mut os_rng())
+    }
+
+    pub fn request_with_rng<R: RngCore + CryptoRng>(
+        self,
+        public_key: &GE,
+        reveal: &Round1Reveal,
+        msg: &[u8],
+        digest: MessageDigest,
+        bitsize: usize,
+        rng: &mut R,
+    ) -> Result<(UserSession, Round2), Error> {
+        let round1 = &reveal.round1;
+        if round1.session_id != self.commit.session_id || round1.backend != self.commit.backend {
+            return Err(InvalidProof);
+        }
+        let opened = HashCommitment::create_commitment_with_user_defined_randomness(&round1_hash(round1), &reveal.blind_factor);
+        if opened != self.commit.commitment {
+            return Err(InvalidCom);
+        }
+        User_EcKeyPair::s2_verify_K1(&round1.K1, &round1.K1_proof)?;
+        let t = nonce_tweak(&round1.session_id, &self.commit.commitment, &self.rho)?;
+        let G: GE = ECPoint::generator();
+        let tG = G.scalar_mul(&t.get_element());
+        if tG.x_coor() == round1.K1.x_coor() {
+            return Err(InvalidKey); // K1' would be the point at infinity
+        }
+        let K1 = round1.K1.add_point(&tG.get_element());
+        Ok(UserSession::request_for_K1(public_key, round1, &K1, msg, digest, bitsize, rng))
+    }
+}
+
+impl KOpening {
+    fn commitment(&self) -> BigInt {
+        let K_hash = HSha256::create_hash(&[&BigInt::from(self.session_id.as_bytes()), &self.proof.pk.bytes_compressed_to_big_int()]);
+        HashCommitment::create_commitment_with_user_defined_randomness(&K_hash, &self.blind_factor)
+    }
+}
+
+impl SessionReceipt {
+    // sig came from this session: the opening matches the commitment, proves
+    // k2 over the session's K1, and K gives sig's r
+    pub fn verify_opening(&self, opening: &KOpening, sig: &Signature) -> Result<(), Error> {
+        if opening.session_id != self.session_id || opening.proof.base != self.K1 {
+            return Err(InvalidKey);
+        }
+        if opening.commitment() != self.K_commitment {
+            return Err(InvalidCom);
+        }
+        opening.proof.verify().map_err(|_| InvalidProof)?;
+        let Kx = opening.proof.pk.x_coor().ok_or(InvalidKey)?.mod_floor(&FE::q());
+        if Kx != sig.r {
+            return Err(InvalidSig);
+        }
+        Ok(())
+    }
+}
+
+// what the signer commits to: session id, backend, K1 and its proof
+fn round1_hash(round1: &Round1) -> BigInt {
+    HSha256::create_hash(&[
+        &BigInt::from(round1.session_id.as_bytes()),
+        &BigInt::from(round1.backend.to_string().as_bytes()),
+        &round1.K1.bytes_compressed_to_big_int(),
+        &round1.K1_proof.pk.bytes_compressed_to_big_int(),
+        &round1.K1_proof.pk_t_rand_commitment.bytes_compressed_to_big_int(),
+        &round1.K1_proof.challenge_response.to_big_int(),
+    ])
+}
+
+// t = H(session id, commitment, rho) mod q
+fn nonce_tweak(session_id: &str, commitment: &BigInt, rho: &BigInt) -> Result<FE, Error> {
+    let t = HSha256::create_hash(&[&BigInt::from(session_id.as_bytes()), commitment, rho]).mod_floor(&FE::q());
+    if t == BigInt::zero() {
+        return Err(InvalidCom);
+    }
+    Ok(ECScalar::from(&t))
+}
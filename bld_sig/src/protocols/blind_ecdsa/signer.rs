@@ -2,7 +2,7 @@ use std::cmp;
 
 use class_group::primitives::cl_dl_lcm::Ciphertext;
 use class_group::primitives::cl_dl_lcm::Witness;
-use class_group::primitives::cl_dl_lcm::{CLDLProof,  HSMCL};
+use class_group::primitives::cl_dl_lcm::{CLDLProof, PK};
 // use class_group::primitives::cl_dl_lcm::{CLDLProofPublicSetup, HSMCl};
 use curv::arithmetic::traits::*;
 use curv::cryptographic_primitives::commitments::hash_commitment::HashCommitment;
@@ -19,32 +19,251 @@ use curv::GE;
 use crate::*;
 use serde::{Deserialize, Serialize};
 use protocols::asiaccs_zk::ZK_AsiaCCS_19;
+use protocols::eval_zk::{EvalProofHsmcl, EvalProofPaillier};
 use protocols::mpaillier::Pallier_AsiaCCS_19;
+use protocols::range_zk::RANGE_SLACK;
 use protocols::hsmcl_zk::CLDLProof_modified;
+use protocols::validate::{valid_ciphertext, valid_pk};
+use super::generic::{eval_exponents, s3_eval, s3_partial_sig, s3_prove_eval, BlindRequest};
+use super::user::{User_Enc_and_NIZK_hsmcl_GGM, User_Enc_and_NIZK_hsmcl_version, User_Enc_and_NIZK_mpaillier_version};
+use secret::SecretScalar;
+use super::nonce::{derive_nonce, fresh_randomness_with_rng, K1_LABEL};
+use protocols::rng::{os_rng, sample_scalar};
+use rand::{CryptoRng, RngCore};
 
-use crate::Error::{self, InvalidSig};
+use crate::Error::{self, InvalidKey, InvalidProof, InvalidSig};
+
+// Long-term ECDSA key of the signer, public_key = sk * G
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Signer_SigningKey {
+    pub public_key: GE,
+    #[serde(with = "secret::export")]
+    secret_key: SecretScalar,
+}
+
+impl Signer_SigningKey {
+    pub fn keygen() -> Self {
+        Self::keygen_with_rng(&mut os_rng())
+    }
+
+    pub fn keygen_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let secret_key = SecretScalar::new(sample_scalar(rng));
+        let base: GE = ECPoint::generator();
+        let public_key = base.scalar_mul(&secret_key.expose_secret().get_element());
+        Self{
+            public_key,
+            secret_key,
+        }
+    }
+
+    // signing key sk
+    pub fn expose_secret(&self) -> &FE {
+        self.secret_key.expose_secret()
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Signer_EcKeyPair {
     pub public_share: GE,
-    pub secret_share: FE, // should be private variable, changed for test
+    #[serde(with = "secret::export")]
+    secret_share: SecretScalar,
 }
 
 impl Signer_EcKeyPair {
     pub fn s1_generate_K1() -> Self {
+        Self::s1_generate_K1_with_rng(&mut os_rng())
+    }
+
+    pub fn s1_generate_K1_with_rng<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self::from_secret_share(SecretScalar::new(sample_scalar(rng)))
+    }
+
+    // k1 derived from the signing key sk, the session id and fresh randomness,
+    // so a failing RNG alone cannot leak sk through a repeated nonce
+    pub fn s1_generate_K1_hedged(sk: &BigInt, session_id: &[u8]) -> Self {
+        Self::s1_generate_K1_hedged_with_rng(sk, session_id, &mut os_rng())
+    }
+
+    pub fn s1_generate_K1_hedged_with_rng<R: RngCore + CryptoRng>(sk: &BigInt, session_id: &[u8], rng: &mut R) -> Self {
+        Self::s1_derive_K1(sk, session_id, &fresh_randomness_with_rng(rng))
+    }
+
+    pub fn s1_derive_K1(sk: &BigInt, session_id: &[u8], randomness: &[u8]) -> Self {
+        let k1 = derive_nonce(sk, session_id, K1_LABEL, randomness);
+        Self::from_secret_share(SecretScalar::new(k1))
+    }
+
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn from_secret_for_test(k1: FE) -> Self {
+        Self::from_secret_share(SecretScalar::new(k1))
+    }
+
+    fn from_secret_share(secret_share: SecretScalar) -> Self {
         let base: GE = ECPoint::generator();
-        let secret_share: FE = ECScalar::new_random();
-        let public_share = base.scalar_mul(&secret_share.get_element());
+        let public_share = base.scalar_mul(&secret_share.expose_secret().get_element());
         Self{
             public_share,
             secret_share,
         }
     }
+
+    // nonce share k1
+    pub fn expose_secret(&self) -> &FE {
+        self.secret_share.expose_secret()
+    }
+
+    // proof of knowledge of k1, sent with K1 in step 1
+    pub fn s1_prove_K1(&self) -> DLogProof {
+        DLogProof::prove(self.secret_share.expose_secret())
+    }
+
+    // k1 + t, for the nonce fixed jointly with the user (see session.rs)
+    pub fn s1_tweak_K1(&self, t: &FE) -> Self {
+        let k1 = self.secret_share.expose_secret().add(&t.get_element());
+        Self::from_secret_share(SecretScalar::new(k1))
+    }
 }
 
-pub fn s3_scal_and_eval(C1: &BigInt, C2: &BigInt, k1: &BigInt, sk: &BigInt, bitsize: &usize) -> BigInt{
-    let q = FE::q();
-    let k1_inv = k1.invert(&q).unwrap();
-    let key = Pallier_AsiaCCS_19::keygen(bitsize.clone());
-    let c2sk = C2.powm(sk, &key.N_square);
-}
\ No newline at end of file
+// Checks the NIZKs of many step-2 requests at once. On failure returns the
+// indices of the requests whose proofs are invalid or do not match their ciphertexts.
+pub fn s3_verify_batch_mpaillier(requests: &[User_Enc_and_NIZK_mpaillier_version]) -> Result<(), Vec<usize>> {
+    s3_verify_batch_mpaillier_with_slack(requests, RANGE_SLACK)
+}
+
+// range proofs are checked one by one, accepting plaintexts below q * 2^(C + slack)
+pub fn s3_verify_batch_mpaillier_with_slack(requests: &[User_Enc_and_NIZK_mpaillier_version], slack: usize) -> Result<(), Vec<usize>> {
+    let mut bad = requests
+        .iter()
+        .enumerate()
+        .filter(|(_, req)| {
+            req.p1.C != req.C1
+                || req.p2.C != req.C2
+                || req.range1.C != req.C1
+                || req.range2.C != req.C2
+                || !req.range1.verify(slack)
+                || !req.range2.verify(slack)
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<usize>>();
+    let proofs = requests
+        .iter()
+        .flat_map(|req| vec![&req.p1, &req.p2])
+        .collect::<Vec<&ZK_AsiaCCS_19>>();
+    if let Err(bad_proofs) = ZK_AsiaCCS_19::verify_batch(&proofs) {
+        bad.extend(bad_proofs.iter().map(|i| i / 2)); // p1 and p2 of request i are at 2i and 2i + 1
+    }
+    bad.sort();
+    bad.dedup();
+    match bad.is_empty() {
+        true => Ok(()),
+        false => Err(bad),
+    }
+}
+
+pub fn s3_verify_batch_hsmcl_ggm(requests: &[User_Enc_and_NIZK_hsmcl_GGM]) -> Result<(), Vec<usize>> {
+    let mut bad = requests
+        .iter()
+        .enumerate()
+        .filter(|(_, req)| {
+            !valid_pk(&req.proof.pk)
+                || !valid_ciphertext(&req.Enc_h, &req.proof.pk)
+                || !valid_ciphertext(&req.Enc_Kx, &req.proof.pk)
+                || req.proof.x1 != req.Enc_h.c1
+                || req.proof.x2 != req.Enc_h.c2
+                || req.proof.y1 != req.Enc_Kx.c1
+                || req.proof.y2 != req.Enc_Kx.c2
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<usize>>();
+    let proofs = requests
+        .iter()
+        .map(|req| &req.proof)
+        .collect::<Vec<&zkPoKEncProof>>();
+    if let Err(bad_proofs) = zkPoKEncProof::verify_batch(&proofs) {
+        bad.extend(bad_proofs);
+    }
+    bad.sort();
+    bad.dedup();
+    match bad.is_empty() {
+        true => Ok(()),
+        false => Err(bad),
+    }
+}
+
+// Step 3: (Enc(h) * Enc(Kx)^sk)^(k1^-1) = Enc(k1^-1 * (h + Kx * sk)). The
+// request's proofs are checked first, including that h and Kx are below q up
+// to the slack, and both ciphertexts must be under the same key.
+pub fn s3_partial_sig_mpaillier(request: &User_Enc_and_NIZK_mpaillier_version, k1: &BigInt, sk: &BigInt) -> Result<BigInt, Error> {
+    s3_partial_sig(request, k1, sk)
+}
+
+pub fn s3_partial_sig_mpaillier_with_slack(request: &User_Enc_and_NIZK_mpaillier_version, k1: &BigInt, sk: &BigInt, slack: usize) -> Result<BigInt, Error> {
+    s3_check_mpaillier(request, slack)?;
+    s3_eval::<Pallier_AsiaCCS_19>(&request.public_key(), &request.C1, &request.C2, k1, sk)
+}
+
+pub fn s3_partial_sig_hsmcl(request: &User_Enc_and_NIZK_hsmcl_version, k1: &BigInt, sk: &BigInt) -> Result<Ciphertext, Error> {
+    s3_partial_sig(request, k1, sk)
+}
+
+pub fn s3_partial_sig_hsmcl_ggm(request: &User_Enc_and_NIZK_hsmcl_GGM, k1: &BigInt, sk: &BigInt) -> Result<Ciphertext, Error> {
+    s3_partial_sig(request, k1, sk)
+}
+
+// the checks of s3_partial_sig_mpaillier, see BlindRequest
+pub(crate) fn s3_check_mpaillier(request: &User_Enc_and_NIZK_mpaillier_version, slack: usize) -> Result<(), Error> {
+    if s3_verify_batch_mpaillier_with_slack(&[request.clone()], slack).is_err() {
+        return Err(InvalidProof);
+    }
+    let (p1, p2) = (&request.p1, &request.p2);
+    let (range1, range2) = (&request.range1, &request.range2);
+    if p1.N != p2.N || p1.N_square != p2.N_square || p1.g != p2.g
+        || range1.N != p1.N || range2.N != p1.N || range1.g != p1.g || range2.g != p1.g {
+        return Err(InvalidKey);
+    }
+    Ok(())
+}
+
+pub(crate) fn s3_check_hsmcl(request: &User_Enc_and_NIZK_hsmcl_version) -> Result<(), Error> {
+    let (p1, p2) = (&request.p1, &request.p2);
+    let c: usize = 10; // as in the user's proofs
+    if !valid_pk(&p1.pk) {
+        return Err(InvalidKey);
+    }
+    if !valid_ciphertext(&request.Enc_h, &p1.pk) || !valid_ciphertext(&request.Enc_Kx, &p1.pk) {
+        return Err(InvalidProof);
+    }
+    if p1.verify(c).is_err() || p2.verify(c).is_err() {
+        return Err(InvalidProof);
+    }
+    if !same_ciphertext(&p1.ciphertext, &request.Enc_h) || !same_ciphertext(&p2.ciphertext, &request.Enc_Kx) {
+        return Err(InvalidProof);
+    }
+    if !same_pk(&p1.pk, &p2.pk) {
+        return Err(InvalidKey);
+    }
+    Ok(())
+}
+
+pub(crate) fn s3_check_hsmcl_ggm(request: &User_Enc_and_NIZK_hsmcl_GGM) -> Result<(), Error> {
+    s3_verify_batch_hsmcl_ggm(&[request.clone()]).map_err(|_| InvalidProof)
+}
+
+// Optional proof for the user that c3 = (C1 * C2^sk)^(k1^-1) for the K1 of
+// round 1 and the published key, so that c3 cannot carry anything else
+pub fn s3_prove_eval_mpaillier(request: &User_Enc_and_NIZK_mpaillier_version, k1: &BigInt, sk: &BigInt, c3: &BigInt) -> Result<EvalProofPaillier, Error> {
+    s3_prove_eval(request, k1, sk, c3, &mut os_rng())
+}
+
+pub fn s3_prove_eval_hsmcl(Enc_h: &Ciphertext, Enc_Kx: &Ciphertext, k1: &BigInt, sk: &BigInt, c3: &Ciphertext) -> Result<EvalProofHsmcl, Error> {
+    let (a, b) = eval_exponents(k1, sk)?;
+    EvalProofHsmcl::prove(Enc_h, Enc_Kx, c3, &a, &b)
+}
+
+fn same_ciphertext(a: &Ciphertext, b: &Ciphertext) -> bool {
+    a.c1 == b.c1 && a.c2 == b.c2
+}
+
+fn same_pk(a: &PK, b: &PK) -> bool {
+    a.q == b.q && a.delta_k == b.delta_k && a.delta_q == b.delta_q && a.gq == b.gq && a.h == b.h && a.stilde == b.stilde
+}
@@ -1,73 +1,58 @@
-use class_group::primitives::cl_dl_lcm::HSMCL;
-use curv::cryptographic_primitives::hashing::traits::Hash;
-use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
 use curv::elliptic::curves::traits::{ECPoint, ECScalar};
 use curv::BigInt;
 use curv::{FE, GE};
 use crate::*;
+use digest::MessageDigest;
+use protocols::backend::AdditivelyHomomorphicBackend;
+use protocols::mpaillier::Pallier_AsiaCCS_19;
+use protocols::rng::os_rng;
+use signature::{verify, Signature};
+use super::generic::*;
 use super::signer::*;
 use super::user::*;
 
-pub fn test_sign_mpaillier(bitsize: &usize, message: &BigInt) -> (BigInt, BigInt) {
+// step 1 and the user's nonce: the signing key, k1, k2 and Kx
+fn nonces() -> (Signer_SigningKey, BigInt, BigInt, BigInt) {
+    let signing_key = Signer_SigningKey::keygen(); // sk and its public key
     let ec_key1: Signer_EcKeyPair = Signer_EcKeyPair::s1_generate_K1(); // K1 and k1
     let K1 = ec_key1.public_share;
-    let k1 = ec_key1.secret_share.to_big_int();
+    let k1 = ec_key1.expose_secret().to_big_int();
     let ec_key2: User_EcKeyPair = User_EcKeyPair::s2_generate_K(&K1); // K and k2
-    let K = ec_key2.public_share;
-    let k2 = ec_key2.secret_share.to_big_int();
-    let Kx = ec_key2.Kx;
-    let h = HSha256::create_hash(&[&message]);
-    let s2_user: User_Enc_and_NIZK_mpaillier_version = 
-    User_Enc_and_NIZK_mpaillier_version::s2_mpaillier_encrypt_and_nizk(&h, &Kx, &bitsize);
-    assert!(s2_user.p1.verify(), true);
-    assert!(s2_user.p2.verify(), true);
-    let sk_fe: FE = ECScalar::new_random();
-    let sk = sk_fe.to_big_int();
-    let s3_signer: BigInt = s3_partial_sig_mpaillier(&s2_user.C1, &s2_user.C2, &k1, &sk, &bitsize);
-    let k2_inv = k2.invert(&FE::q()).unwrap();
-    let s = s4_sig_by_mpaillier(&k2_inv, &s3_signer, &s2_user.key);
-    (Kx, s)
+    let k2 = ec_key2.expose_secret().to_big_int();
+    (signing_key, k1, k2, ec_key2.Kx)
 }
 
-pub fn test_sign_hsmcl(lam: &usize, message: &BigInt) -> (BigInt, BigInt) {
-    let ec_key1: Signer_EcKeyPair = Signer_EcKeyPair::s1_generate_K1(); // K1 and k1
-    let K1 = ec_key1.public_share;
-    let k1 = ec_key1.secret_share.to_big_int();
-    let ec_key2: User_EcKeyPair = User_EcKeyPair::s2_generate_K(&K1); // K and k2
-    let K = ec_key2.public_share;
-    let k2 = ec_key2.secret_share.to_big_int();
-    let Kx = ec_key2.Kx;
-    let h = HSha256::create_hash(&[&message]);
-    let s2_user: User_Enc_and_NIZK_hsmcl_version = 
-    User_Enc_and_NIZK_hsmcl_version::s2_hsmcl_encrypt_and_nizk(&h, &Kx, &lam);
-    assert!(s2_user.p1.verify(C).is_ok());
-    assert!(s2_user.p2.verify(C).is_ok());
-    let sk_fe: FE = ECScalar::new_random();
-    let sk = sk_fe.to_big_int();
-    let s3_signer = s3_partial_sig_hsmcl(&s2_user.Enc_h, &s2_user.Enc_Kx, &k1, &sk, &lam);
-    let k2_inv = k2.invert(&FE::q()).unwrap();
-    let hsmcl = HSMCL::keygen(&FE::q(), &lam);
-    let s = s4_sig_by_hsmcl(&s3_signer, &k2_inv, &s2_user.hsmcl);
+// steps 2 to 4 over any request type
+fn sign_with<B, Q>(bitsize: &usize, message: &[u8]) -> (BigInt, BigInt)
+where
+    B: AdditivelyHomomorphicBackend,
+    Q: BlindRequest<Backend = B>,
+{
+    let (signing_key, k1, k2, Kx) = nonces();
+    let sk = signing_key.expose_secret().to_big_int();
+    let h = MessageDigest::Sha256.hash_to_scalar(message);
+    let (s2_user, key) = Q::create(&h, &Kx, *bitsize, &mut os_rng());
+    let s3_signer = s3_partial_sig(&s2_user, &k1, &sk).unwrap();
+    let s = s4_sig::<B>(&k2, &s3_signer, &key).unwrap();
+    assert!(verify(&signing_key.public_key, &h, &Signature::new(Kx.clone(), s.clone())).is_ok());
     (Kx, s)
 }
 
-pub fn test_sign_hsmcl_ggm(lam: &usize, message: &BigInt) -> (BigInt, BigInt) {
-    let ec_key1: Signer_EcKeyPair = Signer_EcKeyPair::s1_generate_K1(); // K1 and k1
-    let K1 = ec_key1.public_share;
-    let k1 = ec_key1.secret_share.to_big_int();
-    let ec_key2: User_EcKeyPair = User_EcKeyPair::s2_generate_K(&K1); // K and k2
-    let K = ec_key2.public_share;
-    let k2 = ec_key2.secret_share.to_big_int();
-    let Kx = ec_key2.Kx;
-    let h = HSha256::create_hash(&[&message]);
-    let s2_user: User_Enc_and_NIZK_hsmcl_GGM = 
-    User_Enc_and_NIZK_hsmcl_GGM::s2_hsmcl_encrypt_and_ggm_nizk(&h, &Kx, &lam);
-    assert!(s2_user.proof.verify().is_ok());
-    let sk_fe: FE = ECScalar::new_random();
-    let sk = sk_fe.to_big_int();
-    let s3_signer = s3_partial_sig_hsmcl(&s2_user.Enc_h, &s2_user.Enc_Kx, &k1, &sk, &lam);
-    let k2_inv = k2.invert(&FE::q()).unwrap();
-    let hsmcl = HSMCL::keygen(&FE::q(), &lam);
-    let s = s4_sig_by_hsmcl(&s3_signer, &k2_inv, &s2_user.hsmcl);
-    (Kx, s)
-}
\ No newline at end of file
+// steps 2 to 4 over any backend
+pub fn test_sign<B: AdditivelyHomomorphicBackend>(bitsize: &usize, message: &[u8]) -> (BigInt, BigInt) {
+    sign_with::<B, User_Enc_and_NIZK<B>>(bitsize, message)
+}
+
+pub fn test_sign_mpaillier(bitsize: &usize, message: &[u8]) -> (BigInt, BigInt) {
+    test_sign::<Pallier_AsiaCCS_19>(bitsize, message)
+}
+
+pub fn test_sign_hsmcl(lam: &usize, message: &[u8]) -> (BigInt, BigInt) {
+    test_sign::<HSMCL>(lam, message)
+}
+
+// the joint GGM proof covers both ciphertexts, so it is its own request type
+// rather than a per-ciphertext EncProof
+pub fn test_sign_hsmcl_ggm(lam: &usize, message: &[u8]) -> (BigInt, BigInt) {
+    sign_with::<HSMCL, User_Enc_and_NIZK_hsmcl_GGM>(lam, message)
+}
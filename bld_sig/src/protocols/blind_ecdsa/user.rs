@@ -19,17 +19,28 @@ use serde::{Deserialize, Serialize};
 use crate::*;
 use paillier::keygen::PrimeSampable;
 
-use crate::Error::{self, InvalidSig};
+use crate::Error::{self, InvalidKey, InvalidProof, InvalidSig};
 
 use protocols::asiaccs_zk::ZK_AsiaCCS_19;
+use protocols::dlog::DLogProofWithBase;
+use protocols::eval_zk::{EvalProofHsmcl, EvalProofPaillier};
 use protocols::mpaillier::Pallier_AsiaCCS_19;
 use protocols::hsmcl_zk::CLDLProof_modified;
 use protocols::hsmcl_zk::Witness;
+use protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
+use protocols::range_zk::{PaillierRangeProof, RANGE_SLACK};
+use digest::MessageDigest;
+use secret::{HsmclKey, SecretScalar};
+use super::generic::s4_sig;
+use super::nonce::{derive_nonce, fresh_randomness_with_rng, K2_LABEL};
+use protocols::rng::{hsmcl_keygen_with_setup, os_rng, sample_below, sample_scalar};
+use rand::{CryptoRng, RngCore};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct User_EcKeyPair {
     pub public_share: GE,
-    pub secret_share: FE, // should be private variable, changed for test
+    #[serde(with = "secret::export")]
+    secret_share: SecretScalar,
     pub Kx: BigInt,
 }
 
@@ -39,6 +50,8 @@ pub struct User_Enc_and_NIZK_mpaillier_version {
     pub C2: BigInt, // should be private variable, changed for test
     pub p1: ZK_AsiaCCS_19,
     pub p2: ZK_AsiaCCS_19,
+    pub range1: PaillierRangeProof, // h < q
+    pub range2: PaillierRangeProof, // Kx < q
 }
 
 
@@ -59,8 +72,34 @@ pub struct User_Enc_and_NIZK_hsmcl_GGM {
 
 impl User_EcKeyPair {
     pub fn s2_generate_K(K1: &GE) -> Self {
-        let secret_share: FE = ECScalar::new_random();
-        let public_share = K1.scalar_mul(&secret_share.get_element());
+        Self::s2_generate_K_with_rng(K1, &mut os_rng())
+    }
+
+    pub fn s2_generate_K_with_rng<R: RngCore + CryptoRng>(K1: &GE, rng: &mut R) -> Self {
+        Self::from_secret_share(K1, SecretScalar::new(sample_scalar(rng)))
+    }
+
+    // k2 derived from a user secret, the session id and fresh randomness
+    pub fn s2_generate_K_hedged(K1: &GE, secret: &BigInt, session_id: &[u8]) -> Self {
+        Self::s2_generate_K_hedged_with_rng(K1, secret, session_id, &mut os_rng())
+    }
+
+    pub fn s2_generate_K_hedged_with_rng<R: RngCore + CryptoRng>(K1: &GE, secret: &BigInt, session_id: &[u8], rng: &mut R) -> Self {
+        Self::s2_derive_K(K1, secret, session_id, &fresh_randomness_with_rng(rng))
+    }
+
+    pub fn s2_derive_K(K1: &GE, secret: &BigInt, session_id: &[u8], randomness: &[u8]) -> Self {
+        let k2 = derive_nonce(secret, session_id, K2_LABEL, randomness);
+        Self::from_secret_share(K1, SecretScalar::new(k2))
+    }
+
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn from_secret_for_test(K1: &GE, k2: FE) -> Self {
+        Self::from_secret_share(K1, SecretScalar::new(k2))
+    }
+
+    fn from_secret_share(K1: &GE, secret_share: SecretScalar) -> Self {
+        let public_share = K1.scalar_mul(&secret_share.expose_secret().get_element());
         let Kx = public_share.x_coor().unwrap().mod_floor(&FE::q());
         Self{
             public_share,
@@ -68,48 +107,102 @@ impl User_EcKeyPair {
             Kx,
         }
     }
+
+    // nonce share k2
+    pub fn expose_secret(&self) -> &FE {
+        self.secret_share.expose_secret()
+    }
+
+    // checks the signer's proof for K1 before k2 is chosen
+    pub fn s2_verify_K1(K1: &GE, proof: &DLogProof) -> Result<(), Error> {
+        if &proof.pk != K1 {
+            return Err(InvalidKey);
+        }
+        DLogProof::verify(proof).map_err(|_| InvalidProof)
+    }
+
+    // proof of knowledge of k2 with K = k2 * K1. It reveals K and so r, which
+    // the signer must not see before the signature is published: it is for
+    // showing afterwards that a signature came from this session.
+    pub fn s2_prove_K(&self, K1: &GE) -> DLogProofWithBase {
+        self.s2_prove_K_with_rng(K1, &mut os_rng())
+    }
+
+    pub fn s2_prove_K_with_rng<R: RngCore + CryptoRng>(&self, K1: &GE, rng: &mut R) -> DLogProofWithBase {
+        DLogProofWithBase::prove_with_rng(K1, self.secret_share.expose_secret(), rng)
+    }
 }
 
 impl User_Enc_and_NIZK_mpaillier_version {
     pub fn s2_mpaillier_encrypt_and_nizk(h: &BigInt, Kx: &BigInt, bitsize: &usize) -> Self {
-        let key = Pallier_AsiaCCS_19::keygen(bitsize.clone());
-        let r1_fe: FE = ECScalar::new_random();
-        let r2_fe: FE = ECScalar::new_random();
+        Self::s2_mpaillier_encrypt_and_nizk_with_rng(h, Kx, bitsize, &mut os_rng())
+    }
+
+    // h = digest(msg) reduced mod q
+    pub fn s2_mpaillier_encrypt_and_nizk_from_message(msg: &[u8], digest: MessageDigest, Kx: &BigInt, bitsize: &usize) -> Self {
+        Self::s2_mpaillier_encrypt_and_nizk(&digest.hash_to_scalar(msg), Kx, bitsize)
+    }
+
+    pub fn s2_mpaillier_encrypt_and_nizk_with_rng<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, bitsize: &usize, rng: &mut R) -> Self {
+        Self::s2_mpaillier_encrypt_and_nizk_with_key(h, Kx, bitsize, rng).0
+    }
+
+    // also returns the decryption key, which the user keeps for step 4
+    pub fn s2_mpaillier_encrypt_and_nizk_with_key<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, bitsize: &usize, rng: &mut R) -> (Self, Pallier_AsiaCCS_19) {
+        let key = Pallier_AsiaCCS_19::keygen_with_rng(bitsize.clone(), rng);
+        let pp = PrecomputedPaillier::from_key(&key);
+        let r1_fe: FE = sample_scalar(rng);
+        let r2_fe: FE = sample_scalar(rng);
         let r1 = r1_fe.to_big_int();
         let r2 = r2_fe.to_big_int();
-        let C1 = Pallier_AsiaCCS_19::encrypt(h, &r1, &key.N, &key.N_square, &key.g, &key.q);
-        let C2 = Pallier_AsiaCCS_19::encrypt(Kx, &r2, &key.N, &key.N_square, &key.g, &key.q);
-        let p1 =  ZK_AsiaCCS_19::prove(
-            key.N.clone(),
-            key.N_square.clone(),
-            key.q.clone(),
-            key.g.clone(),
+        let C1 = pp.encrypt(h, &r1);
+        let C2 = pp.encrypt(Kx, &r2);
+        let p1 =  ZK_AsiaCCS_19::prove_precomputed_with_rng(
+            &pp,
             C1.clone(),
             h.clone(), 
             r1.clone(),
+            rng,
         );
-        let p2 =  ZK_AsiaCCS_19::prove(
-            key.N.clone(),
-            key.N_square.clone(),
-            key.q.clone(),
-            key.g.clone(),
+        let p2 =  ZK_AsiaCCS_19::prove_precomputed_with_rng(
+            &pp,
             C2.clone(),
             Kx.clone(), 
             r2.clone(),
+            rng,
         );
-        Self {
+        let range1 = PaillierRangeProof::prove_with_rng(&pp, C1.clone(), h.clone(), r1, RANGE_SLACK, rng);
+        let range2 = PaillierRangeProof::prove_with_rng(&pp, C2.clone(), Kx.clone(), r2, RANGE_SLACK, rng);
+        let request = Self {
             C1,
             C2,
             p1,
             p2,
-        }
+            range1,
+            range2,
+        };
+        (request, key)
     }
 }
 
 
 impl User_Enc_and_NIZK_hsmcl_version {
     pub fn s2_hsmcl_encrypt_and_ggm_nizk(h: &BigInt, Kx: &BigInt, lam: &usize) -> Self {
-        unsafe { pari_init(10000000000, 2) };
+        Self::s2_hsmcl_encrypt_and_ggm_nizk_with_rng(h, Kx, lam, &mut os_rng())
+    }
+
+    // h = digest(msg) reduced mod q
+    pub fn s2_hsmcl_encrypt_and_ggm_nizk_from_message(msg: &[u8], digest: MessageDigest, Kx: &BigInt, lam: &usize) -> Self {
+        Self::s2_hsmcl_encrypt_and_ggm_nizk(&digest.hash_to_scalar(msg), Kx, lam)
+    }
+
+    pub fn s2_hsmcl_encrypt_and_ggm_nizk_with_rng<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, lam: &usize, rng: &mut R) -> Self {
+        Self::s2_hsmcl_encrypt_and_ggm_nizk_with_key(h, Kx, lam, rng).0
+    }
+
+    // also returns the decryption key, which the user keeps for step 4
+    pub fn s2_hsmcl_encrypt_and_ggm_nizk_with_key<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, lam: &usize, rng: &mut R) -> (Self, HsmclKey) {
+        let _pari = pari::enter_or_panic();
         let q = str::parse(
             "115792089237316195423570985008687907852837564279074904382605163141518161494337",
         )
@@ -119,27 +212,29 @@ impl User_Enc_and_NIZK_hsmcl_version {
             "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
         ).unwrap();
     
-        let hsmcl = HSMCL::keygen_with_setup(&q, lam, &seed);
-        let r1 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
-        let r2 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
-        let Enc_h = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &h, &r1);
-        let Enc_Kx = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &Kx, &r2);
-        let witness = Witness { x: h.clone(), r: r1.clone(), };
-        let witness = Witness { x: Kx.clone(), r: r2.clone(), };
+        let hsmcl = hsmcl_keygen_with_setup(rng, &q, lam, &seed);
+        let ppk = PrecomputedPK::new(&hsmcl.pk);
+        let r1 = sample_below(rng, &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+        let r2 = sample_below(rng, &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+        let Enc_h = ppk.encrypt_predefined_randomness(&h, &r1);
+        let Enc_Kx = ppk.encrypt_predefined_randomness(&Kx, &r2);
+        let witness_h = Witness { x: h.clone(), r: r1.clone(), };
+        let witness_Kx = Witness { x: Kx.clone(), r: r2.clone(), };
         let h_fe: FE = ECScalar::from(&h);
         let Kx_fe: FE = ECScalar::from(&Kx);
         let gh = GE::generator() * h_fe;
         let gKx = GE::generator() * Kx_fe;
         let c: usize = 10;
-        let p1 = CLDLProof_modified::prove(witness.clone(), hsmcl.pk.clone(), Enc_h.clone(), gh, seed.clone(), c.clone());
-        let p2 = CLDLProof_modified::prove(witness, hsmcl.pk.clone(), Enc_Kx.clone(), gKx, seed, c.clone());
+        let p1 = CLDLProof_modified::prove_precomputed_with_rng(witness_h, &ppk, Enc_h.clone(), gh, seed.clone(), c.clone(), rng);
+        let p2 = CLDLProof_modified::prove_precomputed_with_rng(witness_Kx, &ppk, Enc_Kx.clone(), gKx, seed, c.clone(), rng);
 
-        Self {
+        let request = Self {
             Enc_h,
             Enc_Kx,
             p1,
             p2,
-        }
+        };
+        (request, HsmclKey::from_hsmcl(hsmcl))
         
     }
 
@@ -147,7 +242,21 @@ impl User_Enc_and_NIZK_hsmcl_version {
 
 impl User_Enc_and_NIZK_hsmcl_GGM {
     pub fn s2_hsmcl_encrypt_and_ggm_nizk(h: &BigInt, Kx: &BigInt, lam: &usize) -> Self {
-        unsafe { pari_init(10000000000, 2) };
+        Self::s2_hsmcl_encrypt_and_ggm_nizk_with_rng(h, Kx, lam, &mut os_rng())
+    }
+
+    // h = digest(msg) reduced mod q
+    pub fn s2_hsmcl_encrypt_and_ggm_nizk_from_message(msg: &[u8], digest: MessageDigest, Kx: &BigInt, lam: &usize) -> Self {
+        Self::s2_hsmcl_encrypt_and_ggm_nizk(&digest.hash_to_scalar(msg), Kx, lam)
+    }
+
+    pub fn s2_hsmcl_encrypt_and_ggm_nizk_with_rng<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, lam: &usize, rng: &mut R) -> Self {
+        Self::s2_hsmcl_encrypt_and_ggm_nizk_with_key(h, Kx, lam, rng).0
+    }
+
+    // also returns the decryption key, which the user keeps for step 4
+    pub fn s2_hsmcl_encrypt_and_ggm_nizk_with_key<R: RngCore + CryptoRng>(h: &BigInt, Kx: &BigInt, lam: &usize, rng: &mut R) -> (Self, HsmclKey) {
+        let _pari = pari::enter_or_panic();
         let q = str::parse(
             "115792089237316195423570985008687907852837564279074904382605163141518161494337",
         )
@@ -156,16 +265,17 @@ impl User_Enc_and_NIZK_hsmcl_GGM {
             "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
         ).unwrap();
 
-        let hsmcl = HSMCL::keygen_with_setup(&q, lam, &seed);
-        let r1 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
-        let r2 = BigInt::sample_below(&(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
-        let Enc_h = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &h, &r1);
+        let hsmcl = hsmcl_keygen_with_setup(rng, &q, lam, &seed);
+        let ppk = PrecomputedPK::new(&hsmcl.pk);
+        let r1 = sample_below(rng, &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+        let r2 = sample_below(rng, &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+        let Enc_h = ppk.encrypt_predefined_randomness(&h, &r1);
         let x1 = Enc_h.c1.clone();
         let x2 = Enc_h.c2.clone();
-        let Enc_Kx = HSMCL::encrypt_predefined_randomness(&hsmcl.pk, &Kx, &r2);
+        let Enc_Kx = ppk.encrypt_predefined_randomness(&Kx, &r2);
         let y1 = Enc_Kx.c1.clone();
         let y2 = Enc_Kx.c2.clone();
-        let SK_fe: FE = FE::new_random();
+        let SK_fe: FE = sample_scalar(rng);
         let SK = SK_fe.to_big_int();
         let g = GE::generator(); // ECC generator
         let PK = g.clone() * SK_fe;
@@ -174,9 +284,10 @@ impl User_Enc_and_NIZK_hsmcl_GGM {
         let B = &two_pow_exp * &hsmcl.pk.stilde;
         let minus_one = BigInt::from(-1);
         let minus_B = &minus_one * &B; // = -B
-        let proof = zkPoKEncProof::prove( // contain pk well-formedness
+        let proof = zkPoKEncProof::prove_precomputed_with_rng( // contain pk well-formedness
             g.clone(), 
-            hsmcl.clone(), 
+            &hsmcl,
+            &ppk,
             h.clone(),
             Kx.clone(),
             r1.clone(),
@@ -190,22 +301,48 @@ impl User_Enc_and_NIZK_hsmcl_GGM {
             B.clone(),
             minus_B.clone(),
             seed.clone(),
+            rng,
         );
-        Self {
+        let request = Self {
             Enc_h,
             Enc_Kx,
             proof,
-        }
+        };
+        (request, HsmclKey::from_hsmcl(hsmcl))
         
     }
 
 }
 
-pub fn s4_sig_by_mpaillier(k2: BigInt, c: BigInt, key: Pallier_AsiaCCS_19) -> BigInt {
-    let q = FE::q();
-    let dec = Pallier_AsiaCCS_19::decrypt(&c, key);
-    let k2_inv = k2.invert(&q).unwrap();
-    let prod_ = dec * &k2_inv;
-    let prod = prod_.div_floor(&q);
-    prod
-}
\ No newline at end of file
+// Step 4: s = k2^-1 * Dec(c) mod q, where Dec(c) = k1^-1 * (h + Kx * sk)
+pub fn s4_sig_by_mpaillier(k2: &BigInt, c: &BigInt, key: &Pallier_AsiaCCS_19) -> Result<BigInt, Error> {
+    s4_sig::<Pallier_AsiaCCS_19>(k2, c, key)
+}
+
+// Checks the signer's optional evaluation proof against this session's
+// ciphertexts, K1 and the signer's key, before c3 is decrypted
+pub fn s4_verify_eval_mpaillier(proof: &EvalProofPaillier, C1: &BigInt, C2: &BigInt, c3: &BigInt, N: &BigInt, K1: &GE, public_key: &GE) -> Result<(), Error> {
+    if &proof.C1 != C1 || &proof.C2 != C2 || &proof.c3 != c3 || &proof.N != N || &proof.K1 != K1 || &proof.pk != public_key {
+        return Err(InvalidProof);
+    }
+    match proof.verify() {
+        true => Ok(()),
+        false => Err(InvalidProof),
+    }
+}
+
+pub fn s4_verify_eval_hsmcl(proof: &EvalProofHsmcl, Enc_h: &Ciphertext, Enc_Kx: &Ciphertext, c3: &Ciphertext, K1: &GE, public_key: &GE) -> Result<(), Error> {
+    let same = |a: &Ciphertext, b: &Ciphertext| a.c1 == b.c1 && a.c2 == b.c2;
+    if !same(&proof.C1, Enc_h) || !same(&proof.C2, Enc_Kx) || !same(&proof.c3, c3) || &proof.K1 != K1 || &proof.pk != public_key {
+        return Err(InvalidProof);
+    }
+    match proof.verify() {
+        true => Ok(()),
+        false => Err(InvalidProof),
+    }
+}
+
+// rejects a c that is not a pair of forms of the key's discriminant
+pub fn s4_sig_by_hsmcl(k2: &BigInt, c: &Ciphertext, key: &HsmclKey) -> Result<BigInt, Error> {
+    s4_sig::<HSMCL>(k2, c, key)
+}
@@ -0,0 +1,94 @@
+use crate::*;
+use curv::cryptographic_primitives::proofs::ProofError;
+use serde::{Deserialize, Serialize};
+use protocols::rng::{os_rng, sample_scalar};
+use protocols::sigma::{fs_prove, fs_verify, SigmaProtocol};
+use rand::{CryptoRng, RngCore};
+
+// Schnorr proof of knowledge of x with pk = x * base, for a base other than
+// the generator (curv's DLogProof only covers base G). Used for the user's
+// nonce share: K = k2 * K1.
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DLogProofWithBase {
+    pub base: GE,
+    pub pk: GE,
+    pub commitment: GE, // t * base
+    pub response: FE,   // t - e * x
+}
+
+impl DLogProofWithBase {
+    pub fn prove(base: &GE, x: &FE) -> Self {
+        Self::prove_with_rng(base, x, &mut os_rng())
+    }
+
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(base: &GE, x: &FE, rng: &mut R) -> Self {
+        let pk = base.scalar_mul(&x.get_element());
+        let (commitment, response) = fs_prove(&DLogWithBase { base, pk: &pk }, x, rng);
+        DLogProofWithBase {
+            base: base.clone(),
+            pk,
+            commitment,
+            response,
+        }
+    }
+
+    pub fn verify(&self) -> Result<(), ProofError> {
+        let statement = DLogWithBase { base: &self.base, pk: &self.pk };
+        match fs_verify(&statement, &self.commitment, &self.response) {
+            true => Ok(()),
+            false => Err(ProofError),
+        }
+    }
+}
+
+// The Sigma protocol behind DLogProofWithBase, with challenges in Z_q
+pub struct DLogWithBase<'a> {
+    pub base: &'a GE,
+    pub pk: &'a GE,
+}
+
+impl SigmaProtocol for DLogWithBase<'_> {
+    type Witness = FE;
+    type Commitment = GE;
+    type Randomness = FE;
+    type Response = FE;
+
+    fn challenge_bound(&self) -> BigInt {
+        FE::q()
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        let G: GE = ECPoint::generator();
+        vec![
+            G.bytes_compressed_to_big_int(),
+            self.base.bytes_compressed_to_big_int(),
+            self.pk.bytes_compressed_to_big_int(),
+        ]
+    }
+
+    fn commitment_hash_input(&self, a: &GE) -> Vec<BigInt> {
+        vec![a.bytes_compressed_to_big_int()]
+    }
+
+    fn commit<R: RngCore + CryptoRng>(&self, _x: &FE, rng: &mut R) -> (GE, FE) {
+        let t: FE = sample_scalar(rng);
+        (self.base.scalar_mul(&t.get_element()), t)
+    }
+
+    fn respond(&self, x: &FE, t: FE, e: &BigInt) -> FE {
+        let e: FE = ECScalar::from(e);
+        t.sub(&e.mul(&x.get_element()).get_element())
+    }
+
+    // response * base + e * pk == commitment
+    fn verify(&self, a: &GE, e: &BigInt, z: &FE) -> bool {
+        let e: FE = ECScalar::from(e);
+        let lhs = self.base.scalar_mul(&z.get_element());
+        let rhs = self.pk.scalar_mul(&e.get_element());
+        if lhs.x_coor() == rhs.x_coor() {
+            return false; // lhs = rhs, or a sum at infinity that curv cannot represent
+        }
+        &lhs.add_point(&rhs.get_element()) == a
+    }
+}
@@ -0,0 +1,382 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use class_group::primitives::cl_dl_lcm::Ciphertext;
+use curv::BigInt;
+use curv::{FE, GE};
+use protocols::multiexp::multi_exp_mod;
+use protocols::rng::{os_rng, sample_below};
+use protocols::sigma::{fs_prove, fs_verify, Repeat, SigmaProtocol};
+use protocols::validate::{discriminant, valid_group_element, valid_qf};
+use rand::{CryptoRng, RngCore};
+
+// Proofs from the signer that its step-3 ciphertext c3 was computed from the
+// user's C1 = Enc(h) and C2 = Enc(Kx) as
+//   c3 = C1^a * C2^b with a * K1 = G and b * K1 = pk
+// for the K1 of round 1 and the signer's public key pk, i.e. a = k1^-1 and
+// b = sk * k1^-1 (b is not reduced mod q, as in the evaluation itself).
+// The ciphertext groups have unknown order, so the responses are taken over
+// the integers, masked by rho < q^2 * 2^(C + SECURITY_PARAMETER), with C-bit
+// challenges over SECURITY_PARAMETER / C rounds as in ZK_AsiaCCS_19. Per round:
+//   T1 = rho_a * K1, T2 = rho_b * K1, T3 = C1^rho_a * C2^rho_b
+//   z_a = rho_a + e * a, z_b = rho_b + e * b
+//   z_a * K1 == T1 + e * G, z_b * K1 == T2 + e * pk, C1^z_a * C2^z_b == T3 * c3^e
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EvalResponse {
+    pub z_a: BigInt,
+    pub z_b: BigInt,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EvalProofPaillier {
+    pub C1: BigInt,
+    pub C2: BigInt,
+    pub c3: BigInt,
+    pub N: BigInt,
+    pub N_square: BigInt,
+    pub K1: GE,
+    pub pk: GE,
+    T1_vec: Vec<GE>,
+    T2_vec: Vec<GE>,
+    T3_vec: Vec<BigInt>,
+    response_vec: Vec<EvalResponse>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EvalProofHsmcl {
+    pub C1: Ciphertext,
+    pub C2: Ciphertext,
+    pub c3: Ciphertext,
+    pub K1: GE,
+    pub pk: GE,
+    T1_vec: Vec<GE>,
+    T2_vec: Vec<GE>,
+    T3_vec: Vec<Ciphertext>,
+    response_vec: Vec<EvalResponse>,
+}
+
+impl EvalProofPaillier {
+    pub fn prove(C1: &BigInt, C2: &BigInt, c3: &BigInt, N: &BigInt, N_square: &BigInt, a: &BigInt, b: &BigInt) -> Result<Self, Error> {
+        Self::prove_with_rng(C1, C2, c3, N, N_square, a, b, &mut os_rng())
+    }
+
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        C1: &BigInt,
+        C2: &BigInt,
+        c3: &BigInt,
+        N: &BigInt,
+        N_square: &BigInt,
+        a: &BigInt,
+        b: &BigInt,
+        rng: &mut R,
+    ) -> Result<Self, Error> {
+        let (K1, pk) = statement_points(a, b)?;
+        let rounds = Self::sigma(C1, C2, c3, N, N_square, &K1, &pk);
+        let (T_vec, response_vec) = fs_prove(&rounds, &(a.clone(), b.clone()), rng);
+        let (T1_vec, T2_vec, T3_vec) = split(T_vec);
+        Ok(EvalProofPaillier {
+            C1: C1.clone(),
+            C2: C2.clone(),
+            c3: c3.clone(),
+            N: N.clone(),
+            N_square: N_square.clone(),
+            K1,
+            pk,
+            T1_vec,
+            T2_vec,
+            T3_vec,
+            response_vec,
+        })
+    }
+
+    pub fn verify(&self) -> bool {
+        if !well_formed(&self.T1_vec, &self.T2_vec, &self.T3_vec, &self.response_vec)
+            || self.N <= BigInt::one()
+            || self.N_square != &self.N * &self.N
+        {
+            return false;
+        }
+        let rounds = Self::sigma(&self.C1, &self.C2, &self.c3, &self.N, &self.N_square, &self.K1, &self.pk);
+        let T_vec = join(&self.T1_vec, &self.T2_vec, &self.T3_vec);
+        fs_verify(&rounds, &T_vec, &self.response_vec)
+    }
+
+    // the statement as SECURITY_PARAMETER / C runs of EvalPaillier
+    pub fn sigma<'a>(
+        C1: &'a BigInt,
+        C2: &'a BigInt,
+        c3: &'a BigInt,
+        N: &'a BigInt,
+        N_square: &'a BigInt,
+        K1: &'a GE,
+        pk: &'a GE,
+    ) -> Repeat<EvalPaillier<'a>> {
+        Repeat::new(EvalPaillier { C1, C2, c3, N, N_square, K1, pk }, SECURITY_PARAMETER / C)
+    }
+}
+
+impl EvalProofHsmcl {
+    pub fn prove(C1: &Ciphertext, C2: &Ciphertext, c3: &Ciphertext, a: &BigInt, b: &BigInt) -> Result<Self, Error> {
+        Self::prove_with_rng(C1, C2, c3, a, b, &mut os_rng())
+    }
+
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(C1: &Ciphertext, C2: &Ciphertext, c3: &Ciphertext, a: &BigInt, b: &BigInt, rng: &mut R) -> Result<Self, Error> {
+        let _pari = pari::enter().map_err(|_| Error::Pari)?;
+        let (K1, pk) = statement_points(a, b)?;
+        let rounds = Self::sigma(C1, C2, c3, &K1, &pk);
+        let (T_vec, response_vec) = fs_prove(&rounds, &(a.clone(), b.clone()), rng);
+        let (T1_vec, T2_vec, T3_vec) = split(T_vec);
+        Ok(EvalProofHsmcl {
+            C1: C1.clone(),
+            C2: C2.clone(),
+            c3: c3.clone(),
+            K1,
+            pk,
+            T1_vec,
+            T2_vec,
+            T3_vec,
+            response_vec,
+        })
+    }
+
+    // The ciphertexts are only checked against each other, the caller checks
+    // them against its key (see validate::valid_ciphertext)
+    pub fn verify(&self) -> bool {
+        if !well_formed(&self.T1_vec, &self.T2_vec, &self.T3_vec, &self.response_vec) {
+            return false;
+        }
+        let delta = discriminant(&self.C1.c1);
+        let ciphertexts_valid = [&self.C1, &self.C2, &self.c3]
+            .iter()
+            .all(|c| valid_group_element(&c.c1, &delta) && valid_group_element(&c.c2, &delta));
+        if !ciphertexts_valid {
+            return false;
+        }
+        let _pari = match pari::enter() {
+            Ok(guard) => guard,
+            Err(_) => return false,
+        };
+        let rounds = Self::sigma(&self.C1, &self.C2, &self.c3, &self.K1, &self.pk);
+        let T_vec = join(&self.T1_vec, &self.T2_vec, &self.T3_vec);
+        fs_verify(&rounds, &T_vec, &self.response_vec)
+    }
+
+    // the statement as SECURITY_PARAMETER / C runs of EvalHsmcl
+    pub fn sigma<'a>(C1: &'a Ciphertext, C2: &'a Ciphertext, c3: &'a Ciphertext, K1: &'a GE, pk: &'a GE) -> Repeat<EvalHsmcl<'a>> {
+        Repeat::new(EvalHsmcl { C1, C2, c3, K1, pk }, SECURITY_PARAMETER / C)
+    }
+}
+
+// One round of EvalProofPaillier: knowledge of (a, b) with c3 = C1^a * C2^b
+// mod N^2, a * K1 = G and b * K1 = pk, for C-bit challenges
+pub struct EvalPaillier<'a> {
+    pub C1: &'a BigInt,
+    pub C2: &'a BigInt,
+    pub c3: &'a BigInt,
+    pub N: &'a BigInt,
+    pub N_square: &'a BigInt,
+    pub K1: &'a GE,
+    pub pk: &'a GE,
+}
+
+impl SigmaProtocol for EvalPaillier<'_> {
+    type Witness = (BigInt, BigInt); // (a, b)
+    type Commitment = (GE, GE, BigInt); // (T1, T2, T3)
+    type Randomness = (BigInt, BigInt);
+    type Response = EvalResponse;
+
+    fn challenge_bound(&self) -> BigInt {
+        BigInt::from(2).pow(C as u32)
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        let mut items = ec_statement(self.K1, self.pk);
+        items.extend([self.N, self.C1, self.C2, self.c3].iter().map(|x| (*x).clone()));
+        items
+    }
+
+    fn commitment_hash_input(&self, T: &(GE, GE, BigInt)) -> Vec<BigInt> {
+        vec![T.0.bytes_compressed_to_big_int(), T.1.bytes_compressed_to_big_int(), T.2.clone()]
+    }
+
+    fn commit<R: RngCore + CryptoRng>(&self, _w: &(BigInt, BigInt), rng: &mut R) -> ((GE, GE, BigInt), (BigInt, BigInt)) {
+        let ((T1, T2), (rho_a, rho_b)) = ec_commit(self.K1, rng);
+        let T3 = multi_exp_mod(&[self.C1, self.C2], &[&rho_a, &rho_b], self.N_square).unwrap(); // exponents >= 0
+        ((T1, T2, T3), (rho_a, rho_b))
+    }
+
+    fn respond(&self, w: &(BigInt, BigInt), rho: (BigInt, BigInt), e: &BigInt) -> EvalResponse {
+        respond(w, rho, e)
+    }
+
+    fn verify(&self, T: &(GE, GE, BigInt), e: &BigInt, z: &EvalResponse) -> bool {
+        if !ec_verify(self.K1, self.pk, &T.0, &T.1, z, e) {
+            return false;
+        }
+        let minus_e = BigInt::zero() - e;
+        // C1^z_a * C2^z_b * c3^(-e) == T3
+        match multi_exp_mod(&[self.C1, self.C2, self.c3], &[&z.z_a, &z.z_b, &minus_e], self.N_square) {
+            Some(lhs) => lhs == T.2.mod_floor(self.N_square),
+            None => false, // c3 is not invertible mod N^2
+        }
+    }
+}
+
+// One round of EvalProofHsmcl: as EvalPaillier, with c3 = C1^a * C2^b
+// evaluated on HSM-CL ciphertexts
+pub struct EvalHsmcl<'a> {
+    pub C1: &'a Ciphertext,
+    pub C2: &'a Ciphertext,
+    pub c3: &'a Ciphertext,
+    pub K1: &'a GE,
+    pub pk: &'a GE,
+}
+
+impl SigmaProtocol for EvalHsmcl<'_> {
+    type Witness = (BigInt, BigInt); // (a, b)
+    type Commitment = (GE, GE, Ciphertext); // (T1, T2, T3)
+    type Randomness = (BigInt, BigInt);
+    type Response = EvalResponse;
+
+    fn challenge_bound(&self) -> BigInt {
+        BigInt::from(2).pow(C as u32)
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        let mut items = ec_statement(self.K1, self.pk);
+        items.extend(forms(&[self.C1, self.C2, self.c3]));
+        items
+    }
+
+    fn commitment_hash_input(&self, T: &(GE, GE, Ciphertext)) -> Vec<BigInt> {
+        let mut items = vec![T.0.bytes_compressed_to_big_int(), T.1.bytes_compressed_to_big_int()];
+        items.extend(forms(&[&T.2]));
+        items
+    }
+
+    fn commit<R: RngCore + CryptoRng>(&self, _w: &(BigInt, BigInt), rng: &mut R) -> ((GE, GE, Ciphertext), (BigInt, BigInt)) {
+        let ((T1, T2), (rho_a, rho_b)) = ec_commit(self.K1, rng);
+        let T3 = HSMCL::eval_sum(&HSMCL::eval_scal(self.C1, &rho_a), &HSMCL::eval_scal(self.C2, &rho_b));
+        ((T1, T2, T3), (rho_a, rho_b))
+    }
+
+    fn respond(&self, w: &(BigInt, BigInt), rho: (BigInt, BigInt), e: &BigInt) -> EvalResponse {
+        respond(w, rho, e)
+    }
+
+    // C1^z_a * C2^z_b == T3 * c3^e
+    fn verify(&self, T: &(GE, GE, Ciphertext), e: &BigInt, z: &EvalResponse) -> bool {
+        let delta = discriminant(&self.C1.c1);
+        if !valid_qf(&T.2.c1, &delta) || !valid_qf(&T.2.c2, &delta) {
+            return false;
+        }
+        if !ec_verify(self.K1, self.pk, &T.0, &T.1, z, e) {
+            return false;
+        }
+        let lhs = HSMCL::eval_sum(&HSMCL::eval_scal(self.C1, &z.z_a), &HSMCL::eval_scal(self.C2, &z.z_b));
+        let rhs = match e == &BigInt::zero() {
+            true => T.2.clone(),
+            false => HSMCL::eval_sum(&T.2, &HSMCL::eval_scal(self.c3, e)),
+        };
+        lhs.c1 == rhs.c1 && lhs.c2 == rhs.c2
+    }
+}
+
+// K1 = a^-1 * G and pk = b * K1
+// InvalidKey if a is 0 mod q, for which no K1 exists
+fn statement_points(a: &BigInt, b: &BigInt) -> Result<(GE, GE), Error> {
+    let q = FE::q();
+    let G: GE = ECPoint::generator();
+    let a_inv: FE = ECScalar::from(&a.invert(&q).ok_or(Error::InvalidKey)?);
+    let K1 = G.scalar_mul(&a_inv.get_element());
+    let b_fe: FE = ECScalar::from(&b.mod_floor(&q));
+    let pk = K1.scalar_mul(&b_fe.get_element());
+    Ok((K1, pk))
+}
+
+// q^2 * 2^(C + SECURITY_PARAMETER), above a and b by the statistical masking margin
+fn mask_bound() -> BigInt {
+    let q = FE::q();
+    (&q * &q) << (C + SECURITY_PARAMETER)
+}
+
+// G, K1 and pk
+fn ec_statement(K1: &GE, pk: &GE) -> Vec<BigInt> {
+    let G: GE = ECPoint::generator();
+    [&G, K1, pk].iter().map(|P| P.bytes_compressed_to_big_int()).collect()
+}
+
+// T1 = rho_a * K1 and T2 = rho_b * K1
+fn ec_commit<R: RngCore + CryptoRng>(K1: &GE, rng: &mut R) -> ((GE, GE), (BigInt, BigInt)) {
+    let bound = mask_bound();
+    let (rho_a, rho_b) = (sample_below(rng, &bound), sample_below(rng, &bound));
+    let T1 = K1.scalar_mul(&to_scalar(&rho_a).get_element());
+    let T2 = K1.scalar_mul(&to_scalar(&rho_b).get_element());
+    ((T1, T2), (rho_a, rho_b))
+}
+
+fn respond(w: &(BigInt, BigInt), rho: (BigInt, BigInt), e: &BigInt) -> EvalResponse {
+    let (a, b) = w;
+    let (rho_a, rho_b) = rho;
+    EvalResponse {
+        z_a: rho_a + e * a,
+        z_b: rho_b + e * b,
+    }
+}
+
+fn ec_verify(K1: &GE, pk: &GE, T1: &GE, T2: &GE, z: &EvalResponse, e: &BigInt) -> bool {
+    let G: GE = ECPoint::generator();
+    z.z_a >= BigInt::zero()
+        && z.z_b >= BigInt::zero()
+        && ec_check(K1, &z.z_a, T1, e, &G)
+        && ec_check(K1, &z.z_b, T2, e, pk)
+}
+
+// z * K1 == T + e * P
+fn ec_check(K1: &GE, z: &BigInt, T: &GE, e: &BigInt, P: &GE) -> bool {
+    let q = FE::q();
+    if z.mod_floor(&q) == BigInt::zero() {
+        return false; // z * K1 at infinity, which curv cannot represent
+    }
+    let lhs = K1.scalar_mul(&to_scalar(z).get_element());
+    if e == &BigInt::zero() {
+        return &lhs == T;
+    }
+    let eP = P.scalar_mul(&to_scalar(e).get_element());
+    if eP.x_coor() == T.x_coor() {
+        return false; // T + e * P at infinity, or a doubling
+    }
+    lhs == T.add_point(&eP.get_element())
+}
+
+fn forms(ciphertexts: &[&Ciphertext]) -> Vec<BigInt> {
+    ciphertexts
+        .iter()
+        .flat_map(|c| vec![BigInt::from(&c.c1.to_bytes()[..]), BigInt::from(&c.c2.to_bytes()[..])])
+        .collect()
+}
+
+fn well_formed<T>(T1_vec: &[GE], T2_vec: &[GE], T3_vec: &[T], response_vec: &[EvalResponse]) -> bool {
+    let repeat = SECURITY_PARAMETER / C;
+    T1_vec.len() == repeat && T2_vec.len() == repeat && T3_vec.len() == repeat && response_vec.len() == repeat
+}
+
+fn split<T>(T_vec: Vec<(GE, GE, T)>) -> (Vec<GE>, Vec<GE>, Vec<T>) {
+    let mut T1_vec = Vec::new();
+    let mut T2_vec = Vec::new();
+    let mut T3_vec = Vec::new();
+    for (T1, T2, T3) in T_vec {
+        T1_vec.push(T1);
+        T2_vec.push(T2);
+        T3_vec.push(T3);
+    }
+    (T1_vec, T2_vec, T3_vec)
+}
+
+fn join<T: Clone>(T1_vec: &[GE], T2_vec: &[GE], T3_vec: &[T]) -> Vec<(GE, GE, T)> {
+    (0..T1_vec.len()).map(|i| (T1_vec[i].clone(), T2_vec[i].clone(), T3_vec[i].clone())).collect()
+}
+
+fn to_scalar(x: &BigInt) -> FE {
+    ECScalar::from(&x.mod_floor(&FE::q()))
+}
@@ -1,16 +1,25 @@
 use crate::*;
 use serde::{Deserialize, Serialize};
+use class_group::primitives::cl_dl_lcm::Ciphertext;
 use class_group::primitives::cl_dl_lcm::HSMCL;
 use class_group::primitives::cl_dl_lcm::PK;
 use class_group::primitives::cl_dl_lcm::next_probable_small_prime;
 use class_group::BinaryQF;
 use curv::arithmetic::traits::Modulo;
 use curv::cryptographic_primitives::hashing::traits::Hash;
-use curv::arithmetic::traits::Samplable;
+use curv::arithmetic::traits::{Samplable, ZeroizeBN};
 use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
 use curv::elliptic::curves::traits::{ECPoint, ECScalar};
 use curv::BigInt;
 use curv::{FE, GE};
+use protocols::batch::{batch_weight, bisect, group_by_key};
+use protocols::dlog::DLogWithBase;
+use protocols::multiexp::multi_exp_qf;
+use protocols::precomp::PrecomputedPK;
+use protocols::rng::{os_rng, sample_range};
+use protocols::sigma::{fs_challenge, fs_prove, fs_verify, And, SigmaProtocol};
+use protocols::validate::{valid_group_element, valid_pk, valid_qf};
+use rand::{CryptoRng, RngCore};
 
 // HSM-CL Encryption Well-formedness ZKPoK
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -47,6 +56,11 @@ pub struct zkPoKEncProof_v0 {
 }
 
 // add PK well-formedness
+//
+// Proofs from before the Sigma port do not verify: the challenge now also
+// hashes the statement, and u_rho = s_rho - c * SK (it was s_rho + c * SK),
+// checked as u_rho * G + c * PK == S_hat. tests/vectors/ggm_zk.json pins
+// the current format.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct zkPoKEncProof {
     pub seed: BigInt,
@@ -89,8 +103,7 @@ pub struct zkPoKEncProof {
 }
 
 impl zkPoKEncProof_v0 {
-    pub fn prove(g: GE, 
-            hsmcl: HSMCL, 
+    pub fn prove(hsmcl: &HSMCL, 
             h: BigInt, 
             Kx: BigInt, 
             r1: BigInt, 
@@ -103,85 +116,57 @@ impl zkPoKEncProof_v0 {
             minus_B: BigInt, 
             seed: BigInt
         ) -> Self {
+        let ppk = PrecomputedPK::plain(&hsmcl.pk);
+        Self::prove_precomputed(hsmcl, &ppk, h, Kx, r1, r2, x1, x2, y1, y2, B, minus_B, seed)
+    }
 
-        unsafe { pari_init(10000000, 2) };
-        let s_1 = BigInt::sample_range(&minus_B, &B);
-        let s_2 = BigInt::sample_range(&minus_B, &B);
-        let s_h = BigInt::sample_range(&minus_B, &B); // for h
-        let s_x = BigInt::sample_range(&minus_B, &B); // for Kx
-
-        // calculate commit
-        let fsh = BinaryQF::expo_f(&hsmcl.pk.q, &hsmcl.pk.delta_q, &s_h);
-        let fsx = BinaryQF::expo_f(&hsmcl.pk.q, &hsmcl.pk.delta_q, &s_x);
-        let pks1 = hsmcl.pk.h.clone().exp(&s_1); // pk^s_1
-        let pks2 = hsmcl.pk.h.clone().exp(&s_2); // pk^s_2
-
-        let S1 = hsmcl.pk.gq.exp(&s_1);
-        let S2 = fsh.compose(&pks1).reduce();
-        let S3 = hsmcl.pk.gq.exp(&s_2);   
-        let S4 = fsx.compose(&pks2).reduce();
-        
-        //use fiat shamir transform to calculate challenge c
-        let fs1 = HSha256::create_hash(&[
-            &BigInt::from(&S1.to_bytes()[..]),
-            &BigInt::from(&S2.to_bytes()[..]),
-            &BigInt::from(&S3.to_bytes()[..]),
-            &BigInt::from(&S4.to_bytes()[..]),
-
-        ]);
-        let c = HSha256::create_hash(&[&fs1]).mod_floor(&hsmcl.pk.q);
-
-        let u_1 = s_1 + &c * &r1;
-        let u_2 = s_2 + &c * &r2;
-        let u_h = s_h + &c * &h;
-        let u_x = s_x + &c * &Kx;
-
-        let d_1 = u_1.div_floor(&hsmcl.pk.q);
-        let d_2 = u_2.div_floor(&hsmcl.pk.q);
-
-        let e_1 = u_1.mod_floor(&hsmcl.pk.q);
-        let e_2 = u_2.mod_floor(&hsmcl.pk.q);
-        let e_h = u_h.mod_floor(&hsmcl.pk.q);
-        let e_x = u_x.mod_floor(&hsmcl.pk.q);
-
-        let D1 = hsmcl.pk.gq.exp(&d_1);
-        let D2 = hsmcl.pk.h.clone().exp(&d_1);
-        let D3 = hsmcl.pk.gq.exp(&d_2);
-        let D4 = hsmcl.pk.h.clone().exp(&d_2);
-
-        //use fiat shamir transform to calculate l
-        let fs2 = HSha256::create_hash(&[
-            &BigInt::from(&D1.to_bytes()[..]),
-            &BigInt::from(&D2.to_bytes()[..]),
-            &BigInt::from(&D3.to_bytes()[..]),
-            &BigInt::from(&D4.to_bytes()[..]),
-            &u_h,
-            &u_x,
-            &e_1,
-            &e_2,
-
-        ]);
-
-        let ell_bits = 87; 
-        let two_pow_ellbits = BigInt::ui_pow_ui(2,ell_bits);
-        let r = HSha256::create_hash(&[&fs2]).mod_floor(&two_pow_ellbits);
-        let l = next_probable_small_prime(&r);
-
-        let q_1 = u_1.div_floor(&l);
-        let q_2 = u_2.div_floor(&l);
-        let gamma_1 = u_1.mod_floor(&l);
-        let gamma_2 = u_2.mod_floor(&l);
-
-        let Q1 = hsmcl.pk.gq.exp(&q_1);
-        let Q2 = hsmcl.pk.h.exp(&q_1); 
-        let Q3 = hsmcl.pk.gq.exp(&q_2);
-        let Q4 = hsmcl.pk.h.exp(&q_2); 
+    // exponentiations of gq and h are read from the fixed-base tables of ppk
+    pub fn prove_precomputed(hsmcl: &HSMCL, 
+            ppk: &PrecomputedPK, 
+            h: BigInt, 
+            Kx: BigInt, 
+            r1: BigInt, 
+            r2: BigInt, 
+            x1: BinaryQF, 
+            x2: BinaryQF, 
+            y1: BinaryQF, 
+            y2: BinaryQF, 
+            B: BigInt, 
+            minus_B: BigInt, 
+            seed: BigInt
+        ) -> Self {
+        Self::prove_precomputed_with_rng(hsmcl, ppk, h, Kx, r1, r2, x1, x2, y1, y2, B, minus_B, seed, &mut os_rng())
+    }
 
-        let pk = hsmcl.pk.clone();
+    pub fn prove_precomputed_with_rng<R: RngCore + CryptoRng>(hsmcl: &HSMCL, 
+            ppk: &PrecomputedPK, 
+            h: BigInt, 
+            Kx: BigInt, 
+            r1: BigInt, 
+            r2: BigInt, 
+            x1: BinaryQF, 
+            x2: BinaryQF, 
+            y1: BinaryQF, 
+            y2: BinaryQF, 
+            B: BigInt, 
+            minus_B: BigInt, 
+            seed: BigInt,
+            rng: &mut R,
+        ) -> Self {
+        let _pari = pari::enter_or_panic();
+        let statement = Self::sigma(ppk, &seed, &x1, &x2, &y1, &y2);
+        let w = GgmWitness { r: vec![r1, r2], m: vec![h, Kx], sk: None, B, minus_B };
+        let (S, z) = fs_prove(&statement, &w, rng);
+        let [S1, S2, S3, S4] = four(S);
+        let [D1, D2, D3, D4] = four(z.D);
+        let [Q1, Q2, Q3, Q4] = four(z.Q);
+        let [e_1, e_2] = two(z.e);
+        let [gamma_1, gamma_2] = two(z.gamma);
+        let [u_h, u_x] = two(z.u_m);
 
         zkPoKEncProof_v0  {
             seed,
-            pk,
+            pk: hsmcl.pk.clone(),
             x1,
             x2,
             y1,
@@ -213,141 +198,45 @@ impl zkPoKEncProof_v0 {
     }
 
     pub fn verify(&self) -> Result<(), ProofError>{
-        unsafe { pari_init(100000000, 2) };
-        let mut flag = true;
-
-        //use fiat shamir transform to calculate challenge c
-        let fs1 = HSha256::create_hash(&[
-            &BigInt::from(&self.S1.to_bytes()[..]),
-            &BigInt::from(&self.S2.to_bytes()[..]),
-            &BigInt::from(&self.S3.to_bytes()[..]),
-            &BigInt::from(&self.S4.to_bytes()[..]),
-        ]);
-        let c = HSha256::create_hash(&[&fs1]).mod_floor(&self.pk.q);
-
-        // VERIFY STEP 4
-        if &self.e_1 > &&FE::q()   
-            || &self.e_1 < &BigInt::zero()
-            || &self.e_2 > &&FE::q()   
-            || &self.e_2 < &BigInt::zero()
-        {
-            flag = false;
-        }
-
-        // intermediate variables
-        let c_bias_fe: FE = ECScalar::from(&(c.clone() + BigInt::one()));
-        let fuh = BinaryQF::expo_f(&self.pk.q, &self.pk.delta_q, &self.u_h);
-        let fux = BinaryQF::expo_f(&self.pk.q, &self.pk.delta_q, &self.u_x);
-        let pke1 = self.pk.h.clone().exp(&self.e_1);
-        let pke2 = self.pk.h.clone().exp(&self.e_2);
-        let pke1fuh = fuh.compose(&pke1).reduce();
-        let pke2fux = fux.compose(&pke2).reduce();
-        let d1q = self.D1.exp(&self.pk.q);
-        let d2q = self.D2.exp(&self.pk.q);
-        let d3q = self.D3.exp(&self.pk.q);
-        let d4q = self.D4.exp(&self.pk.q);
-        let gqe1 = self.pk.gq.exp(&self.e_1);
-        let gqe2 = self.pk.gq.exp(&self.e_2);
-        let x1c = self.x1.exp(&c);
-        let s1x1c = x1c.compose(&self.S1).reduce();
-        let x2c = self.x2.exp(&c);
-        let s2x2c = x2c.compose(&self.S2).reduce();
-        let y1c = self.y1.exp(&c);
-        let s3y1c = y1c.compose(&self.S3).reduce();
-        let y2c = self.y2.exp(&c);
-        let s4y2c = y2c.compose(&self.S4).reduce();
-
-        let d1qgqe1 = gqe1.compose(&d1q).reduce();
-        if d1qgqe1 != s1x1c {
-            flag = false;
-        }
-        assert!(flag == true, "verification failed");
-
-        let d2qpke1fuh = pke1fuh.compose(&d2q).reduce();
-        if d2qpke1fuh != s2x2c {
-            flag = false;
-        }
-        assert!(flag == true, "verification failed");
-
-        let d3qgqe2 = gqe2.compose(&d3q).reduce();
-        if d3qgqe2 != s3y1c {
-            flag = false;
-        }
-        assert!(flag == true, "verification failed");
-
-        let d4qpke2fux = pke2fux.compose(&d4q).reduce();
-        if d4qpke2fux != s4y2c {
-            flag = false;
-        }
-        assert!(flag == true, "verification failed");
-
-        //use fiat shamir transform
-        let fs2 = HSha256::create_hash(&[
-            &BigInt::from(&self.D1.to_bytes()[..]),
-            &BigInt::from(&self.D2.to_bytes()[..]),
-            &BigInt::from(&self.D3.to_bytes()[..]),
-            &BigInt::from(&self.D4.to_bytes()[..]),
-            &self.u_h,
-            &self.u_x,
-            &self.e_1,
-            &self.e_2,
-        ]);
-
-        let ell_bits = 87;
-        let two_pow_ellbits = BigInt::ui_pow_ui(2,ell_bits);
-        let r = HSha256::create_hash(&[&fs2]).mod_floor(&two_pow_ellbits);
-        let l = next_probable_small_prime(&r);
-
-        //VERIFY STEP 6
-        if self.gamma_1 < BigInt::zero() 
-            || self.gamma_1 > l 
-            || self.gamma_2 < BigInt::zero() 
-            || self.gamma_2 > l 
-        {
-            flag = false;
-        }
-
-        // intermediate variables
-        let pkgamma1 = self.pk.h.clone().exp(&self.gamma_1);
-        let pkgamma2 = self.pk.h.clone().exp(&self.gamma_2);
-        let pkgamma1fuh = fuh.compose(&pkgamma1).reduce();
-        let pkgamma2fux = fux.compose(&pkgamma2).reduce();
-        let q1l = self.Q1.exp(&l);
-        let q2l = self.Q2.exp(&l);
-        let q3l = self.Q3.exp(&l);
-        let q4l = self.Q4.exp(&l);
-        let gqgamma1 = self.pk.gq.exp(&self.gamma_1);
-        let gqgamma2 = self.pk.gq.exp(&self.gamma_2);
-
-        let q1lgqgamma1 = gqgamma1.compose(&q1l).reduce();
-        if q1lgqgamma1 != s1x1c {
-            flag = false;
-        }
-        assert!(flag == true, "verification failed");
-
-        let q2lpkgamma1fuh = pkgamma1fuh.compose(&q2l).reduce();
-        if q2lpkgamma1fuh != s2x2c {
-            flag = false;
-        }
-        assert!(flag == true, "verification failed");
+        self.verify_precomputed(&PrecomputedPK::plain(&self.pk))
+    }
 
-        let q3lgqgamma2 = gqgamma2.compose(&q3l).reduce();
-        if q3lgqgamma2 != s3y1c {
-            flag = false;
+    // exponentiations of gq and h are read from the fixed-base tables of ppk,
+    // which must be built for self.pk
+    pub fn verify_precomputed(&self, ppk: &PrecomputedPK) -> Result<(), ProofError>{
+        if !ppk.matches(&self.pk) {
+            return Err(ProofError);
         }
-        assert!(flag == true, "verification failed");
-
-        let q4lpkgamma2fux = pkgamma2fux.compose(&q4l).reduce();
-        if q4lpkgamma2fux != s4y2c {
-            flag = false;
+        let _pari = pari::enter().map_err(|_| ProofError)?;
+        let statement = Self::sigma(ppk, &self.seed, &self.x1, &self.x2, &self.y1, &self.y2);
+        if !statement.statement_valid() {
+            return Err(ProofError);
         }
-        assert!(flag == true, "verification failed");
-
-        match flag {
+        let S = vec![self.S1.clone(), self.S2.clone(), self.S3.clone(), self.S4.clone()];
+        let z = GgmResponse {
+            D: vec![self.D1.clone(), self.D2.clone(), self.D3.clone(), self.D4.clone()],
+            e: vec![self.e_1.clone(), self.e_2.clone()],
+            Q: vec![self.Q1.clone(), self.Q2.clone(), self.Q3.clone(), self.Q4.clone()],
+            gamma: vec![self.gamma_1.clone(), self.gamma_2.clone()],
+            u_m: vec![self.u_h.clone(), self.u_x.clone()],
+        };
+        match fs_verify(&statement, &S, &z) {
             true => Ok(()),
             false => Err(ProofError),
         }
     }
+
+    // the class group statement without the key
+    pub fn sigma<'a>(
+        ppk: &'a PrecomputedPK,
+        seed: &'a BigInt,
+        x1: &'a BinaryQF,
+        x2: &'a BinaryQF,
+        y1: &'a BinaryQF,
+        y2: &'a BinaryQF,
+    ) -> GgmEnc<'a> {
+        GgmEnc { ppk, seed, ciphertexts: vec![(x1, x2), (y1, y2)], with_key: false }
+    }
 }
 
 
@@ -355,7 +244,7 @@ impl zkPoKEncProof_v0 {
 
 impl zkPoKEncProof{
     pub fn prove(g: GE, 
-            hsmcl: HSMCL, 
+            hsmcl: &HSMCL, 
             h: BigInt, 
             Kx: BigInt, 
             r1: BigInt, 
@@ -370,113 +259,64 @@ impl zkPoKEncProof{
             minus_B: BigInt, 
             seed: BigInt
         ) -> Self {
+        let ppk = PrecomputedPK::plain(&hsmcl.pk);
+        Self::prove_precomputed(g, hsmcl, &ppk, h, Kx, r1, r2, x1, x2, y1, y2, PK, SK, B, minus_B, seed)
+    }
 
-        unsafe { pari_init(10000000, 2) };
-        let s_1 = BigInt::sample_range(&minus_B, &B);
-        let s_2 = BigInt::sample_range(&minus_B, &B);
-        let s_k = BigInt::sample_range(&minus_B, &B); // for sk
-        let s_h = BigInt::sample_range(&minus_B, &B); // for h
-        let s_x = BigInt::sample_range(&minus_B, &B); // for Kx
-        let srho_fe: FE = FE::new_random();
-        let s_rho = srho_fe.to_big_int(); //according to line 519
-
-        // calculate commit
-        let fsh = BinaryQF::expo_f(&hsmcl.pk.q, &hsmcl.pk.delta_q, &s_h);
-        let fsx = BinaryQF::expo_f(&hsmcl.pk.q, &hsmcl.pk.delta_q, &s_x);
-        let pks1 = hsmcl.pk.h.clone().exp(&s_1); // pk^s_1
-        let pks2 = hsmcl.pk.h.clone().exp(&s_2); // pk^s_2
-
-        let S_hat = &g * &srho_fe; 
-        let S1 = hsmcl.pk.gq.exp(&s_1);
-        let S2 = fsh.compose(&pks1).reduce();
-        let S3 = hsmcl.pk.gq.exp(&s_2);   
-        let S4 = fsx.compose(&pks2).reduce();
-        let S5 = hsmcl.pk.gq.exp(&s_k);
-        
-
-        //use fiat shamir transform to calculate challenge c
-        let fs1 = HSha256::create_hash(&[
-            &S_hat.bytes_compressed_to_big_int(),
-            &BigInt::from(&S1.to_bytes()[..]),
-            &BigInt::from(&S2.to_bytes()[..]),
-            &BigInt::from(&S3.to_bytes()[..]),
-            &BigInt::from(&S4.to_bytes()[..]),
-            &BigInt::from(&S5.to_bytes()[..]),
-        ]);
-        let c = HSha256::create_hash(&[&fs1]).mod_floor(&hsmcl.pk.q);
-
-        let u_1 = s_1 + &c * &r1;
-        let u_2 = s_2 + &c * &r2;
-        let u_k = s_k + &c * &hsmcl.sk;
-        let u_h = s_h + &c * &h;
-        let u_x = s_x + &c * &Kx;
-        let u_rho = BigInt::mod_add(&s_rho, &(&c * &SK), &FE::q());
-
-        let d_1 = u_1.div_floor(&hsmcl.pk.q);
-        let d_2 = u_2.div_floor(&hsmcl.pk.q);
-        let d_k = u_k.div_floor(&hsmcl.pk.q);
-        // let d_h = u_h.div_floor(&hsmcl.pk.q);
-        // let d_x = u_x.div_floor(&hsmcl.pk.q);
-
-        let e_1 = u_1.mod_floor(&hsmcl.pk.q);
-        let e_2 = u_2.mod_floor(&hsmcl.pk.q);
-        let e_k = u_k.mod_floor(&hsmcl.pk.q);
-        let e_h = u_h.mod_floor(&hsmcl.pk.q);
-        let e_x = u_x.mod_floor(&hsmcl.pk.q);
-
-        let D1 = hsmcl.pk.gq.exp(&d_1);
-        let D2 = hsmcl.pk.h.clone().exp(&d_1);
-        let D3 = hsmcl.pk.gq.exp(&d_2);
-        let D4 = hsmcl.pk.h.clone().exp(&d_2);
-        let D5 = hsmcl.pk.gq.exp(&d_k);
-
-        //use fiat shamir transform to calculate l
-        let fs2 = HSha256::create_hash(&[
-            &BigInt::from(&D1.to_bytes()[..]),
-            &BigInt::from(&D2.to_bytes()[..]),
-            &BigInt::from(&D3.to_bytes()[..]),
-            &BigInt::from(&D4.to_bytes()[..]),
-            &BigInt::from(&D5.to_bytes()[..]),
-            &u_rho,
-            &u_h,
-            &u_x,
-            &e_1,
-            &e_2,
-            &e_k,
-            // &e_h,
-            // &e_x,
-        ]);
-
-        // reconstruct prime l <- Primes(87), 
-        // For our case, we need to ensure that we have 2^80 primes 
-        // in the challenge set. In order to generate enough prime, 
-        // we need to find X such that "80 = X - log_2 X”. 
-        // Then X is the number of bits outputted by the Primes() function.
-        // X \in (86, 87), so we adopt 87
-
-        let ell_bits = 87; 
-        let two_pow_ellbits = BigInt::ui_pow_ui(2,ell_bits);
-        let r = HSha256::create_hash(&[&fs2]).mod_floor(&two_pow_ellbits);
-        let l = next_probable_small_prime(&r);
-
-        let q_1 = u_1.div_floor(&l);
-        let q_2 = u_2.div_floor(&l);
-        let q_k = u_k.div_floor(&l);
-        let gamma_1 = u_1.mod_floor(&l);
-        let gamma_2 = u_2.mod_floor(&l);
-        let gamma_k = u_k.mod_floor(&l);
-
-        let Q1 = hsmcl.pk.gq.exp(&q_1);
-        let Q2 = hsmcl.pk.h.exp(&q_1); 
-        let Q3 = hsmcl.pk.gq.exp(&q_2);
-        let Q4 = hsmcl.pk.h.exp(&q_2); 
-        let Q5 = hsmcl.pk.gq.exp(&q_k);
+    // exponentiations of gq and h are read from the fixed-base tables of ppk
+    pub fn prove_precomputed(g: GE, 
+            hsmcl: &HSMCL, 
+            ppk: &PrecomputedPK, 
+            h: BigInt, 
+            Kx: BigInt, 
+            r1: BigInt, 
+            r2: BigInt, 
+            x1: BinaryQF, 
+            x2: BinaryQF, 
+            y1: BinaryQF, 
+            y2: BinaryQF, 
+            PK: GE, 
+            SK: BigInt, 
+            B: BigInt, 
+            minus_B: BigInt, 
+            seed: BigInt
+        ) -> Self {
+        Self::prove_precomputed_with_rng(g, hsmcl, ppk, h, Kx, r1, r2, x1, x2, y1, y2, PK, SK, B, minus_B, seed, &mut os_rng())
+    }
 
-        let pk = hsmcl.pk.clone();
+    pub fn prove_precomputed_with_rng<R: RngCore + CryptoRng>(g: GE, 
+            hsmcl: &HSMCL, 
+            ppk: &PrecomputedPK, 
+            h: BigInt, 
+            Kx: BigInt, 
+            r1: BigInt, 
+            r2: BigInt, 
+            x1: BinaryQF, 
+            x2: BinaryQF, 
+            y1: BinaryQF, 
+            y2: BinaryQF, 
+            PK: GE, 
+            SK: BigInt, 
+            B: BigInt, 
+            minus_B: BigInt, 
+            seed: BigInt,
+            rng: &mut R,
+        ) -> Self {
+        let _pari = pari::enter_or_panic();
+        let statement = Self::sigma(ppk, &seed, &x1, &x2, &y1, &y2, &g, &PK);
+        let w = GgmWitness { r: vec![r1, r2], m: vec![h, Kx], sk: Some(hsmcl.sk.clone()), B, minus_B };
+        let SK_fe: FE = ECScalar::from(&SK);
+        let ((S, S_hat), (z, u_rho)) = fs_prove(&statement, &(w, SK_fe), rng);
+        let [S1, S2, S3, S4, S5] = five(S);
+        let [D1, D2, D3, D4, D5] = five(z.D);
+        let [Q1, Q2, Q3, Q4, Q5] = five(z.Q);
+        let [e_1, e_2, e_k] = three(z.e);
+        let [gamma_1, gamma_2, gamma_k] = three(z.gamma);
+        let [u_h, u_x] = two(z.u_m);
 
         zkPoKEncProof {
             seed,
-            pk,
+            pk: hsmcl.pk.clone(),
             x1,
             x2,
             y1,
@@ -496,7 +336,7 @@ impl zkPoKEncProof{
             D4,
             D5,
         
-            u_rho,
+            u_rho: u_rho.to_big_int(),
             u_h,
             u_x,
             e_1,
@@ -516,180 +356,609 @@ impl zkPoKEncProof{
     }
 
     pub fn verify(&self) -> Result<(), ProofError>{
-        unsafe { pari_init(100000000, 2) };
-        let mut flag = true;
+        self.verify_precomputed(&PrecomputedPK::plain(&self.pk))
+    }
 
-        //use fiat shamir transform to calculate challenge c
-        let fs1 = HSha256::create_hash(&[
-            &self.S_hat.bytes_compressed_to_big_int(),
-            &BigInt::from(&self.S1.to_bytes()[..]),
-            &BigInt::from(&self.S2.to_bytes()[..]),
-            &BigInt::from(&self.S3.to_bytes()[..]),
-            &BigInt::from(&self.S4.to_bytes()[..]),
-            &BigInt::from(&self.S5.to_bytes()[..]),
-        ]);
-        let c = HSha256::create_hash(&[&fs1]).mod_floor(&self.pk.q);
-
-        // VERIFY STEP 4
-        if &self.u_rho > &FE::q() 
-            || &self.u_rho < &BigInt::zero() 
-            || &self.e_1 > &&FE::q()   
-            || &self.e_1 < &BigInt::zero()
-            || &self.e_2 > &&FE::q()   
-            || &self.e_2 < &BigInt::zero()
-            || &self.e_k > &&FE::q()  
-            || &self.e_k < &BigInt::zero() 
-        {
-            flag = false;
+    // exponentiations of gq and h are read from the fixed-base tables of ppk,
+    // which must be built for self.pk
+    pub fn verify_precomputed(&self, ppk: &PrecomputedPK) -> Result<(), ProofError>{
+        if !ppk.matches(&self.pk) {
+            return Err(ProofError);
+        }
+        // u_rho is sent as an integer, it must be a canonical scalar
+        if &self.u_rho >= &FE::q() || &self.u_rho < &BigInt::zero() {
+            return Err(ProofError);
         }
+        let _pari = pari::enter().map_err(|_| ProofError)?;
+        let G = GE::generator();
+        let statement = self.statement(ppk, &G);
+        if !statement.left.statement_valid() {
+            return Err(ProofError);
+        }
+        let commitment = (self.commitments(), self.S_hat);
+        let u_rho: FE = ECScalar::from(&self.u_rho);
+        match fs_verify(&statement, &commitment, &(self.response(), u_rho)) {
+            true => Ok(()),
+            false => Err(ProofError),
+        }
+    }
+
+    // Verifies many proofs at once, returns the indices of the invalid proofs on failure
+    pub fn verify_batch(proofs: &[&zkPoKEncProof]) -> Result<(), Vec<usize>> {
+        Self::verify_batch_with_rng(proofs, &mut os_rng())
+    }
 
-        // intermediate variables
-        let urho_fe: FE = ECScalar::from(&self.u_rho);
-        let ghatum = GE::generator() * urho_fe;
-        let c_bias_fe: FE = ECScalar::from(&(c.clone() + BigInt::one()));
-        let shatpkc = (self.S_hat + self.PK.clone() * c_bias_fe).sub_point(&self.PK.get_element());
-        let fuh = BinaryQF::expo_f(&self.pk.q, &self.pk.delta_q, &self.u_h);
-        let fux = BinaryQF::expo_f(&self.pk.q, &self.pk.delta_q, &self.u_x);
-        let pke1 = self.pk.h.clone().exp(&self.e_1);
-        let pke2 = self.pk.h.clone().exp(&self.e_2);
-        let pke1fuh = fuh.compose(&pke1).reduce();
-        let pke2fux = fux.compose(&pke2).reduce();
-        let d1q = self.D1.exp(&self.pk.q);
-        let d2q = self.D2.exp(&self.pk.q);
-        let d3q = self.D3.exp(&self.pk.q);
-        let d4q = self.D4.exp(&self.pk.q);
-        let d5q = self.D5.exp(&self.pk.q);
-        let gqe1 = self.pk.gq.exp(&self.e_1);
-        let gqe2 = self.pk.gq.exp(&self.e_2);
-        let gqek = self.pk.gq.exp(&self.e_k);
-        let x1c = self.x1.exp(&c);
-        let s1x1c = x1c.compose(&self.S1).reduce();
-        let x2c = self.x2.exp(&c);
-        let s2x2c = x2c.compose(&self.S2).reduce();
-        let y1c = self.y1.exp(&c);
-        let s3y1c = y1c.compose(&self.S3).reduce();
-        let y2c = self.y2.exp(&c);
-        let s4y2c = y2c.compose(&self.S4).reduce();
-
-        if shatpkc != ghatum { // ECC equation
-            flag = false;
+    // rng draws the weights
+    pub fn verify_batch_with_rng<R: RngCore + CryptoRng>(proofs: &[&zkPoKEncProof], rng: &mut R) -> Result<(), Vec<usize>> {
+        bisect(proofs, &mut |subset: &[&zkPoKEncProof]| Self::batch_check(subset, rng))
+    }
+
+    // The class group equations of all proofs under the same pk are combined
+    // into two equations, one for step 4 with random odd weights a_i and one
+    // for step 6 with weights b_i:
+    //   gq^(..) * h^(..) * f^(..) * prod D_i^(a_i * q)
+    //     == prod S_i^a_i * x1^(a_1 * c) * x2^(a_2 * c) * y1^(a_3 * c) * y2^(a_4 * c)
+    // and the same with b_i, Q_i and l. S_i appears in both steps, so combining
+    // them into one equation would give it the even weight a_i + b_i, under
+    // which a tampered S_i * A with A of order 2 goes unnoticed. The range
+    // checks and the ECC equation are checked for each proof on its own.
+    fn batch_check<R: RngCore + CryptoRng>(proofs: &[&zkPoKEncProof], rng: &mut R) -> bool {
+        let _pari = match pari::enter() {
+            Ok(guard) => guard,
+            Err(_) => return false,
+        };
+        let groups = group_by_key(proofs, |p| {
+            (p.pk.gq.clone(), p.pk.h.clone(), p.pk.delta_q.clone())
+        });
+        let G = GE::generator();
+        for group in groups {
+            let pk = &proofs[group[0]].pk;
+            let ppk = PrecomputedPK::plain(pk);
+            let mut step4 = BatchEquation::new();
+            let mut step6 = BatchEquation::new();
+            for &j in group.iter() {
+                let p = proofs[j];
+                if &p.u_rho >= &FE::q() || &p.u_rho < &BigInt::zero() {
+                    return false;
+                }
+                let statement = p.statement(&ppk, &G);
+                let c = fs_challenge(&statement, &(p.commitments(), p.S_hat));
+                let z = p.response();
+                if !statement.left.statement_valid() || !statement.left.forms_valid(&p.commitments(), &z) {
+                    return false;
+                }
+                let l = z.ell();
+                let u_rho: FE = ECScalar::from(&p.u_rho);
+                if !statement.left.ranges_ok(&z, &l) || !statement.right.verify(&p.S_hat, &c, &u_rho) {
+                    return false;
+                }
+                let S = [&p.S1, &p.S2, &p.S3, &p.S4, &p.S5];
+                let X = [&p.x1, &p.x2, &p.y1, &p.y2];
+                step4.add(&S, &X, &c, [&p.e_1, &p.e_2, &p.e_k], [&p.D1, &p.D2, &p.D3, &p.D4, &p.D5], &pk.q, [&p.u_h, &p.u_x], rng);
+                step6.add(&S, &X, &c, [&p.gamma_1, &p.gamma_2, &p.gamma_k], [&p.Q1, &p.Q2, &p.Q3, &p.Q4, &p.Q5], &l, [&p.u_h, &p.u_x], rng);
+            }
+            if !step4.holds(pk) || !step6.holds(pk) {
+                return false;
+            }
         }
-        assert!(flag == true, "verification failed");
+        true
+    }
 
+    // the class group part and S_hat + c * PK == u_rho * g under one challenge
+    pub fn sigma<'a>(
+        ppk: &'a PrecomputedPK,
+        seed: &'a BigInt,
+        x1: &'a BinaryQF,
+        x2: &'a BinaryQF,
+        y1: &'a BinaryQF,
+        y2: &'a BinaryQF,
+        g: &'a GE,
+        PK: &'a GE,
+    ) -> And<GgmEnc<'a>, DLogWithBase<'a>> {
+        And::new(GgmEnc { ppk, seed, ciphertexts: vec![(x1, x2), (y1, y2)], with_key: true }, DLogWithBase { base: g, pk: PK })
+    }
 
-        let d1qgqe1 = gqe1.compose(&d1q).reduce();
-        if d1qgqe1 != s1x1c {
-            flag = false;
+    fn statement<'a>(&'a self, ppk: &'a PrecomputedPK, G: &'a GE) -> And<GgmEnc<'a>, DLogWithBase<'a>> {
+        Self::sigma(ppk, &self.seed, &self.x1, &self.x2, &self.y1, &self.y2, G, &self.PK)
+    }
+
+    fn commitments(&self) -> Vec<BinaryQF> {
+        vec![self.S1.clone(), self.S2.clone(), self.S3.clone(), self.S4.clone(), self.S5.clone()]
+    }
+
+    fn response(&self) -> GgmResponse {
+        GgmResponse {
+            D: vec![self.D1.clone(), self.D2.clone(), self.D3.clone(), self.D4.clone(), self.D5.clone()],
+            e: vec![self.e_1.clone(), self.e_2.clone(), self.e_k.clone()],
+            Q: vec![self.Q1.clone(), self.Q2.clone(), self.Q3.clone(), self.Q4.clone(), self.Q5.clone()],
+            gamma: vec![self.gamma_1.clone(), self.gamma_2.clone(), self.gamma_k.clone()],
+            u_m: vec![self.u_h.clone(), self.u_x.clone()],
         }
-        assert!(flag == true, "verification failed");
+    }
+}
+
+// One step (4 or 6) of zkPoKEncProof for many proofs, each of its five
+// equations S * x^c == gq^e * h^e * f^u * R^m with its own odd weight w
+struct BatchEquation<'a> {
+    gq_exp: BigInt,
+    h_exp: BigInt,
+    f_exp: BigInt,
+    left_bases: Vec<&'a BinaryQF>,
+    left_exps: Vec<BigInt>,
+    right_bases: Vec<&'a BinaryQF>,
+    right_exps: Vec<BigInt>,
+}
 
-        let d2qpke1fuh = pke1fuh.compose(&d2q).reduce();
-        if d2qpke1fuh != s2x2c {
-            flag = false;
+impl<'a> BatchEquation<'a> {
+    fn new() -> Self {
+        BatchEquation {
+            gq_exp: BigInt::zero(),
+            h_exp: BigInt::zero(),
+            f_exp: BigInt::zero(),
+            left_bases: Vec::new(),
+            left_exps: Vec::new(),
+            right_bases: Vec::new(),
+            right_exps: Vec::new(),
         }
-        assert!(flag == true, "verification failed");
+    }
 
-        let d3qgqe2 = gqe2.compose(&d3q).reduce();
-        if d3qgqe2 != s3y1c {
-            flag = false;
+    // e = (e_1, e_2, e_k) and R = D with m = q for step 4, (gamma_1, gamma_2,
+    // gamma_k) and Q with m = l for step 6; u = (u_h, u_x)
+    fn add<Rng: RngCore + CryptoRng>(
+        &mut self,
+        S: &[&'a BinaryQF; 5],
+        X: &[&'a BinaryQF; 4],
+        c: &BigInt,
+        e: [&BigInt; 3],
+        R: [&'a BinaryQF; 5],
+        m: &BigInt,
+        u: [&BigInt; 2],
+        rng: &mut Rng,
+    ) {
+        let w = (0..5).map(|_| batch_weight(rng)).collect::<Vec<BigInt>>();
+        let gq_exp = &w[0] * e[0] + &w[2] * e[1] + &w[4] * e[2];
+        // h^c from the fifth equation moves to the left
+        let h_exp = &w[1] * e[0] + &w[3] * e[1] - &w[4] * c;
+        let f_exp = &w[1] * u[0] + &w[3] * u[1];
+        self.gq_exp = &self.gq_exp + &gq_exp;
+        self.h_exp = &self.h_exp + &h_exp;
+        self.f_exp = &self.f_exp + &f_exp;
+        for (R_i, w_i) in R.iter().zip(w.iter()) {
+            self.left_bases.push(*R_i);
+            self.left_exps.push(w_i * m);
+        }
+        for (S_i, w_i) in S.iter().zip(w.iter()) {
+            self.right_bases.push(*S_i);
+            self.right_exps.push(w_i.clone());
+        }
+        for (X_i, w_i) in X.iter().zip(w.iter()) {
+            self.right_bases.push(*X_i);
+            self.right_exps.push(w_i * c);
         }
-        assert!(flag == true, "verification failed");
+    }
+
+    fn holds(mut self, pk: &'a PK) -> bool {
+        self.left_bases.push(&pk.gq);
+        self.left_exps.push(self.gq_exp.clone());
+        self.left_bases.push(&pk.h);
+        self.left_exps.push(self.h_exp.clone());
+        // f has order q, so its exponent can be reduced first
+        let f = BinaryQF::expo_f(&pk.q, &pk.delta_q, &self.f_exp.mod_floor(&pk.q));
+        let left_exps_ref = self.left_exps.iter().collect::<Vec<&BigInt>>();
+        let right_exps_ref = self.right_exps.iter().collect::<Vec<&BigInt>>();
+        match (multi_exp_qf(&self.left_bases, &left_exps_ref), multi_exp_qf(&self.right_bases, &right_exps_ref)) {
+            (Some(left), Some(right)) => left.compose(&f).reduce() == right,
+            _ => false,
+        }
+    }
+}
+
+// zkPoKEncProof for any number of ciphertexts under one pk, with one challenge
+// and one prime l for all of them
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct zkPoKEncVecProof {
+    pub seed: BigInt,
+    pub pk: PK,
+    pub ciphertexts: Vec<Ciphertext>,
+    pub PK: GE, //ECC: G^SK
+
+    S_hat: GE,
+    S: Vec<BinaryQF>, // 2 per ciphertext and 1 for pk
+    u_rho: BigInt,
+    z: GgmResponse,
+}
+
+impl zkPoKEncVecProof {
+    pub fn prove(g: GE, 
+            hsmcl: &HSMCL, 
+            m: Vec<BigInt>, 
+            r: Vec<BigInt>, 
+            ciphertexts: Vec<Ciphertext>, 
+            PK: GE, 
+            SK: BigInt, 
+            B: BigInt, 
+            minus_B: BigInt, 
+            seed: BigInt
+        ) -> Result<Self, ProofError> {
+        let ppk = PrecomputedPK::plain(&hsmcl.pk);
+        Self::prove_precomputed(g, hsmcl, &ppk, m, r, ciphertexts, PK, SK, B, minus_B, seed)
+    }
 
-        let d4qpke2fux = pke2fux.compose(&d4q).reduce();
-        if d4qpke2fux != s4y2c {
-            flag = false;
+    // exponentiations of gq and h are read from the fixed-base tables of ppk
+    pub fn prove_precomputed(g: GE, 
+            hsmcl: &HSMCL, 
+            ppk: &PrecomputedPK, 
+            m: Vec<BigInt>, 
+            r: Vec<BigInt>, 
+            ciphertexts: Vec<Ciphertext>, 
+            PK: GE, 
+            SK: BigInt, 
+            B: BigInt, 
+            minus_B: BigInt, 
+            seed: BigInt
+        ) -> Result<Self, ProofError> {
+        Self::prove_precomputed_with_rng(g, hsmcl, ppk, m, r, ciphertexts, PK, SK, B, minus_B, seed, &mut os_rng())
+    }
+
+    // ciphertexts[i] encrypts m[i] with randomness r[i]; ProofError if the
+    // lengths differ
+    pub fn prove_precomputed_with_rng<R: RngCore + CryptoRng>(g: GE, 
+            hsmcl: &HSMCL, 
+            ppk: &PrecomputedPK, 
+            m: Vec<BigInt>, 
+            r: Vec<BigInt>, 
+            ciphertexts: Vec<Ciphertext>, 
+            PK: GE, 
+            SK: BigInt, 
+            B: BigInt, 
+            minus_B: BigInt, 
+            seed: BigInt,
+            rng: &mut R,
+        ) -> Result<Self, ProofError> {
+        if m.len() != ciphertexts.len() || r.len() != ciphertexts.len() {
+            return Err(ProofError);
         }
-        assert!(flag == true, "verification failed");
+        let _pari = pari::enter().map_err(|_| ProofError)?;
+        let statement = Self::sigma(ppk, &seed, &ciphertexts, &g, &PK);
+        let w = GgmWitness { r, m, sk: Some(hsmcl.sk.clone()), B, minus_B };
+        let SK_fe: FE = ECScalar::from(&SK);
+        let ((S, S_hat), (z, u_rho)) = fs_prove(&statement, &(w, SK_fe), rng);
+        Ok(zkPoKEncVecProof {
+            seed,
+            pk: hsmcl.pk.clone(),
+            ciphertexts,
+            PK,
+            S_hat,
+            S,
+            u_rho: u_rho.to_big_int(),
+            z,
+        })
+    }
 
-        let d5qgqek = gqek.compose(&d5q).reduce();
-        let pkc = self.pk.h.exp(&c);
-        let s5pkc = pkc.compose(&self.S5).reduce();
-        if d5qgqek != s5pkc {
-            flag = false;
+    pub fn verify(&self) -> Result<(), ProofError>{
+        self.verify_precomputed(&PrecomputedPK::plain(&self.pk))
+    }
+
+    // exponentiations of gq and h are read from the fixed-base tables of ppk,
+    // which must be built for self.pk
+    pub fn verify_precomputed(&self, ppk: &PrecomputedPK) -> Result<(), ProofError>{
+        if !ppk.matches(&self.pk) {
+            return Err(ProofError);
+        }
+        // u_rho is sent as an integer, it must be a canonical scalar
+        if &self.u_rho >= &FE::q() || &self.u_rho < &BigInt::zero() {
+            return Err(ProofError);
+        }
+        let _pari = pari::enter().map_err(|_| ProofError)?;
+        let G = GE::generator();
+        let statement = Self::sigma(ppk, &self.seed, &self.ciphertexts, &G, &self.PK);
+        if !statement.left.statement_valid() {
+            return Err(ProofError);
+        }
+        let commitment = (self.S.clone(), self.S_hat);
+        let u_rho: FE = ECScalar::from(&self.u_rho);
+        match fs_verify(&statement, &commitment, &(self.z.clone(), u_rho)) {
+            true => Ok(()),
+            false => Err(ProofError),
         }
-        assert!(flag == true, "verification failed");
-
-        //use fiat shamir transform
-        let fs2 = HSha256::create_hash(&[
-            &BigInt::from(&self.D1.to_bytes()[..]),
-            &BigInt::from(&self.D2.to_bytes()[..]),
-            &BigInt::from(&self.D3.to_bytes()[..]),
-            &BigInt::from(&self.D4.to_bytes()[..]),
-            &BigInt::from(&self.D5.to_bytes()[..]),
-            &self.u_rho,
-            &self.u_h,
-            &self.u_x,
-            &self.e_1,
-            &self.e_2,
-            &self.e_k,
-            // &self.e_h,
-            // &self.e_x,
-        ]);
+    }
+
+    // the class group part for all ciphertexts and S_hat + c * PK == u_rho * g
+    // under one challenge
+    pub fn sigma<'a>(
+        ppk: &'a PrecomputedPK,
+        seed: &'a BigInt,
+        ciphertexts: &'a [Ciphertext],
+        g: &'a GE,
+        PK: &'a GE,
+    ) -> And<GgmEnc<'a>, DLogWithBase<'a>> {
+        let ciphertexts = ciphertexts.iter().map(|c| (&c.c1, &c.c2)).collect();
+        And::new(GgmEnc { ppk, seed, ciphertexts, with_key: true }, DLogWithBase { base: g, pk: PK })
+    }
+}
 
+// The class group part of zkPoKEncProof_v0, zkPoKEncProof and
+// zkPoKEncVecProof: every ciphertext (c1, c2) = (gq^r_i, f^m_i * pk^r_i), and
+// with_key also pk = gq^sk. Challenges are in Z_q.
+pub struct GgmEnc<'a> {
+    pub ppk: &'a PrecomputedPK,
+    pub seed: &'a BigInt,
+    pub ciphertexts: Vec<(&'a BinaryQF, &'a BinaryQF)>,
+    pub with_key: bool,
+}
+
+// the masks are sampled from [minus_B, B]
+#[derive(Clone, Debug)]
+pub struct GgmWitness {
+    pub r: Vec<BigInt>, // randomness of each ciphertext
+    pub m: Vec<BigInt>, // plaintext of each ciphertext
+    pub sk: Option<BigInt>, // for with_key
+    pub B: BigInt,
+    pub minus_B: BigInt,
+}
+
+impl Drop for GgmWitness {
+    fn drop(&mut self) {
+        self.r.iter_mut().chain(self.m.iter_mut()).chain(self.sk.iter_mut()).for_each(|x| x.zeroize_bn());
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GgmMasks {
+    s: Vec<BigInt>, // for each r_i (and sk)
+    s_m: Vec<BigInt>, // for each m_i
+}
+
+// The integer responses u = s + c * w for the r_i (and sk) are sent twice in
+// compressed form, as u = d * q + e and u = q' * l + gamma with D = gq^d, pk^d
+// and Q = gq^q', pk^q', for a prime l derived from the first form
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GgmResponse {
+    pub D: Vec<BinaryQF>,
+    pub e: Vec<BigInt>,
+    pub Q: Vec<BinaryQF>,
+    pub gamma: Vec<BigInt>,
+    pub u_m: Vec<BigInt>,
+}
+
+impl GgmResponse {
+    //use fiat shamir transform to calculate prime l
+    pub fn ell(&self) -> BigInt {
+        let mut items = self.D
+            .iter()
+            .map(|D| BigInt::from(&D.to_bytes()[..]))
+            .collect::<Vec<BigInt>>();
+        items.extend(self.u_m.iter().cloned());
+        items.extend(self.e.iter().cloned());
+        let fs2 = HSha256::create_hash(&items.iter().collect::<Vec<&BigInt>>());
+
+        // reconstruct prime l <- Primes(87), 
+        // For our case, we need to ensure that we have 2^80 primes 
+        // in the challenge set. In order to generate enough prime, 
+        // we need to find X such that "80 = X - log_2 X”. 
+        // Then X is the number of bits outputted by the Primes() function.
+        // X \in (86, 87), so we adopt 87
         let ell_bits = 87;
         let two_pow_ellbits = BigInt::ui_pow_ui(2,ell_bits);
         let r = HSha256::create_hash(&[&fs2]).mod_floor(&two_pow_ellbits);
-        let l = next_probable_small_prime(&r);
-
-        //VERIFY STEP 6
-        if self.gamma_1 < BigInt::zero() 
-            || self.gamma_1 > l 
-            || self.gamma_2 < BigInt::zero() 
-            || self.gamma_2 > l 
-            || self.gamma_k < BigInt::zero() 
-            || self.gamma_k > l
-        {
-            flag = false;
-        }
+        next_probable_small_prime(&r)
+    }
+}
 
-        // intermediate variables
-        let pkgamma1 = self.pk.h.clone().exp(&self.gamma_1);
-        let pkgamma2 = self.pk.h.clone().exp(&self.gamma_2);
-        let pkgamma1fuh = fuh.compose(&pkgamma1).reduce();
-        let pkgamma2fux = fux.compose(&pkgamma2).reduce();
-        let q1l = self.Q1.exp(&l);
-        let q2l = self.Q2.exp(&l);
-        let q3l = self.Q3.exp(&l);
-        let q4l = self.Q4.exp(&l);
-        let q5l = self.Q5.exp(&l);
-        let gqgamma1 = self.pk.gq.exp(&self.gamma_1);
-        let gqgamma2 = self.pk.gq.exp(&self.gamma_2);
-        let gqgammak = self.pk.gq.exp(&self.gamma_k);
-
-        let q1lgqgamma1 = gqgamma1.compose(&q1l).reduce();
-        if q1lgqgamma1 != s1x1c {
-            flag = false;
-        }
-        assert!(flag == true, "verification failed");
+impl GgmEnc<'_> {
+    // number of compressed responses, one per r_i (and sk)
+    fn n(&self) -> usize {
+        self.ciphertexts.len() + if self.with_key { 1 } else { 0 }
+    }
 
-        let q2lpkgamma1fuh = pkgamma1fuh.compose(&q2l).reduce();
-        if q2lpkgamma1fuh != s2x2c {
-            flag = false;
+    // gq^d and pk^d for each r_i, gq^d for sk
+    fn split_bases(&self, d: &[BigInt]) -> Vec<BinaryQF> {
+        let k = self.ciphertexts.len();
+        let mut D = d
+            .iter()
+            .take(k)
+            .flat_map(|d| vec![self.ppk.gq_exp(d), self.ppk.h_exp(d)])
+            .collect::<Vec<BinaryQF>>();
+        if self.with_key {
+            D.extend(d.get(k).map(|d| self.ppk.gq_exp(d)));
         }
-        assert!(flag == true, "verification failed");
+        D
+    }
 
-        let q3lgqgamma2 = gqgamma2.compose(&q3l).reduce();
-        if q3lgqgamma2 != s3y1c {
-            flag = false;
+    // the key and every ciphertext
+    fn statement_valid(&self) -> bool {
+        let delta = &self.ppk.pk.delta_q;
+        valid_pk(&self.ppk.pk)
+            && self.ciphertexts.iter().all(|(c1, c2)| valid_group_element(c1, delta) && valid_group_element(c2, delta))
+    }
+
+    // the commitments and the compressed responses
+    fn forms_valid(&self, S: &[BinaryQF], z: &GgmResponse) -> bool {
+        let delta = &self.ppk.pk.delta_q;
+        S.iter().chain(z.D.iter()).chain(z.Q.iter()).all(|f| valid_qf(f, delta))
+    }
+
+    // VERIFY STEP 4 and 6 length checks
+    fn ranges_ok(&self, z: &GgmResponse, l: &BigInt) -> bool {
+        let q = FE::q();
+        z.e.iter().all(|e| e >= &BigInt::zero() && e <= &q)
+            && z.gamma.iter().all(|gamma| gamma >= &BigInt::zero() && gamma <= l)
+    }
+
+    // each equation S * x^c == gq^e * D^m is checked as gq^e * D^m * x^(-c) == S,
+    // with gq^e, h^e from the fixed-base tables and the rest as one multi-exponentiation;
+    // (e, D, m) is (e, D, q) for step 4 and (gamma, Q, l) for step 6.
+    // False if a multi-exponentiation fails on the prover's forms.
+    fn equations_hold(&self, S: &[BinaryQF], c: &BigInt, e: &[BigInt], D: &[BinaryQF], m: &BigInt, u_m: &[BigInt]) -> bool {
+        let ppk = self.ppk;
+        let minus_c = BigInt::zero() - c;
+        let mut flag = true;
+
+        for (i, (c1, c2)) in self.ciphertexts.iter().enumerate() {
+            let (Dc1, Dc2) = match (
+                multi_exp_qf(&[&D[2 * i], *c1], &[m, &minus_c]), // two bases
+                multi_exp_qf(&[&D[2 * i + 1], *c2], &[m, &minus_c]),
+            ) {
+                (Some(Dc1), Some(Dc2)) => (Dc1, Dc2),
+                _ => return false,
+            };
+            let dgqec1c = ppk.gq_exp(&e[i]).compose(&Dc1).reduce();
+            if dgqec1c != S[2 * i].reduce() {
+                flag = false;
+            }
+
+            let dpkefumc2c = ppk.f_exp(&u_m[i]).compose(&ppk.h_exp(&e[i])).reduce()
+                .compose(&Dc2)
+                .reduce();
+            if dpkefumc2c != S[2 * i + 1].reduce() {
+                flag = false;
+            }
         }
-        assert!(flag == true, "verification failed");
 
-        let q4lpkgamma2fux = pkgamma2fux.compose(&q4l).reduce();
-        if q4lpkgamma2fux != s4y2c {
-            flag = false;
+        if self.with_key {
+            let k = self.ciphertexts.len();
+            let Dk = match multi_exp_qf(&[&D[2 * k]], &[m]) {
+                Some(Dk) => Dk,
+                None => return false,
+            };
+            let dgqekpkc = ppk.gq_exp(&e[k])
+                .compose(&ppk.h_exp(&minus_c)).reduce()
+                .compose(&Dk)
+                .reduce();
+            if dgqekpkc != S[2 * k].reduce() {
+                flag = false;
+            }
         }
-        assert!(flag == true, "verification failed");
+        flag
+    }
+}
+
+impl SigmaProtocol for GgmEnc<'_> {
+    type Witness = GgmWitness;
+    type Commitment = Vec<BinaryQF>; // gq^s_i, f^s_m_i * pk^s_i per ciphertext (gq^s_k)
+    type Randomness = GgmMasks;
+    type Response = GgmResponse;
+
+    fn challenge_bound(&self) -> BigInt {
+        self.ppk.pk.q.clone()
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        let forms = self.ciphertexts
+            .iter()
+            .flat_map(|(c1, c2)| vec![*c1, *c2])
+            .collect::<Vec<&BinaryQF>>();
+        vec![statement_hash(&self.ppk.pk, self.seed, &forms)]
+    }
+
+    fn commitment_hash_input(&self, S: &Vec<BinaryQF>) -> Vec<BigInt> {
+        S.iter().map(|S| BigInt::from(&S.to_bytes()[..])).collect()
+    }
 
-        let q5lgqgammak = gqgammak.compose(&q5l).reduce();
-        if q5lgqgammak != s5pkc {
-            flag = false;
+    fn commit<R: RngCore + CryptoRng>(&self, w: &GgmWitness, rng: &mut R) -> (Vec<BinaryQF>, GgmMasks) {
+        let ppk = self.ppk;
+        let s = (0..self.n())
+            .map(|_| sample_range(rng, &w.minus_B, &w.B)) // for each r_i (and sk)
+            .collect::<Vec<BigInt>>();
+        let s_m = (0..self.ciphertexts.len())
+            .map(|_| sample_range(rng, &w.minus_B, &w.B))
+            .collect::<Vec<BigInt>>();
+
+        let mut S = s_m
+            .iter()
+            .enumerate()
+            .flat_map(|(i, s_m)| vec![ppk.gq_exp(&s[i]), ppk.f_exp(s_m).compose(&ppk.h_exp(&s[i])).reduce()])
+            .collect::<Vec<BinaryQF>>();
+        if self.with_key {
+            S.push(ppk.gq_exp(&s[self.ciphertexts.len()]));
         }
-        assert!(flag == true, "verification failed");
+        (S, GgmMasks { s, s_m })
+    }
 
+    fn respond(&self, w: &GgmWitness, masks: GgmMasks, c: &BigInt) -> GgmResponse {
+        let q = &self.ppk.pk.q;
+        let mut secrets = w.r.iter().collect::<Vec<&BigInt>>();
+        if self.with_key {
+            // without sk the response is one short and fails verification
+            secrets.extend(w.sk.as_ref());
+        }
+        let u = masks.s
+            .iter()
+            .zip(secrets)
+            .map(|(s, x)| s + &(c * x))
+            .collect::<Vec<BigInt>>();
+        let u_m = masks.s_m
+            .iter()
+            .zip(w.m.iter())
+            .map(|(s, m)| s + &(c * m))
+            .collect::<Vec<BigInt>>();
+
+        let d = u.iter().map(|u| u.div_floor(q)).collect::<Vec<BigInt>>();
+        let e = u.iter().map(|u| u.mod_floor(q)).collect::<Vec<BigInt>>();
+        let mut z = GgmResponse {
+            D: self.split_bases(&d),
+            e,
+            Q: Vec::new(),
+            gamma: Vec::new(),
+            u_m,
+        };
+        let l = z.ell();
+        let q_ = u.iter().map(|u| u.div_floor(&l)).collect::<Vec<BigInt>>();
+        z.Q = self.split_bases(&q_);
+        z.gamma = u.iter().map(|u| u.mod_floor(&l)).collect();
+        z
+    }
 
-        match flag {
-            true => Ok(()),
-            false => Err(ProofError),
+    fn verify(&self, S: &Vec<BinaryQF>, c: &BigInt, z: &GgmResponse) -> bool {
+        let (k, n) = (self.ciphertexts.len(), self.n());
+        if S.len() != n + k || z.D.len() != n + k || z.Q.len() != n + k
+            || z.e.len() != n || z.gamma.len() != n || z.u_m.len() != k {
+            return false;
+        }
+        if !self.forms_valid(S, z) {
+            return false;
         }
+        let l = z.ell();
+        if !self.ranges_ok(z, &l) {
+            return false;
+        }
+        self.equations_hold(S, c, &z.e, &z.D, &self.ppk.pk.q, &z.u_m)
+            && self.equations_hold(S, c, &z.gamma, &z.Q, &l, &z.u_m)
     }
-}
\ No newline at end of file
+}
+
+fn two(v: Vec<BigInt>) -> [BigInt; 2] {
+    let mut v = v.into_iter();
+    [v.next().unwrap(), v.next().unwrap()]
+}
+
+fn three(v: Vec<BigInt>) -> [BigInt; 3] {
+    let mut v = v.into_iter();
+    [v.next().unwrap(), v.next().unwrap(), v.next().unwrap()]
+}
+
+fn four(v: Vec<BinaryQF>) -> [BinaryQF; 4] {
+    let mut v = v.into_iter();
+    [v.next().unwrap(), v.next().unwrap(), v.next().unwrap(), v.next().unwrap()]
+}
+
+fn five(v: Vec<BinaryQF>) -> [BinaryQF; 5] {
+    let mut v = v.into_iter();
+    [v.next().unwrap(), v.next().unwrap(), v.next().unwrap(), v.next().unwrap(), v.next().unwrap()]
+}
+
+// hash of the public key, setup seed and ciphertexts, so that none of them
+// can be changed without changing the challenge c
+fn statement_hash(pk: &PK, seed: &BigInt, forms: &[&BinaryQF]) -> BigInt {
+    let mut items = vec![
+        pk.q.clone(),
+        pk.delta_k.clone(),
+        pk.delta_q.clone(),
+        BigInt::from(&pk.gq.to_bytes()[..]),
+        BigInt::from(&pk.h.to_bytes()[..]),
+        pk.stilde.clone(),
+        seed.clone(),
+    ];
+    items.extend(forms.iter().map(|x| BigInt::from(&x.to_bytes()[..])));
+    HSha256::create_hash(&items.iter().collect::<Vec<&BigInt>>())
+}
@@ -18,6 +18,11 @@ use curv::GE;
 use serde::{Deserialize, Serialize};
 use crate::*;
 use paillier::keygen::PrimeSampable;
+use protocols::precomp::PrecomputedPK;
+use protocols::rng::{os_rng, sample_below, sample_scalar};
+use protocols::sigma::{fs_prove, fs_verify, Repeat, SigmaProtocol};
+use protocols::validate::{valid_ciphertext, valid_pk, valid_qf};
+use rand::{CryptoRng, RngCore};
 
 use crate::Error::{self, InvalidSig};
 
@@ -47,157 +52,171 @@ pub struct Witness {
 // forked from Zen-Go's code, fix a problem in && operation
 impl CLDLProof_modified {
     pub fn prove(w: Witness, pk: PK, ciphertext: Ciphertext, q: GE, seed: BigInt, c: usize,) -> Self {
-        unsafe { pari_init(100000000, 2) };
-        // let repeat = SECURITY_PARAMETER / C + 1;
-        let repeat = 80 / c;
-        let triplets_and_fs_and_r_vec = (0..repeat)
-            .map(|_| {
-                // let r1 = BigInt::sample_below(
-                //     &(&pk.stilde
-                //         * BigInt::from(2).pow(40)
-                //         * BigInt::from(2).pow(C as u32)
-                //         * BigInt::from(2).pow(40)),
-                // );
-                let r1 = BigInt::sample_below(
-                    &(&pk.stilde
-                        * BigInt::from(2).pow(80)
-                        * BigInt::from(2).pow(C as u32)
-                        * BigInt::from(2).pow(80)),
-                );
-                let r2_fe: FE = FE::new_random();
-                let r2 = r2_fe.to_big_int();
-                let fr2 = BinaryQF::expo_f(&pk.q, &pk.delta_q, &r2);
-                let pkr1 = pk.h.exp(&r1);
-                let t2 = fr2.compose(&pkr1).reduce();
-                let T = GE::generator() * r2_fe;
-                let t1 = pk.gq.exp(&r1);
-                let fs = HSha256::create_hash(&[
-                    &BigInt::from(&t1.to_bytes()[..]),
-                    &BigInt::from(&t2.to_bytes()[..]),
-                    &T.bytes_compressed_to_big_int(),
-                ]);
-                (TTriplets { t1, t2, T }, fs, r1, r2)
-            })
-            .collect::<Vec<(TTriplets, BigInt, BigInt, BigInt)>>();
-        let triplets_vec = (0..repeat)
-            .map(|i| triplets_and_fs_and_r_vec[i].0.clone())
-            .collect::<Vec<TTriplets>>();
-        let fiat_shamir_vec = (0..repeat)
-            .map(|i| &triplets_and_fs_and_r_vec[i].1)
-            .collect::<Vec<&BigInt>>();
-        let r1_vec = (0..repeat)
-            .map(|i| triplets_and_fs_and_r_vec[i].2.clone())
-            .collect::<Vec<BigInt>>();
-        let r2_vec = (0..repeat)
-            .map(|i| triplets_and_fs_and_r_vec[i].3.clone())
-            .collect::<Vec<BigInt>>();
-        // using Fiat Shamir transform
-        let k = HSha256::create_hash(&fiat_shamir_vec);
-        // println!("k:{}",k);
-
-        // let ten = BigInt::from(C as u32);
-        // println!("ten:{}", ten); //original version seems not correct should be 2^10
-
-        let two: i32 = 2;
-        let two_pow_ten = two.pow(C as u32);
-        let ten_1_bits_string = BigInt::from(two_pow_ten - 1);
-        
-        let u1u2_vec = (0..repeat)
-            .map(|i| {
-                let k_slice_i = (k.clone() >> (i * C)) & ten_1_bits_string.clone();
-                // println!("k_slice_i:{}",k_slice_i);
-                // let and_c = (k.clone() >> (i * C));
-                // println!("and_c:{}",and_c);
-                let u1 = r1_vec[i].clone() + &k_slice_i * &w.r;
-                let u2 = BigInt::mod_add(&r2_vec[i], &(&k_slice_i * &w.x), &FE::q());
-                U1U2 { u1, u2 }
-            })
-            .collect::<Vec<U1U2>>();
+        Self::prove_precomputed(w, &PrecomputedPK::plain(&pk), ciphertext, q, seed, c)
+    }
+
+    // gq^r1 and h^r1 are read from the fixed-base tables of ppk
+    pub fn prove_precomputed(w: Witness, ppk: &PrecomputedPK, ciphertext: Ciphertext, q: GE, seed: BigInt, c: usize,) -> Self {
+        Self::prove_precomputed_with_rng(w, ppk, ciphertext, q, seed, c, &mut os_rng())
+    }
+
+    pub fn prove_precomputed_with_rng<R: RngCore + CryptoRng>(w: Witness, ppk: &PrecomputedPK, ciphertext: Ciphertext, q: GE, seed: BigInt, c: usize, rng: &mut R,) -> Self {
+        let _pari = pari::enter_or_panic();
+        let (t_vec, u_vec) = fs_prove(&Self::sigma(ppk, &seed, &ciphertext, &q, c), &w, rng);
         CLDLProof_modified {
             seed,
-            pk,
+            pk: ppk.pk.clone(),
             ciphertext,
             q,
-            t_vec: triplets_vec,
-            u_vec: u1u2_vec,
+            t_vec,
+            u_vec,
         }
     }
 
     pub fn verify(&self, c: usize,) -> Result<(), ProofError> {
-        unsafe { pari_init(100000000, 2) };
-        let mut flag = true;
+        self.verify_precomputed(&PrecomputedPK::plain(&self.pk), c)
+    }
 
-        // if HSMCL::setup_verify(&self.pk, &self.seed).is_err() {
-        //     flag = false;
-        // }
-
-        // reconstruct k
-        // let repeat = SECURITY_PARAMETER / C + 1;
-        let repeat = 80 / c;
-        let fs_vec = (0..repeat)
-            .map(|i| {
-                HSha256::create_hash(&[
-                    &BigInt::from(&self.t_vec[i].t1.to_bytes()[..]),
-                    &BigInt::from(&self.t_vec[i].t2.to_bytes()[..]),
-                    &self.t_vec[i].T.bytes_compressed_to_big_int(),
-                ])
-            })
-            .collect::<Vec<BigInt>>();
-        let fs_t_vec = (0..repeat).map(|i| &fs_vec[i]).collect::<Vec<&BigInt>>();
-        let mut flag = true;
-        let k = HSha256::create_hash(&fs_t_vec[..]);
-        // let ten = BigInt::from(C as u32);
-
-        let two: i32 = 2;
-        let two_pow_ten = two.pow(C as u32);
-        let ten_1_bits_string = BigInt::from(two_pow_ten - 1);  // if C = 1, then this string is "1"
-
-        // let sample_size = &self.pk.stilde
-        //     * (BigInt::from(2).pow(40))
-        //     * BigInt::from(2).pow(C as u32)
-        //     * (BigInt::from(2).pow(40) + BigInt::one());
-        let sample_size = &self.pk.stilde
-            * (BigInt::from(2).pow(80))
-            * BigInt::from(2).pow(C as u32)
-            * (BigInt::from(2).pow(80) + BigInt::one());
-        for i in 0..repeat {
-            let k_slice_i = (k.clone() >> (i * C)) & ten_1_bits_string.clone();
-            //length test u1:
-            if &self.u_vec[i].u1 > &sample_size || &self.u_vec[i].u1 < &BigInt::zero() {
-                flag = false;
-            }
-            // length test u2:
-            if &self.u_vec[i].u2 > &FE::q() || &self.u_vec[i].u2 < &BigInt::zero() {
-                flag = false;
-            }
-            let c1k = self.ciphertext.c1.exp(&k_slice_i);
-            let t1c1k = self.t_vec[i].t1.compose(&c1k).reduce();
-            let gqu1 = self.pk.gq.exp(&&self.u_vec[i].u1);
-            if t1c1k != gqu1 {
-                flag = false;
-            };
-
-            let k_slice_i_bias_fe: FE = ECScalar::from(&(k_slice_i.clone() + BigInt::one()));
-            let g = GE::generator();
-            let t2kq = (self.t_vec[i].T + self.q.clone() * k_slice_i_bias_fe)
-                .sub_point(&self.q.get_element());
-            let u2p = &g * &ECScalar::from(&self.u_vec[i].u2);
-            if t2kq != u2p {
-                flag = false;
-            }
-
-            let pku1 = self.pk.h.exp(&self.u_vec[i].u1);
-            let fu2 = BinaryQF::expo_f(&self.pk.q, &self.pk.delta_q, &self.u_vec[i].u2);
-            let c2k = self.ciphertext.c2.exp(&k_slice_i);
-            let t2c2k = self.t_vec[i].t2.compose(&c2k).reduce();
-            let pku1fu2 = pku1.compose(&fu2).reduce();
-            if t2c2k != pku1fu2 {
-                flag = false;
-            }
+    // gq^u1 and h^u1 are read from the fixed-base tables of ppk, which must be built for self.pk
+    pub fn verify_precomputed(&self, ppk: &PrecomputedPK, c: usize,) -> Result<(), ProofError> {
+        if !ppk.matches(&self.pk) || c == 0 || c > 80 {
+            return Err(ProofError);
+        }
+        if !valid_pk(&self.pk) || !valid_ciphertext(&self.ciphertext, &self.pk) {
+            return Err(ProofError);
         }
-        match flag {
+        let _pari = pari::enter().map_err(|_| ProofError)?;
+        let rounds = Self::sigma(ppk, &self.seed, &self.ciphertext, &self.q, c);
+        match fs_verify(&rounds, &self.t_vec, &self.u_vec) {
             true => Ok(()),
             false => Err(ProofError),
         }
     }
-}
\ No newline at end of file
+
+    // the statement as 80 / c runs of ClDlEnc, c must be in 1..=80
+    pub fn sigma<'a>(ppk: &'a PrecomputedPK, seed: &'a BigInt, ciphertext: &'a Ciphertext, q: &'a GE, c: usize) -> Repeat<ClDlEnc<'a>> {
+        Repeat::new(ClDlEnc { ppk, seed, ciphertext, Q: q, c }, 80 / c)
+    }
+}
+
+// One round of CLDLProof_modified: knowledge of (x, r) with
+// ciphertext = (gq^r, f^x * h^r) and Q = x * G, for challenges of c bits
+pub struct ClDlEnc<'a> {
+    pub ppk: &'a PrecomputedPK,
+    pub seed: &'a BigInt,
+    pub ciphertext: &'a Ciphertext,
+    pub Q: &'a GE,
+    pub c: usize,
+}
+
+impl ClDlEnc<'_> {
+    // r1 is sampled below stilde * 2^80 * 2^c * 2^80
+    fn mask_bound(&self) -> BigInt {
+        &self.ppk.pk.stilde * BigInt::from(2).pow(80) * BigInt::from(2).pow(self.c as u32) * BigInt::from(2).pow(80)
+    }
+}
+
+impl SigmaProtocol for ClDlEnc<'_> {
+    type Witness = Witness;
+    type Commitment = TTriplets;
+    type Randomness = (BigInt, FE);
+    type Response = U1U2;
+
+    fn challenge_bound(&self) -> BigInt {
+        BigInt::from(2).pow(self.c as u32)
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        vec![statement_hash(&self.ppk.pk, self.seed, self.ciphertext, self.Q)]
+    }
+
+    fn commitment_hash_input(&self, t: &TTriplets) -> Vec<BigInt> {
+        vec![
+            BigInt::from(&t.t1.to_bytes()[..]),
+            BigInt::from(&t.t2.to_bytes()[..]),
+            t.T.bytes_compressed_to_big_int(),
+        ]
+    }
+
+    // t1 = gq^r1, t2 = f^r2 * h^r1, T = r2 * G
+    fn commit<R: RngCore + CryptoRng>(&self, _w: &Witness, rng: &mut R) -> (TTriplets, (BigInt, FE)) {
+        let pk = &self.ppk.pk;
+        let r1 = sample_below(rng, &self.mask_bound());
+        let r2_fe: FE = sample_scalar(rng);
+        let fr2 = BinaryQF::expo_f(&pk.q, &pk.delta_q, &r2_fe.to_big_int());
+        let pkr1 = self.ppk.h_exp(&r1);
+        let t2 = fr2.compose(&pkr1).reduce();
+        let T = GE::generator() * r2_fe;
+        let t1 = self.ppk.gq_exp(&r1);
+        (TTriplets { t1, t2, T }, (r1, r2_fe))
+    }
+
+    // u1 = r1 + k * r over the integers, u2 = r2 + k * x mod q
+    fn respond(&self, w: &Witness, state: (BigInt, FE), k: &BigInt) -> U1U2 {
+        let (r1, r2_fe) = state;
+        let u1 = r1 + k * &w.r;
+        let u2 = BigInt::mod_add(&r2_fe.to_big_int(), &(k * &w.x), &FE::q());
+        U1U2 { u1, u2 }
+    }
+
+    fn verify(&self, t: &TTriplets, k: &BigInt, u: &U1U2) -> bool {
+        let pk = &self.ppk.pk;
+        if !valid_qf(&t.t1, &pk.delta_q) || !valid_qf(&t.t2, &pk.delta_q) {
+            return false;
+        }
+        let mut flag = true;
+        //length test u1:
+        let sample_size = &pk.stilde
+            * BigInt::from(2).pow(80)
+            * BigInt::from(2).pow(self.c as u32)
+            * (BigInt::from(2).pow(80) + BigInt::one());
+        if &u.u1 > &sample_size || &u.u1 < &BigInt::zero() {
+            flag = false;
+        }
+        // length test u2:
+        if &u.u2 > &FE::q() || &u.u2 < &BigInt::zero() {
+            flag = false;
+        }
+        let c1k = self.ciphertext.c1.exp(k);
+        let t1c1k = t.t1.compose(&c1k).reduce();
+        let gqu1 = self.ppk.gq_exp(&u.u1);
+        if t1c1k != gqu1 {
+            flag = false;
+        };
+
+        let k_bias_fe: FE = ECScalar::from(&(k.clone() + BigInt::one()));
+        let g = GE::generator();
+        let t2kq = (t.T + self.Q.clone() * k_bias_fe).sub_point(&self.Q.get_element());
+        let u2p = &g * &ECScalar::from(&u.u2);
+        if t2kq != u2p {
+            flag = false;
+        }
+
+        let pku1 = self.ppk.h_exp(&u.u1);
+        let fu2 = BinaryQF::expo_f(&pk.q, &pk.delta_q, &u.u2);
+        let c2k = self.ciphertext.c2.exp(k);
+        let t2c2k = t.t2.compose(&c2k).reduce();
+        let pku1fu2 = pku1.compose(&fu2).reduce();
+        if t2c2k != pku1fu2 {
+            flag = false;
+        }
+        flag
+    }
+}
+
+// hash of everything the proof is about, so that none of it can be changed
+// without changing the challenge
+fn statement_hash(pk: &PK, seed: &BigInt, ciphertext: &Ciphertext, q: &GE) -> BigInt {
+    HSha256::create_hash(&[
+        &pk.q,
+        &pk.delta_k,
+        &pk.delta_q,
+        &BigInt::from(&pk.gq.to_bytes()[..]),
+        &BigInt::from(&pk.h.to_bytes()[..]),
+        &pk.stilde,
+        seed,
+        &BigInt::from(&ciphertext.c1.to_bytes()[..]),
+        &BigInt::from(&ciphertext.c2.to_bytes()[..]),
+        &q.bytes_compressed_to_big_int(),
+    ])
+}
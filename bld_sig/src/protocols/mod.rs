@@ -1,5 +1,15 @@
 pub mod asiaccs_zk;
+pub mod backend;
+pub mod batch;
+pub mod dlog;
+pub mod eval_zk;
 pub mod ggm_zk;
 pub mod hsmcl_zk;
 pub mod mpaillier;
+pub mod multiexp;
+pub mod precomp;
+pub mod range_zk;
+pub mod rng;
+pub mod sigma;
+pub mod validate;
 pub mod blind_ecdsa;
\ No newline at end of file
@@ -4,30 +4,39 @@ use curv::elliptic::curves::traits::ECScalar;
 use curv::BigInt;
 use curv::FE;
 use paillier::keygen::PrimeSampable;
+use protocols::rng::{os_rng, sample_prime};
+use rand::{CryptoRng, RngCore};
+use secret::SecretBigInt;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Pallier_AsiaCCS_19{
     pub N: BigInt,
     pub N_square: BigInt,
     pub g: BigInt,
-    pub p: BigInt,
+    #[serde(with = "secret::export")]
+    p: SecretBigInt,
     pub q: BigInt,
-    pub t: BigInt,
+    #[serde(with = "secret::export")]
+    t: SecretBigInt,
 }
 
 impl Pallier_AsiaCCS_19{
     pub fn keygen(bitsize: usize) -> Self {
+        Self::keygen_with_rng(bitsize, &mut os_rng())
+    }
+
+    pub fn keygen_with_rng<R: RngCore + CryptoRng>(bitsize: usize, rng: &mut R) -> Self {
     let q = FE::q(); // ECDSA's q
     let size_left = bitsize - q.bit_length();
-    let mut p = BigInt::sample_prime(size_left / 2);
-    let mut t = BigInt::sample_prime(size_left / 2);
+    let mut p = sample_prime(rng, size_left / 2);
+    let mut t = sample_prime(rng, size_left / 2);
     let mut p_minus_1 = &p - &BigInt::one();
-    let mut t_minus_1 = &p - &BigInt::one();
+    let mut t_minus_1 = &t - &BigInt::one();
 
     while q.gcd(&t_minus_1) != BigInt::one() || q.gcd(&p_minus_1) != BigInt::one() {
-        p = BigInt::sample_prime(size_left / 2);
-        t = BigInt::sample_prime(size_left / 2);
-        t_minus_1 = &p - &BigInt::from(1);
+        p = sample_prime(rng, size_left / 2);
+        t = sample_prime(rng, size_left / 2);
+        t_minus_1 = &t - &BigInt::from(1);
         p_minus_1 = &p - &BigInt::from(1);
     }
     let N = &p *&q *&t;
@@ -41,10 +50,34 @@ impl Pallier_AsiaCCS_19{
         N,
         N_square,
         g,
-        p,
+        p: SecretBigInt::new(p),
         q,
-        t,
+        t: SecretBigInt::new(t),
+    }
     }
+
+    // builds the key from chosen primes p and t, for known-answer tests
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn from_secret_for_test(p: BigInt, t: BigInt) -> Self {
+        let q = FE::q();
+        let N = &p * &q * &t;
+        let N_square = &N * &N;
+        let pt = &p * &t;
+        let N_plus_1 = &N + &BigInt::from(1);
+        let g = N_plus_1.powm(&pt, &N_square);
+        Self{
+            N,
+            N_square,
+            g,
+            p: SecretBigInt::new(p),
+            q,
+            t: SecretBigInt::new(t),
+        }
+    }
+
+    // secret primes (p, t) of N = p * q * t
+    pub fn expose_secret(&self) -> (&BigInt, &BigInt) {
+        (self.p.expose_secret(), self.t.expose_secret())
     }
 
     pub fn encrypt(
@@ -65,14 +98,15 @@ impl Pallier_AsiaCCS_19{
         ciphertext: &BigInt,
         key: Self,
     ) -> BigInt {
-        let p_minus_1 = &key.p - &BigInt::one();
+        let (p, t) = key.expose_secret();
+        let p_minus_1 = p - &BigInt::one();
         let q_minus_1 = &key.q - &BigInt::one();
-        let t_minus_1 = &key.t - &BigInt::one();
+        let t_minus_1 = t - &BigInt::one();
         let exp = &p_minus_1 * &q_minus_1 * &t_minus_1;
         let exp_inv_modq = exp.invert(&key.q).unwrap();
         let D = ciphertext.powm(&exp, &key.N_square);
         let D_minus_1 = &D - &BigInt::one();
-        let Npt = &key.N * &key.p * &key.t;
+        let Npt = &key.N * p * t;
         let f = &D_minus_1 / &Npt;
         let m_recover_ = &f * &exp_inv_modq;
         m_recover_.mod_floor(&key.q)
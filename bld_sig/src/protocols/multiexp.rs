@@ -0,0 +1,116 @@
+use std::cmp;
+
+use crate::*;
+use class_group::BinaryQF;
+use curv::arithmetic::traits::*;
+use curv::BigInt;
+use protocols::precomp::{window_digit, WINDOW};
+
+// Straus (interleaved Shamir) multi-exponentiation in the class group:
+// prod_i bases[i]^exps[i] with one shared chain of WINDOW-bit squarings.
+// A negative exponent is handled with the inverse of its base. Returns None
+// without bases, since the identity depends on their discriminant, if bases
+// and exps differ in length, or if PARI cannot be started.
+pub fn multi_exp_qf(bases: &[&BinaryQF], exps: &[&BigInt]) -> Option<BinaryQF> {
+    if bases.is_empty() || bases.len() != exps.len() {
+        return None;
+    }
+    let _pari = pari::enter().ok()?;
+    let row_len = (1 << WINDOW) - 1;
+    let mut max_bits = 0;
+    let terms = bases
+        .iter()
+        .zip(exps.iter())
+        .filter(|(_, e)| **e != &BigInt::zero())
+        .map(|(base, e)| {
+            let b = if *e < &BigInt::zero() {
+                base.inverse().reduce()
+            } else {
+                (*base).clone()
+            };
+            let e_abs = e.abs();
+            max_bits = cmp::max(max_bits, e_abs.bit_length());
+            // b, b^2, ..., b^(2^WINDOW - 1)
+            let mut table = Vec::with_capacity(row_len);
+            table.push(b.clone());
+            for j in 1..row_len {
+                let next = table[j - 1].compose(&b).reduce();
+                table.push(next);
+            }
+            (table, e_abs)
+        })
+        .collect::<Vec<(Vec<BinaryQF>, BigInt)>>();
+    if terms.is_empty() {
+        return Some(bases[0].exp(&BigInt::zero()));
+    }
+
+    let num_windows = (max_bits + WINDOW - 1) / WINDOW;
+    let mut acc: Option<BinaryQF> = None;
+    for i in (0..num_windows).rev() {
+        if let Some(a) = acc {
+            let mut sq = a;
+            for _ in 0..WINDOW {
+                sq = sq.compose(&sq).reduce();
+            }
+            acc = Some(sq);
+        }
+        for (table, e) in terms.iter() {
+            let digit = window_digit(e, i);
+            if digit == 0 {
+                continue;
+            }
+            acc = match acc {
+                None => Some(table[digit - 1].clone()),
+                Some(a) => Some(a.compose(&table[digit - 1]).reduce()),
+            };
+        }
+    }
+    acc // some digit is set, as some exponent is non-zero
+}
+
+// Same as multi_exp_qf for prod_i bases[i]^exps[i] mod modulus, which is 1
+// without bases. Returns None if a base with a negative exponent is not
+// invertible mod modulus, or if bases and exps differ in length.
+pub fn multi_exp_mod(bases: &[&BigInt], exps: &[&BigInt], modulus: &BigInt) -> Option<BigInt> {
+    if bases.len() != exps.len() {
+        return None;
+    }
+    let row_len = (1 << WINDOW) - 1;
+    let mut max_bits = 0;
+    let mut terms = Vec::with_capacity(bases.len());
+    for (base, e) in bases.iter().zip(exps.iter()) {
+        if *e == &BigInt::zero() {
+            continue;
+        }
+        let b = if *e < &BigInt::zero() {
+            base.invert(modulus)?
+        } else {
+            base.mod_floor(modulus)
+        };
+        let e_abs = e.abs();
+        max_bits = cmp::max(max_bits, e_abs.bit_length());
+        let mut table = Vec::with_capacity(row_len);
+        table.push(b.clone());
+        for j in 1..row_len {
+            let next = (&table[j - 1] * &b).mod_floor(modulus);
+            table.push(next);
+        }
+        terms.push((table, e_abs));
+    }
+
+    let num_windows = (max_bits + WINDOW - 1) / WINDOW;
+    let mut acc = BigInt::one();
+    for i in (0..num_windows).rev() {
+        for _ in 0..WINDOW {
+            acc = (&acc * &acc).mod_floor(modulus);
+        }
+        for (table, e) in terms.iter() {
+            let digit = window_digit(e, i);
+            if digit == 0 {
+                continue;
+            }
+            acc = (&acc * &table[digit - 1]).mod_floor(modulus);
+        }
+    }
+    Some(acc.mod_floor(modulus))
+}
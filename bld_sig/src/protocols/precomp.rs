@@ -0,0 +1,303 @@
+use crate::*;
+use class_group::primitives::cl_dl_lcm::Ciphertext;
+use class_group::primitives::cl_dl_lcm::PK;
+use class_group::BinaryQF;
+use curv::arithmetic::traits::*;
+use curv::BigInt;
+use protocols::mpaillier::Pallier_AsiaCCS_19;
+use protocols::range_zk::RANGE_SLACK;
+use serde::{Deserializer, Serializer};
+
+// width (in bits) of one window of the fixed-base tables
+pub const WINDOW: usize = 4;
+
+// i-th WINDOW-bit digit of e (e >= 0)
+pub(crate) fn window_digit(e: &BigInt, i: usize) -> usize {
+    (0..WINDOW).fold(0, |digit, j| {
+        if e.test_bit(i * WINDOW + j) {
+            digit | (1 << j)
+        } else {
+            digit
+        }
+    })
+}
+
+// Fixed-base windowed exponentiation for class group elements.
+// rows[i][j - 1] = base^(j * 2^(WINDOW * i)) for j in 1..2^WINDOW, so that
+// base^e is the composition of one table entry per non-zero digit of e.
+#[derive(Clone, Debug)]
+pub struct FixedBaseQF {
+    pub base: BinaryQF,
+    max_bits: usize,
+    rows: Vec<Vec<BinaryQF>>,
+}
+
+impl FixedBaseQF {
+    pub fn new(base: &BinaryQF, max_bits: usize) -> Self {
+        let _pari = pari::enter_or_panic();
+        let num_rows = (max_bits + WINDOW - 1) / WINDOW;
+        let row_len = (1 << WINDOW) - 1;
+        let mut rows = Vec::with_capacity(num_rows);
+        let mut row_base = base.clone(); // base^(2^(WINDOW * i))
+        for _ in 0..num_rows {
+            let mut row = Vec::with_capacity(row_len);
+            row.push(row_base.clone());
+            for j in 1..row_len {
+                let next = row[j - 1].compose(&row_base).reduce();
+                row.push(next);
+            }
+            row_base = row[row_len - 1].compose(&row_base).reduce();
+            rows.push(row);
+        }
+        FixedBaseQF {
+            base: base.clone(),
+            max_bits: num_rows * WINDOW,
+            rows,
+        }
+    }
+
+    // no table: every exponentiation falls back to BinaryQF::exp
+    pub fn plain(base: &BinaryQF) -> Self {
+        FixedBaseQF {
+            base: base.clone(),
+            max_bits: 0,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn exp(&self, e: &BigInt) -> BinaryQF {
+        let _pari = pari::enter_or_panic();
+        let e_abs = e.abs();
+        if e == &BigInt::zero() || e_abs.bit_length() > self.max_bits {
+            return self.base.exp(e);
+        }
+        let mut acc: Option<BinaryQF> = None;
+        for (i, row) in self.rows.iter().enumerate() {
+            let digit = window_digit(&e_abs, i);
+            if digit == 0 {
+                continue;
+            }
+            acc = match acc {
+                None => Some(row[digit - 1].clone()),
+                Some(a) => Some(a.compose(&row[digit - 1]).reduce()),
+            };
+        }
+        let res = acc.unwrap(); // e != 0, so at least one digit is set
+        if e < &BigInt::zero() {
+            res.inverse().reduce()
+        } else {
+            res
+        }
+    }
+}
+
+// Same table layout as FixedBaseQF, for base^e mod modulus
+#[derive(Clone, Debug)]
+pub struct FixedBaseMod {
+    pub base: BigInt,
+    pub modulus: BigInt,
+    max_bits: usize,
+    rows: Vec<Vec<BigInt>>,
+}
+
+impl FixedBaseMod {
+    pub fn new(base: &BigInt, modulus: &BigInt, max_bits: usize) -> Self {
+        let num_rows = (max_bits + WINDOW - 1) / WINDOW;
+        let row_len = (1 << WINDOW) - 1;
+        let mut rows = Vec::with_capacity(num_rows);
+        let mut row_base = base.mod_floor(modulus);
+        for _ in 0..num_rows {
+            let mut row = Vec::with_capacity(row_len);
+            row.push(row_base.clone());
+            for j in 1..row_len {
+                let next = (&row[j - 1] * &row_base).mod_floor(modulus);
+                row.push(next);
+            }
+            row_base = (&row[row_len - 1] * &row_base).mod_floor(modulus);
+            rows.push(row);
+        }
+        FixedBaseMod {
+            base: base.clone(),
+            modulus: modulus.clone(),
+            max_bits: num_rows * WINDOW,
+            rows,
+        }
+    }
+
+    pub fn plain(base: &BigInt, modulus: &BigInt) -> Self {
+        FixedBaseMod {
+            base: base.clone(),
+            modulus: modulus.clone(),
+            max_bits: 0,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn exp(&self, e: &BigInt) -> BigInt {
+        if e <= &BigInt::zero() || e.bit_length() > self.max_bits {
+            return self.base.powm(e, &self.modulus);
+        }
+        let mut acc = BigInt::one();
+        for (i, row) in self.rows.iter().enumerate() {
+            let digit = window_digit(e, i);
+            if digit == 0 {
+                continue;
+            }
+            acc = (&acc * &row[digit - 1]).mod_floor(&self.modulus);
+        }
+        acc
+    }
+}
+
+// HSM-CL public key together with fixed-base tables for gq and h.
+// Built once per key and shared by encryption, proving and verification.
+#[derive(Clone, Debug)]
+pub struct PrecomputedPK {
+    pub pk: PK,
+    gq_table: FixedBaseQF,
+    h_table: FixedBaseQF,
+}
+
+impl PrecomputedPK {
+    pub fn new(pk: &PK) -> Self {
+        // covers encryption randomness (stilde * 2^80) and the masks of
+        // zkPoKEncProof (stilde * 2^(2 * SECURITY_PARAMETER + 2)) and of
+        // CLDLProof_modified (stilde * 2^(2 * SECURITY_PARAMETER + c)) for c up to 10
+        let max_bits = pk.stilde.bit_length() + 2 * SECURITY_PARAMETER + 12;
+        PrecomputedPK {
+            pk: pk.clone(),
+            gq_table: FixedBaseQF::new(&pk.gq, max_bits),
+            h_table: FixedBaseQF::new(&pk.h, max_bits),
+        }
+    }
+
+    // no tables, for one-off proofs where building them does not pay off
+    pub fn plain(pk: &PK) -> Self {
+        PrecomputedPK {
+            pk: pk.clone(),
+            gq_table: FixedBaseQF::plain(&pk.gq),
+            h_table: FixedBaseQF::plain(&pk.h),
+        }
+    }
+
+    pub fn matches(&self, pk: &PK) -> bool {
+        self.pk.gq == pk.gq && self.pk.h == pk.h && self.pk.delta_q == pk.delta_q
+    }
+
+    pub fn gq_exp(&self, e: &BigInt) -> BinaryQF {
+        self.gq_table.exp(e)
+    }
+
+    pub fn h_exp(&self, e: &BigInt) -> BinaryQF {
+        self.h_table.exp(e)
+    }
+
+    // f^m is computed in closed form by expo_f, no table is needed
+    pub fn f_exp(&self, m: &BigInt) -> BinaryQF {
+        let _pari = pari::enter_or_panic();
+        BinaryQF::expo_f(&self.pk.q, &self.pk.delta_q, m)
+    }
+
+    // same output as HSMCL::encrypt_predefined_randomness
+    pub fn encrypt_predefined_randomness(&self, m: &BigInt, r: &BigInt) -> Ciphertext {
+        let _pari = pari::enter_or_panic();
+        let c1 = self.gq_exp(r);
+        let c2 = self.f_exp(m).compose(&self.h_exp(r)).reduce();
+        Ciphertext { c1, c2 }
+    }
+}
+
+// Only the key is written out; a deserialized key comes without tables,
+// like plain, and is as untrusted as any other key from the wire.
+impl Serialize for PrecomputedPK {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.pk.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrecomputedPK {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pk = PK::deserialize(deserializer)?;
+        Ok(Self::plain(&pk))
+    }
+}
+
+// Modified Paillier public key together with a fixed-base table for g, built
+// once per key like PrecomputedPK
+#[derive(Clone, Debug)]
+pub struct PrecomputedPaillier {
+    pub N: BigInt,
+    pub N_square: BigInt,
+    pub g: BigInt,
+    pub q: BigInt,
+    g_table: FixedBaseMod,
+}
+
+impl PrecomputedPaillier {
+    pub fn new(N: &BigInt, N_square: &BigInt, g: &BigInt, q: &BigInt) -> Self {
+        // plaintexts and the masks m1 of ZK_AsiaCCS_19 are below q, the masks
+        // alpha of PaillierRangeProof below q * 2^(C + RANGE_SLACK)
+        let max_bits = q.bit_length() + C + RANGE_SLACK;
+        PrecomputedPaillier {
+            N: N.clone(),
+            N_square: N_square.clone(),
+            g: g.clone(),
+            q: q.clone(),
+            g_table: FixedBaseMod::new(g, N_square, max_bits),
+        }
+    }
+
+    pub fn plain(N: &BigInt, N_square: &BigInt, g: &BigInt, q: &BigInt) -> Self {
+        PrecomputedPaillier {
+            N: N.clone(),
+            N_square: N_square.clone(),
+            g: g.clone(),
+            q: q.clone(),
+            g_table: FixedBaseMod::plain(g, N_square),
+        }
+    }
+
+    pub fn from_key(key: &Pallier_AsiaCCS_19) -> Self {
+        Self::new(&key.N, &key.N_square, &key.g, &key.q)
+    }
+
+    pub fn matches(&self, N: &BigInt, g: &BigInt) -> bool {
+        &self.N == N && &self.g == g
+    }
+
+    pub fn g_exp(&self, e: &BigInt) -> BigInt {
+        self.g_table.exp(e)
+    }
+
+    // same output as Pallier_AsiaCCS_19::encrypt
+    pub fn encrypt(&self, message: &BigInt, r: &BigInt) -> BigInt {
+        let gm = self.g_exp(message);
+        let rN = r.powm(&self.N, &self.N_square);
+        let gmrN = &gm * &rN;
+        gmrN.mod_floor(&self.N_square)
+    }
+}
+
+// N and g only: N_square and q are recomputed rather than taken from the wire
+#[derive(Serialize, Deserialize)]
+struct PaillierPublicKey {
+    N: BigInt,
+    g: BigInt,
+}
+
+impl Serialize for PrecomputedPaillier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let key = PaillierPublicKey {
+            N: self.N.clone(),
+            g: self.g.clone(),
+        };
+        key.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrecomputedPaillier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let key = PaillierPublicKey::deserialize(deserializer)?;
+        Ok(Self::plain(&key.N, &(&key.N * &key.N), &key.g, &FE::q()))
+    }
+}
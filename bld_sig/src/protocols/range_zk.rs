@@ -0,0 +1,163 @@
+use crate::*;
+use serde::{Deserialize, Serialize};
+use curv::BigInt;
+use protocols::multiexp::multi_exp_mod;
+use protocols::precomp::PrecomputedPaillier;
+use protocols::rng::{os_rng, sample_below};
+use protocols::sigma::{fs_prove, fs_verify, Repeat, SigmaProtocol};
+use rand::{CryptoRng, RngCore};
+
+// Range proof with slack for a modified Paillier ciphertext C = g^m r^N with
+// 0 <= m < q. Each of the SECURITY_PARAMETER / C rounds commits to
+// A = g^alpha beta^N with alpha < B = q * 2^(C + slack), and answers the
+// C-bit challenge b over the integers:
+//   z = alpha + b * m (not reduced), w = beta * r^b mod N^2
+// The verifier checks g^z w^N == A * C^b and 0 <= z < B. A prover that passes
+// knows a plaintext below B, so the bound holds up to a factor 2^(C + slack).
+// An honest z exceeds B with probability 2^-slack per round, in which case the
+// prover starts over.
+
+// statistical slack (in bits) of the proofs in step-2 requests
+pub const RANGE_SLACK: usize = 40;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PaillierRangeProof {
+    pub C: BigInt, // ciphertext C = g^m r^N mod N^2
+    pub N: BigInt,
+    pub N_square: BigInt,
+    pub g: BigInt,
+    A_vec: Vec<BigInt>, // commit A = g^alpha beta^N mod N^2
+    z_vec: Vec<BigInt>, // z = alpha + b * m
+    w_vec: Vec<BigInt>, // w = beta * r^b mod N^2
+}
+
+impl PaillierRangeProof {
+    pub fn prove(pp: &PrecomputedPaillier, ciphertext: BigInt, m: BigInt, r: BigInt, slack: usize) -> Self {
+        Self::prove_with_rng(pp, ciphertext, m, r, slack, &mut os_rng())
+    }
+
+    // g has order q, so C also encrypts m mod q, which is what the proof is for
+    pub fn prove_with_rng<R: RngCore + CryptoRng>(
+        pp: &PrecomputedPaillier,
+        ciphertext: BigInt,
+        m: BigInt,
+        r: BigInt,
+        slack: usize,
+        rng: &mut R,
+    ) -> Self {
+        let m = m.mod_floor(&pp.q);
+        let rounds = Self::sigma(pp, &ciphertext, slack);
+        let bound = bound(&pp.q, slack);
+        let w = (m, r);
+        loop {
+            let (A_vec, zw_vec) = fs_prove(&rounds, &w, rng);
+            if zw_vec.iter().any(|(z, _)| z >= &bound) {
+                continue; // would reveal a lower bound on m
+            }
+            let (z_vec, w_vec) = zw_vec.into_iter().unzip();
+            return PaillierRangeProof {
+                C: ciphertext.clone(),
+                N: pp.N.clone(),
+                N_square: pp.N_square.clone(),
+                g: pp.g.clone(),
+                A_vec,
+                z_vec,
+                w_vec,
+            };
+        }
+    }
+
+    // slack must be at least the prover's, or honest proofs may be rejected
+    pub fn verify(&self, slack: usize) -> bool {
+        let pp = PrecomputedPaillier::plain(&self.N, &self.N_square, &self.g, &FE::q());
+        self.verify_precomputed(&pp, slack)
+    }
+
+    pub fn verify_precomputed(&self, pp: &PrecomputedPaillier, slack: usize) -> bool {
+        if !pp.matches(&self.N, &self.g) || !self.well_formed() {
+            return false;
+        }
+        let zw_vec = self.z_vec.iter().cloned().zip(self.w_vec.iter().cloned()).collect();
+        fs_verify(&Self::sigma(pp, &self.C, slack), &self.A_vec, &zw_vec)
+    }
+
+    // The statement as SECURITY_PARAMETER / C runs of PaillierRange. Run
+    // interactively, the prover cannot start over once challenged, so an honest
+    // run fails with probability about 2^-slack per round.
+    pub fn sigma<'a>(pp: &'a PrecomputedPaillier, ciphertext: &'a BigInt, slack: usize) -> Repeat<PaillierRange<'a>> {
+        Repeat::new(PaillierRange { pp, C: ciphertext, slack }, SECURITY_PARAMETER / C)
+    }
+
+    fn well_formed(&self) -> bool {
+        let repeat = SECURITY_PARAMETER / C;
+        self.A_vec.len() == repeat
+            && self.z_vec.len() == repeat
+            && self.w_vec.len() == repeat
+            && self.N > BigInt::one()
+            && self.N_square == &self.N * &self.N
+    }
+}
+
+// One round of PaillierRangeProof for C-bit challenges, with the verifier's
+// slack. The slack is not hashed, so that the verifier can choose its own.
+pub struct PaillierRange<'a> {
+    pub pp: &'a PrecomputedPaillier,
+    pub C: &'a BigInt,
+    pub slack: usize,
+}
+
+impl SigmaProtocol for PaillierRange<'_> {
+    type Witness = (BigInt, BigInt); // (m, r)
+    type Commitment = BigInt;
+    type Randomness = (BigInt, BigInt);
+    type Response = (BigInt, BigInt); // (z, w)
+
+    fn challenge_bound(&self) -> BigInt {
+        BigInt::from(2).pow(C as u32)
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        vec![self.pp.N.clone(), self.pp.g.clone(), self.C.clone()]
+    }
+
+    fn commitment_hash_input(&self, A: &BigInt) -> Vec<BigInt> {
+        vec![A.clone()]
+    }
+
+    // A = g^alpha beta^N mod N^2
+    fn commit<R: RngCore + CryptoRng>(&self, _w: &(BigInt, BigInt), rng: &mut R) -> (BigInt, (BigInt, BigInt)) {
+        let N_square = &self.pp.N_square;
+        let alpha = sample_below(rng, &bound(&self.pp.q, self.slack));
+        let beta = sample_below(rng, N_square);
+        let galpha = self.pp.g_exp(&alpha);
+        let betaN = beta.powm(&self.pp.N, N_square);
+        ((&galpha * &betaN).mod_floor(N_square), (alpha, beta))
+    }
+
+    // z = alpha + b * m over the integers, w = beta * r^b mod N^2
+    fn respond(&self, w: &(BigInt, BigInt), state: (BigInt, BigInt), b: &BigInt) -> (BigInt, BigInt) {
+        let (m, r) = w;
+        let (alpha, beta) = state;
+        let N_square = &self.pp.N_square;
+        (alpha + b * m, (beta * r.powm(b, N_square)).mod_floor(N_square))
+    }
+
+    fn verify(&self, A: &BigInt, b: &BigInt, zw: &(BigInt, BigInt)) -> bool {
+        let (z, w) = zw;
+        let N_square = &self.pp.N_square;
+        if z < &BigInt::zero() || z >= &bound(&self.pp.q, self.slack) {
+            return false;
+        }
+        // g^z * w^N * C^(-b) == A, with w^N * C^(-b) as one multi-exponentiation
+        let minus_b = BigInt::zero() - b;
+        match multi_exp_mod(&[w, self.C], &[&self.pp.N, &minus_b], N_square) {
+            Some(wNCb) => (&self.pp.g_exp(z) * &wNCb).mod_floor(N_square) == A.mod_floor(N_square),
+            None => false, // C is not invertible mod N^2
+        }
+    }
+}
+
+// B = q * 2^(C + slack)
+fn bound(q: &BigInt, slack: usize) -> BigInt {
+    q * &(BigInt::one() << (C + slack))
+}
@@ -0,0 +1,138 @@
+use crate::*;
+use class_group::primitives::cl_dl_lcm::HSMCL;
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::FE;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+
+// Sampling from a caller-supplied RNG. The functions without an rng
+// argument elsewhere in the crate call these with os_rng(); tests pass a
+// seeded RNG to get reproducible transcripts.
+
+// odd primes used to discard most candidates before Miller-Rabin
+const SMALL_PRIMES: [u32; 24] = [
+    3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+];
+
+// rounds of Miller-Rabin in sample_prime
+const MR_ROUNDS: usize = 40;
+
+pub fn os_rng() -> OsRng {
+    OsRng::new().expect("OS random number generator unavailable")
+}
+
+// uniform in [0, 2^bits)
+pub fn sample_bits<R: RngCore + CryptoRng>(rng: &mut R, bits: usize) -> BigInt {
+    if bits == 0 {
+        return BigInt::zero();
+    }
+    let bytes = (bits - 1) / 8 + 1;
+    let mut buf: Vec<u8> = vec![0; bytes];
+    rng.fill_bytes(&mut buf);
+    BigInt::from(&*buf) >> (bytes * 8 - bits)
+}
+
+// uniform in [0, upper), by rejection
+pub fn sample_below<R: RngCore + CryptoRng>(rng: &mut R, upper: &BigInt) -> BigInt {
+    assert!(*upper > BigInt::zero());
+    let bits = upper.bit_length();
+    loop {
+        let n = sample_bits(rng, bits);
+        if n < *upper {
+            return n;
+        }
+    }
+}
+
+// uniform in [lower, upper)
+pub fn sample_range<R: RngCore + CryptoRng>(rng: &mut R, lower: &BigInt, upper: &BigInt) -> BigInt {
+    assert!(upper > lower);
+    lower + &sample_below(rng, &(upper - lower))
+}
+
+// prime of exactly `bits` bits
+pub fn sample_prime<R: RngCore + CryptoRng>(rng: &mut R, bits: usize) -> BigInt {
+    assert!(bits >= 8);
+    let top = BigInt::one() << (bits - 1);
+    loop {
+        let candidate = sample_bits(rng, bits) | &top | BigInt::one();
+        if is_probable_prime(rng, &candidate) {
+            return candidate;
+        }
+    }
+}
+
+// random non-zero scalar, same distribution as FE::new_random
+pub fn sample_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> FE {
+    let q = FE::q();
+    loop {
+        let k = sample_below(rng, &q);
+        if k != BigInt::zero() {
+            return ECScalar::from(&k);
+        }
+    }
+}
+
+// HSMCL::keygen_with_setup draws sk from the OS RNG; this keeps the public
+// setup (derived from seed) and redraws sk and h = gq^sk from rng
+pub fn hsmcl_keygen_with_setup<R: RngCore + CryptoRng>(
+    rng: &mut R,
+    q: &BigInt,
+    lam: &usize,
+    seed: &BigInt,
+) -> HSMCL {
+    let _pari = pari::enter_or_panic();
+    let mut hsmcl = HSMCL::keygen_with_setup(q, lam, seed);
+    let mult = BigInt::from(2).pow(40);
+    let sk = sample_below(rng, &(&mult * &hsmcl.pk.stilde));
+    hsmcl.pk.h = hsmcl.pk.gq.exp(&sk);
+    hsmcl.sk.zeroize_bn(); // the key drawn from the OS RNG, unused
+    hsmcl.sk = sk;
+    hsmcl
+}
+
+// trial division by SMALL_PRIMES, then MR_ROUNDS rounds of Miller-Rabin with
+// witnesses from rng. GMP's and the paillier crate's tests draw their own
+// witnesses, which would leave sample_prime outside the injected rng.
+pub fn is_probable_prime<R: RngCore + CryptoRng>(rng: &mut R, n: &BigInt) -> bool {
+    let two = BigInt::from(2);
+    if n < &two {
+        return false;
+    }
+    if !n.test_bit(0) {
+        return n == &two;
+    }
+    for p in SMALL_PRIMES.iter() {
+        let p = BigInt::from(*p);
+        if *n == p {
+            return true;
+        }
+        if n.mod_floor(&p) == BigInt::zero() {
+            return false;
+        }
+    }
+    let one = BigInt::one();
+    let n_minus_1 = n - &one;
+    let mut s = 0;
+    while !n_minus_1.test_bit(s) {
+        s += 1;
+    }
+    let d = &n_minus_1 >> s;
+    'witness: for _ in 0..MR_ROUNDS {
+        let a = sample_range(rng, &two, &n_minus_1);
+        let mut x = a.powm(&d, n);
+        if x == one || x == n_minus_1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = x.powm(&two, n);
+            if x == n_minus_1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
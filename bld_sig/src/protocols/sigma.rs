@@ -0,0 +1,258 @@
+use crate::*;
+use protocols::rng::sample_below;
+use rand::{CryptoRng, RngCore};
+
+// A three-move proof of knowledge: the prover sends a commitment, gets a
+// challenge in [0, challenge_bound()) and sends a response. The implementing
+// type is the statement together with its public parameters. The NIZKs in
+// protocols are built from it with the combinators below, and each can also be
+// run interactively with Prover and Verifier.
+pub trait SigmaProtocol {
+    type Witness;
+    type Commitment;
+    // the prover's state between commit and respond
+    type Randomness;
+    type Response;
+
+    fn challenge_bound(&self) -> BigInt;
+    // what the Fiat-Shamir challenge is bound to besides the commitment
+    fn statement_hash_input(&self) -> Vec<BigInt>;
+    fn commitment_hash_input(&self, a: &Self::Commitment) -> Vec<BigInt>;
+
+    fn commit<R: RngCore + CryptoRng>(&self, w: &Self::Witness, rng: &mut R) -> (Self::Commitment, Self::Randomness);
+    fn respond(&self, w: &Self::Witness, state: Self::Randomness, e: &BigInt) -> Self::Response;
+    // the verification equations, including the range checks on the response
+    fn verify(&self, a: &Self::Commitment, e: &BigInt, z: &Self::Response) -> bool;
+}
+
+// Both statements with one shared challenge
+pub struct And<P, Q> {
+    pub left: P,
+    pub right: Q,
+}
+
+impl<P, Q> And<P, Q> {
+    pub fn new(left: P, right: Q) -> Self {
+        And { left, right }
+    }
+}
+
+impl<P: SigmaProtocol, Q: SigmaProtocol> SigmaProtocol for And<P, Q> {
+    type Witness = (P::Witness, Q::Witness);
+    type Commitment = (P::Commitment, Q::Commitment);
+    type Randomness = (P::Randomness, Q::Randomness);
+    type Response = (P::Response, Q::Response);
+
+    // the challenge has to lie in both challenge spaces
+    fn challenge_bound(&self) -> BigInt {
+        let (l, r) = (self.left.challenge_bound(), self.right.challenge_bound());
+        if l < r { l } else { r }
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        let mut items = self.left.statement_hash_input();
+        items.extend(self.right.statement_hash_input());
+        items
+    }
+
+    fn commitment_hash_input(&self, a: &Self::Commitment) -> Vec<BigInt> {
+        let mut items = self.left.commitment_hash_input(&a.0);
+        items.extend(self.right.commitment_hash_input(&a.1));
+        items
+    }
+
+    fn commit<R: RngCore + CryptoRng>(&self, w: &Self::Witness, rng: &mut R) -> (Self::Commitment, Self::Randomness) {
+        let (a1, s1) = self.left.commit(&w.0, rng);
+        let (a2, s2) = self.right.commit(&w.1, rng);
+        ((a1, a2), (s1, s2))
+    }
+
+    fn respond(&self, w: &Self::Witness, state: Self::Randomness, e: &BigInt) -> Self::Response {
+        (self.left.respond(&w.0, state.0, e), self.right.respond(&w.1, state.1, e))
+    }
+
+    fn verify(&self, a: &Self::Commitment, e: &BigInt, z: &Self::Response) -> bool {
+        self.left.verify(&a.0, e, &z.0) && self.right.verify(&a.1, e, &z.1)
+    }
+}
+
+// times parallel runs of inner. The challenge is split into one challenge per
+// run, e_i = (e / bound^i) mod bound, i.e. C-bit slices for bound = 2^C.
+pub struct Repeat<P> {
+    pub inner: P,
+    pub times: usize,
+}
+
+impl<P: SigmaProtocol> Repeat<P> {
+    pub fn new(inner: P, times: usize) -> Self {
+        Repeat { inner, times }
+    }
+
+    pub fn round_challenge(&self, e: &BigInt, i: usize) -> BigInt {
+        let bound = self.inner.challenge_bound();
+        e.div_floor(&bound.pow(i as u32)).mod_floor(&bound)
+    }
+}
+
+impl<P: SigmaProtocol> SigmaProtocol for Repeat<P> {
+    type Witness = P::Witness;
+    type Commitment = Vec<P::Commitment>;
+    type Randomness = Vec<P::Randomness>;
+    type Response = Vec<P::Response>;
+
+    fn challenge_bound(&self) -> BigInt {
+        self.inner.challenge_bound().pow(self.times as u32)
+    }
+
+    fn statement_hash_input(&self) -> Vec<BigInt> {
+        self.inner.statement_hash_input()
+    }
+
+    // one hash per run
+    fn commitment_hash_input(&self, a: &Self::Commitment) -> Vec<BigInt> {
+        a.iter()
+            .map(|a_i| {
+                let items = self.inner.commitment_hash_input(a_i);
+                HSha256::create_hash(&items.iter().collect::<Vec<&BigInt>>())
+            })
+            .collect()
+    }
+
+    fn commit<R: RngCore + CryptoRng>(&self, w: &Self::Witness, rng: &mut R) -> (Self::Commitment, Self::Randomness) {
+        (0..self.times).map(|_| self.inner.commit(w, rng)).unzip()
+    }
+
+    fn respond(&self, w: &Self::Witness, state: Self::Randomness, e: &BigInt) -> Self::Response {
+        state
+            .into_iter()
+            .enumerate()
+            .map(|(i, s)| self.inner.respond(w, s, &self.round_challenge(e, i)))
+            .collect()
+    }
+
+    fn verify(&self, a: &Self::Commitment, e: &BigInt, z: &Self::Response) -> bool {
+        if a.len() != self.times || z.len() != self.times {
+            return false;
+        }
+        (0..self.times).all(|i| self.inner.verify(&a[i], &self.round_challenge(e, i), &z[i]))
+    }
+}
+
+// The prover of an interactive run. Each commitment is answered at most
+// once, since two answers to one commitment give away the witness.
+pub struct Prover<'a, P: SigmaProtocol> {
+    protocol: &'a P,
+    witness: &'a P::Witness,
+    state: Option<P::Randomness>,
+}
+
+impl<'a, P: SigmaProtocol> Prover<'a, P> {
+    pub fn new(protocol: &'a P, witness: &'a P::Witness) -> Self {
+        Prover { protocol, witness, state: None }
+    }
+
+    // first message; a new commitment replaces one that was not answered
+    pub fn commit<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> P::Commitment {
+        let (a, state) = self.protocol.commit(self.witness, rng);
+        self.state = Some(state);
+        a
+    }
+
+    // None without an open commitment
+    pub fn respond(&mut self, e: &BigInt) -> Option<P::Response> {
+        let state = self.state.take()?;
+        Some(self.protocol.respond(self.witness, state, e))
+    }
+}
+
+// The verifier of an interactive run, which issues the challenge itself
+pub struct Verifier<'a, P: SigmaProtocol> {
+    protocol: &'a P,
+    challenge: Option<BigInt>,
+}
+
+impl<'a, P: SigmaProtocol> Verifier<'a, P> {
+    pub fn new(protocol: &'a P) -> Self {
+        Verifier { protocol, challenge: None }
+    }
+
+    // uniform in [0, challenge_bound)
+    pub fn challenge_with_rng<R: RngCore + CryptoRng>(&mut self, rng: &mut R) -> BigInt {
+        let e = sample_below(rng, &self.protocol.challenge_bound());
+        self.challenge = Some(e.clone());
+        e
+    }
+
+    // H(session, statement, commitment) mod challenge_bound, for a verifier that
+    // derives its challenges from its own secret session randomness
+    pub fn challenge_from_session(&mut self, session: &BigInt, a: &P::Commitment) -> BigInt {
+        let mut items = vec![session.clone()];
+        items.extend(self.protocol.statement_hash_input());
+        items.extend(self.protocol.commitment_hash_input(a));
+        let e = HSha256::create_hash(&items.iter().collect::<Vec<&BigInt>>())
+            .mod_floor(&self.protocol.challenge_bound());
+        self.challenge = Some(e.clone());
+        e
+    }
+
+    // the challenge of the non-interactive proofs
+    pub fn fiat_shamir(&mut self, a: &P::Commitment) -> BigInt {
+        let e = fs_challenge(self.protocol, a);
+        self.challenge = Some(e.clone());
+        e
+    }
+
+    // false before a challenge was issued
+    pub fn verify(&self, a: &P::Commitment, z: &P::Response) -> bool {
+        match &self.challenge {
+            Some(e) => self.protocol.verify(a, e, z),
+            None => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Transcript<A, Z> {
+    pub commitment: A,
+    pub challenge: BigInt,
+    pub response: Z,
+}
+
+// One interactive run with the messages passed in memory. challenge is the
+// verifier's move, e.g. |v, _| v.challenge_with_rng(&mut rng). Returns the
+// transcript and whether the verifier accepted.
+pub fn run_in_memory<P, R, F>(p: &P, w: &P::Witness, prover_rng: &mut R, challenge: F) -> (Transcript<P::Commitment, P::Response>, bool)
+where
+    P: SigmaProtocol,
+    R: RngCore + CryptoRng,
+    F: FnOnce(&mut Verifier<P>, &P::Commitment) -> BigInt,
+{
+    let mut prover = Prover::new(p, w);
+    let mut verifier = Verifier::new(p);
+    let commitment = prover.commit(prover_rng);
+    let challenge = challenge(&mut verifier, &commitment);
+    let response = prover.respond(&challenge).unwrap(); // the commitment is open
+    let accepted = verifier.verify(&commitment, &response);
+    (Transcript { commitment, challenge, response }, accepted)
+}
+
+// Fiat-Shamir challenge H(statement, commitment) mod challenge_bound
+pub fn fs_challenge<P: SigmaProtocol>(p: &P, a: &P::Commitment) -> BigInt {
+    let mut items = p.statement_hash_input();
+    items.extend(p.commitment_hash_input(a));
+    HSha256::create_hash(&items.iter().collect::<Vec<&BigInt>>()).mod_floor(&p.challenge_bound())
+}
+
+// the interactive run with the challenge computed by the prover
+pub fn fs_prove<P: SigmaProtocol, R: RngCore + CryptoRng>(p: &P, w: &P::Witness, rng: &mut R) -> (P::Commitment, P::Response) {
+    let mut prover = Prover::new(p, w);
+    let a = prover.commit(rng);
+    let z = prover.respond(&fs_challenge(p, &a)).unwrap(); // the commitment is open
+    (a, z)
+}
+
+pub fn fs_verify<P: SigmaProtocol>(p: &P, a: &P::Commitment, z: &P::Response) -> bool {
+    let mut verifier = Verifier::new(p);
+    verifier.fiat_shamir(a);
+    verifier.verify(a, z)
+}
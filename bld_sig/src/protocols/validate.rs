@@ -0,0 +1,63 @@
+use crate::*;
+use class_group::primitives::cl_dl_lcm::{Ciphertext, PK};
+use class_group::BinaryQF;
+use curv::arithmetic::traits::*;
+use curv::BigInt;
+
+// Checks on class group elements received from the other party. pari does
+// not check its inputs, so a form of another discriminant or one that is not
+// reduced would be composed as if it were a group element.
+
+// b^2 - 4ac
+pub fn discriminant(f: &BinaryQF) -> BigInt {
+    &f.b * &f.b - BigInt::from(4) * &f.a * &f.c
+}
+
+// |b| <= a <= c, and b >= 0 if |b| == a or a == c
+pub fn is_reduced(f: &BinaryQF) -> bool {
+    let b_abs = f.b.abs();
+    if b_abs > f.a || f.a > f.c {
+        return false;
+    }
+    if b_abs == f.a || f.a == f.c {
+        return f.b >= BigInt::zero();
+    }
+    true
+}
+
+// gcd(a, b, c) == 1
+pub fn is_primitive(f: &BinaryQF) -> bool {
+    f.a.gcd(&f.b).gcd(&f.c) == BigInt::one()
+}
+
+// the principal form (1, b, c) is the only reduced form with a == 1
+pub fn is_identity(f: &BinaryQF) -> bool {
+    f.a == BigInt::one()
+}
+
+// a reduced, primitive, positive definite form of discriminant delta
+pub fn valid_qf(f: &BinaryQF, delta: &BigInt) -> bool {
+    delta < &BigInt::zero()
+        && f.a > BigInt::zero()
+        && &discriminant(f) == delta
+        && is_reduced(f)
+        && is_primitive(f)
+}
+
+// as valid_qf, and not the identity
+pub fn valid_group_element(f: &BinaryQF, delta: &BigInt) -> bool {
+    valid_qf(f, delta) && !is_identity(f)
+}
+
+// both components are non-trivial elements of Cl(delta_q)
+pub fn valid_ciphertext(c: &Ciphertext, pk: &PK) -> bool {
+    valid_group_element(&c.c1, &pk.delta_q) && valid_group_element(&c.c2, &pk.delta_q)
+}
+
+// delta_q = delta_k * q^2 with delta_k < 0, and gq, h non-trivial in Cl(delta_q)
+pub fn valid_pk(pk: &PK) -> bool {
+    pk.delta_k < BigInt::zero()
+        && pk.delta_q == &pk.delta_k * &pk.q * &pk.q
+        && valid_group_element(&pk.gq, &pk.delta_q)
+        && valid_group_element(&pk.h, &pk.delta_q)
+}
@@ -0,0 +1,174 @@
+use std::fmt;
+use std::mem;
+
+use class_group::primitives::cl_dl_lcm::{Ciphertext, HSMCL, PK};
+use curv::arithmetic::traits::ZeroizeBN;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::FE;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroize;
+use crate::pari;
+
+// Wrappers for secret values: the inner value is private, Debug prints
+// [REDACTED], and the value is wiped when dropped. expose_secret() is the
+// only way to read it.
+//
+// The wrappers do not implement Serialize, so a secret is not written out by
+// accident along with the message or log record that contains it. Key files
+// and session state, which have to hold secrets, export them explicitly, per
+// field, with #[serde(with = "secret::export")].
+//
+// Nor do they implement PartialEq, so secrets are not compared with an early
+// exit. The arithmetic on them is not constant time, though: it runs on GMP
+// through curv's BigInt and, for HSM-CL, on PARI, and neither library is
+// written to be. Timing-safe decryption and evaluation would need other
+// bignum and class group backends, which is out of scope for these wrappers.
+
+#[derive(Clone)]
+pub struct SecretScalar(FE);
+
+impl SecretScalar {
+    pub fn new(secret: FE) -> Self {
+        SecretScalar(secret)
+    }
+
+    pub fn new_random() -> Self {
+        SecretScalar(ECScalar::new_random())
+    }
+
+    pub fn expose_secret(&self) -> &FE {
+        &self.0
+    }
+}
+
+impl Zeroize for SecretScalar {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretScalar {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretScalar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretScalar([REDACTED])")
+    }
+}
+
+#[derive(Clone)]
+pub struct SecretBigInt(BigInt);
+
+impl SecretBigInt {
+    pub fn new(secret: BigInt) -> Self {
+        SecretBigInt(secret)
+    }
+
+    pub fn expose_secret(&self) -> &BigInt {
+        &self.0
+    }
+}
+
+impl Zeroize for SecretBigInt {
+    fn zeroize(&mut self) {
+        self.0.zeroize_bn();
+    }
+}
+
+impl Drop for SecretBigInt {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretBigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBigInt([REDACTED])")
+    }
+}
+
+// A secret with an exported form, the value as it is serialized
+pub trait ExportSecret: Sized {
+    type Exported: Serialize + DeserializeOwned;
+
+    fn export_secret(&self) -> &Self::Exported;
+    fn import_secret(exported: Self::Exported) -> Self;
+}
+
+impl ExportSecret for SecretScalar {
+    type Exported = FE;
+
+    fn export_secret(&self) -> &FE {
+        &self.0
+    }
+
+    fn import_secret(exported: FE) -> Self {
+        SecretScalar(exported)
+    }
+}
+
+impl ExportSecret for SecretBigInt {
+    type Exported = BigInt;
+
+    fn export_secret(&self) -> &BigInt {
+        &self.0
+    }
+
+    fn import_secret(exported: BigInt) -> Self {
+        SecretBigInt(exported)
+    }
+}
+
+// serde adapter for the secret fields of key files and session state, in the
+// same format as the plain value
+pub mod export {
+    use super::*;
+
+    pub fn serialize<T: ExportSecret, S: Serializer>(secret: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        secret.export_secret().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: ExportSecret, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        T::Exported::deserialize(deserializer).map(T::import_secret)
+    }
+}
+
+// HSM-CL key pair. class_group's HSMCL keeps sk in a pub field and derives
+// Debug, so the user side holds its key in this form instead.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HsmclKey {
+    pub pk: PK,
+    #[serde(with = "export")]
+    sk: SecretBigInt,
+}
+
+impl HsmclKey {
+    // takes the secret key out of hsmcl, leaving zero in its place
+    pub fn from_hsmcl(mut hsmcl: HSMCL) -> Self {
+        let sk = mem::replace(&mut hsmcl.sk, BigInt::zero());
+        HsmclKey {
+            pk: hsmcl.pk,
+            sk: SecretBigInt::new(sk),
+        }
+    }
+
+    pub fn expose_secret(&self) -> &BigInt {
+        self.sk.expose_secret()
+    }
+
+    pub fn decrypt(&self, c: &Ciphertext) -> BigInt {
+        let _pari = pari::enter_or_panic();
+        let mut hsmcl = HSMCL {
+            sk: self.sk.expose_secret().clone(),
+            pk: self.pk.clone(),
+        };
+        let m = hsmcl.decrypt(c);
+        hsmcl.sk.zeroize_bn();
+        m
+    }
+}
@@ -0,0 +1,210 @@
+use crate::Error::{self, InvalidKey, InvalidSig};
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use serde::{Deserialize, Serialize};
+
+// Plain ECDSA over secp256k1 for the unblinded (r, s): verification, DER and
+// 64-byte compact encodings, low-s normalization and public key recovery.
+// msg_hash is the digest already converted to an integer (see digest.rs).
+
+// field prime of secp256k1
+const P_HEX: &str = "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Signature {
+    pub r: BigInt,
+    pub s: BigInt,
+}
+
+impl Signature {
+    pub fn new(r: BigInt, s: BigInt) -> Self {
+        Signature { r, s }
+    }
+
+    pub fn is_low_s(&self) -> bool {
+        let half_q = FE::q() >> 1;
+        self.s <= half_q
+    }
+
+    // (r, q - s) if s > q/2; both verify, only the low one is accepted by Bitcoin
+    pub fn normalize_s(&self) -> Self {
+        if self.is_low_s() {
+            self.clone()
+        } else {
+            Signature {
+                r: self.r.clone(),
+                s: FE::q() - &self.s,
+            }
+        }
+    }
+
+    // r || s, 32 bytes each, big endian. InvalidSig unless 0 < r, s < q, as
+    // the fields are public and may hold anything.
+    pub fn to_compact(&self) -> Result<[u8; 64], Error> {
+        if !self.in_range() {
+            return Err(InvalidSig);
+        }
+        let mut out = [0u8; 64];
+        out[..32].copy_from_slice(&to_32_bytes(&self.r));
+        out[32..].copy_from_slice(&to_32_bytes(&self.s));
+        Ok(out)
+    }
+
+    pub fn from_compact(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != 64 {
+            return Err(InvalidSig);
+        }
+        let sig = Signature {
+            r: BigInt::from(&bytes[..32]),
+            s: BigInt::from(&bytes[32..]),
+        };
+        match sig.in_range() {
+            true => Ok(sig),
+            false => Err(InvalidSig),
+        }
+    }
+
+    // SEQUENCE { INTEGER r, INTEGER s }, InvalidSig unless 0 < r, s < q
+    pub fn to_der(&self) -> Result<Vec<u8>, Error> {
+        if !self.in_range() {
+            return Err(InvalidSig);
+        }
+        let r = der_integer(&self.r);
+        let s = der_integer(&self.s);
+        let mut out = vec![0x30, (r.len() + s.len()) as u8];
+        out.extend(r);
+        out.extend(s);
+        Ok(out)
+    }
+
+    // strict DER: minimal lengths and integers, no trailing bytes
+    pub fn from_der(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() < 8 || bytes[0] != 0x30 || bytes[1] as usize != bytes.len() - 2 {
+            return Err(InvalidSig);
+        }
+        let (r, rest) = parse_der_integer(&bytes[2..])?;
+        let (s, rest) = parse_der_integer(rest)?;
+        if !rest.is_empty() {
+            return Err(InvalidSig);
+        }
+        let sig = Signature { r, s };
+        match sig.in_range() {
+            true => Ok(sig),
+            false => Err(InvalidSig),
+        }
+    }
+
+    fn in_range(&self) -> bool {
+        let q = FE::q();
+        self.r > BigInt::zero() && self.r < q && self.s > BigInt::zero() && self.s < q
+    }
+}
+
+pub fn verify(pubkey: &GE, msg_hash: &BigInt, sig: &Signature) -> Result<(), Error> {
+    if !sig.in_range() {
+        return Err(InvalidSig);
+    }
+    let q = FE::q();
+    let w = sig.s.invert(&q).ok_or(InvalidSig)?;
+    let u1 = BigInt::mod_mul(&msg_hash.mod_floor(&q), &w, &q);
+    let u2 = BigInt::mod_mul(&sig.r, &w, &q);
+    let R = lin_comb(&u1, &u2, pubkey).ok_or(InvalidSig)?;
+    let v = R.x_coor().unwrap().mod_floor(&q);
+    match v == sig.r {
+        true => Ok(()),
+        false => Err(InvalidSig),
+    }
+}
+
+// Public key with recovery id recid (bit 0: parity of R.y, bit 1: R.x = r + q)
+pub fn recover(msg_hash: &BigInt, sig: &Signature, recid: u8) -> Result<GE, Error> {
+    if recid > 3 || !sig.in_range() {
+        return Err(InvalidSig);
+    }
+    let q = FE::q();
+    let x = match recid & 2 {
+        0 => sig.r.clone(),
+        _ => &sig.r + &q,
+    };
+    if x >= BigInt::from_hex(P_HEX) {
+        return Err(InvalidSig);
+    }
+    let mut compressed = vec![0x02 | (recid & 1)];
+    compressed.extend_from_slice(&to_32_bytes(&x));
+    let R: GE = ECPoint::from_bytes(&compressed).map_err(|_| InvalidSig)?;
+    // Q = r^(-1) * (s * R - z * G)
+    let r_inv = sig.r.invert(&q).ok_or(InvalidSig)?;
+    let u1 = BigInt::mod_mul(&(&q - &msg_hash.mod_floor(&q)), &r_inv, &q);
+    let u2 = BigInt::mod_mul(&sig.s, &r_inv, &q);
+    lin_comb(&u1, &u2, &R).ok_or(InvalidKey)
+}
+
+// recovery id of sig for pubkey, None if sig does not verify under pubkey
+pub fn recovery_id(pubkey: &GE, msg_hash: &BigInt, sig: &Signature) -> Option<u8> {
+    verify(pubkey, msg_hash, sig).ok()?;
+    (0..4).find(|&recid| match recover(msg_hash, sig, recid) {
+        Ok(Q) => &Q == pubkey,
+        Err(_) => false,
+    })
+}
+
+// u1 * G + u2 * P, None for the point at infinity. curv scalars cannot be
+// zero and curv points cannot be infinity, so those cases are handled here.
+fn lin_comb(u1: &BigInt, u2: &BigInt, P: &GE) -> Option<GE> {
+    let term = |u: &BigInt, base: &GE| -> Option<GE> {
+        if u == &BigInt::zero() {
+            return None;
+        }
+        let u_fe: FE = ECScalar::from(u);
+        Some(base.scalar_mul(&u_fe.get_element()))
+    };
+    let G: GE = ECPoint::generator();
+    match (term(u1, &G), term(u2, P)) {
+        (None, None) => None,
+        (Some(A), None) => Some(A),
+        (None, Some(B)) => Some(B),
+        (Some(A), Some(B)) => {
+            if A.x_coor() == B.x_coor() && A.y_coor() != B.y_coor() {
+                None // B = -A
+            } else {
+                Some(A.add_point(&B.get_element()))
+            }
+        }
+    }
+}
+
+// n must be below 2^256
+fn to_32_bytes(n: &BigInt) -> [u8; 32] {
+    let v = BigInt::to_vec(n);
+    let mut out = [0u8; 32];
+    out[32 - v.len()..].copy_from_slice(&v);
+    out
+}
+
+fn der_integer(n: &BigInt) -> Vec<u8> {
+    let mut v = BigInt::to_vec(n);
+    if v.is_empty() || v[0] & 0x80 != 0 {
+        v.insert(0, 0);
+    }
+    let mut out = vec![0x02, v.len() as u8];
+    out.extend(v);
+    out
+}
+
+fn parse_der_integer(bytes: &[u8]) -> Result<(BigInt, &[u8]), Error> {
+    if bytes.len() < 3 || bytes[0] != 0x02 {
+        return Err(InvalidSig);
+    }
+    let len = bytes[1] as usize;
+    if len == 0 || len > 33 || bytes.len() < 2 + len {
+        return Err(InvalidSig);
+    }
+    let v = &bytes[2..2 + len];
+    // negative, or a leading zero that is not needed
+    if v[0] & 0x80 != 0 || (len > 1 && v[0] == 0 && v[1] & 0x80 == 0) {
+        return Err(InvalidSig);
+    }
+    Ok((BigInt::from(v), &bytes[2 + len..]))
+}
@@ -0,0 +1,101 @@
+// Both encryption schemes through AdditivelyHomomorphicBackend, and the
+// blind signature written against it
+
+use bld_sig::digest::MessageDigest;
+use bld_sig::protocols::backend::AdditivelyHomomorphicBackend;
+use bld_sig::protocols::blind_ecdsa::generic::{s3_partial_sig, s3_prove_eval, s4_sig, s4_verify_eval, User_Enc_and_NIZK};
+use bld_sig::protocols::blind_ecdsa::signer::{Signer_EcKeyPair, Signer_SigningKey};
+use bld_sig::protocols::blind_ecdsa::user::User_EcKeyPair;
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::signature::{verify, Signature};
+use class_group::primitives::cl_dl_lcm::HSMCL;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+// discriminant size of the HSM-CL keys (112-bit security)
+const LAM: usize = 1348;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+// Dec((Enc(m1) + rerandomized Enc(m2)) * k) == (m1 + m2) * k mod q
+fn homomorphic<B: AdditivelyHomomorphicBackend>(bitsize: usize, seed: u8) {
+    let mut rng = seeded(seed);
+    let key = B::keygen(bitsize, &mut rng);
+    let pk = B::public_key(&key);
+    let (c1, r1) = B::encrypt(&pk, &BigInt::from(5), &mut rng);
+    let (c2, _) = B::encrypt(&pk, &BigInt::from(7), &mut rng);
+    let c2 = B::rerandomize(&pk, &c2, &mut rng).unwrap();
+    let c = B::scalar_mul(&pk, &B::add(&pk, &c1, &c2).unwrap(), &BigInt::from(3)).unwrap();
+    assert_eq!(B::decrypt(&key, &c).unwrap(), BigInt::from(36));
+    // an encryption proof holds for its own ciphertext only
+    let proof = B::prove_enc(&pk, &c1, &BigInt::from(5), &r1, &mut rng);
+    assert!(B::verify_enc(&pk, &c1, &proof).is_ok());
+    assert!(B::verify_enc(&pk, &c2, &proof).is_err());
+}
+
+#[test]
+fn mpaillier_backend() {
+    homomorphic::<Pallier_AsiaCCS_19>(1024, 10);
+}
+
+#[test]
+fn hsmcl_backend() {
+    homomorphic::<HSMCL>(LAM, 20);
+}
+
+// steps 1 to 4 with the signer's evaluation proof
+fn sign<B: AdditivelyHomomorphicBackend>(bitsize: usize, seed: u8) {
+    let mut rng = seeded(seed);
+    let key = Signer_SigningKey::keygen_with_rng(&mut rng);
+    let sk = key.expose_secret().to_big_int();
+    let k1 = Signer_EcKeyPair::s1_generate_K1_with_rng(&mut rng);
+    let k2 = User_EcKeyPair::s2_generate_K_with_rng(&k1.public_share, &mut rng);
+    let h = MessageDigest::Sha256.hash_to_scalar(b"generic");
+    let (request, dec_key) = User_Enc_and_NIZK::<B>::s2_encrypt_and_nizk(&h, &k2.Kx, bitsize, &mut rng);
+    let k1_int = k1.expose_secret().to_big_int();
+    let c3 = s3_partial_sig(&request, &k1_int, &sk).unwrap();
+    let proof = s3_prove_eval(&request, &k1_int, &sk, &c3, &mut rng).unwrap();
+    assert!(s4_verify_eval(&request, &c3, &k1.public_share, &key.public_key, &proof).is_ok());
+    // the proof is for the signer's key and nonce
+    assert!(s4_verify_eval(&request, &c3, &k2.public_share, &key.public_key, &proof).is_err());
+    let s = s4_sig::<B>(&k2.expose_secret().to_big_int(), &c3, &dec_key).unwrap();
+    assert!(verify(&key.public_key, &h, &Signature::new(k2.Kx.clone(), s)).is_ok());
+}
+
+#[test]
+fn mpaillier_generic_sign() {
+    sign::<Pallier_AsiaCCS_19>(1024, 30);
+}
+
+#[test]
+fn hsmcl_generic_sign() {
+    sign::<HSMCL>(LAM, 40);
+}
+
+// a request whose proofs are swapped is rejected before evaluating
+#[test]
+fn generic_request_checked() {
+    let mut rng = seeded(50);
+    let (request, _) = User_Enc_and_NIZK::<Pallier_AsiaCCS_19>::s2_encrypt_and_nizk(&BigInt::from(1), &BigInt::from(2), 1024, &mut rng);
+    let mut swapped = request.clone();
+    swapped.p1 = request.p2.clone();
+    swapped.p2 = request.p1.clone();
+    assert!(s3_partial_sig(&swapped, &BigInt::from(3), &BigInt::from(4)).is_err());
+}
+
+// a request read back from JSON has a plain key with N_square recomputed, and is signed as before
+#[test]
+fn generic_request_serialized() {
+    let mut rng = seeded(60);
+    let (request, key) = User_Enc_and_NIZK::<Pallier_AsiaCCS_19>::s2_encrypt_and_nizk(&BigInt::from(1), &BigInt::from(2), 1024, &mut rng);
+    let json = serde_json::to_string(&request).unwrap();
+    let received: User_Enc_and_NIZK<Pallier_AsiaCCS_19> = serde_json::from_str(&json).unwrap();
+    assert_eq!(received.pk.N_square, &received.pk.N * &received.pk.N);
+    let c3 = s3_partial_sig(&received, &BigInt::from(3), &BigInt::from(4)).unwrap();
+    // Dec(c3) = (1 + 4 * 2) * 3^-1 = 3 mod q
+    assert_eq!(s4_sig::<Pallier_AsiaCCS_19>(&BigInt::from(1), &c3, &key).unwrap(), BigInt::from(3));
+}
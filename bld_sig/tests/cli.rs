@@ -0,0 +1,157 @@
+// The bld-sig binary end to end: every subcommand in the order of the usage
+// in src/main.rs, with the messages passed through files
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+// a fresh directory per test under the system temp directory
+fn workdir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("bld-sig-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bld-sig")).current_dir(dir).args(args).output().unwrap()
+}
+
+fn run_ok(dir: &Path, args: &[&str]) {
+    let out = run(dir, args);
+    assert!(out.status.success(), "{:?}: {}", args, String::from_utf8_lossy(&out.stderr));
+}
+
+#[cfg(unix)]
+fn assert_owner_only(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600, "{}", path.display());
+}
+
+#[cfg(not(unix))]
+fn assert_owner_only(_path: &Path) {}
+
+// keygen, nonce, request, respond, finalize, verify and check-opening with the
+// mpaillier backend
+fn sign(dir: &Path, prove: bool) {
+    fs::write(dir.join("msg.txt"), b"blind me").unwrap();
+    run_ok(dir, &["signer", "keygen", "--key", "signer.key", "--pubkey", "signer.pub"]);
+    assert_owner_only(&dir.join("signer.key"));
+    run_ok(dir, &["signer", "nonce", "--key", "signer.key", "--backend", "mpaillier", "--state", "signer.state", "--out", "round1.json"]);
+    assert_owner_only(&dir.join("signer.state"));
+    run_ok(dir, &[
+        "user", "request", "--pubkey", "signer.pub", "--round1", "round1.json", "--msg", "msg.txt",
+        "--bitsize", "1024", "--state", "user.state", "--out", "round2.json",
+    ]);
+    assert_owner_only(&dir.join("user.state"));
+
+    let mut respond = vec![
+        "signer", "respond", "--key", "signer.key", "--state", "signer.state", "--round2", "round2.json", "--receipt", "receipt.json", "--out",
+        "round3.json",
+    ];
+    let mut finalize = vec![
+        "user", "finalize", "--state", "user.state", "--round3", "round3.json", "--out", "sig.json", "--der", "sig.der", "--opening", "opening.json",
+    ];
+    if prove {
+        respond.push("--prove");
+        finalize.push("--require-proof");
+    }
+    run_ok(dir, &respond);
+    assert!(!dir.join("signer.state").exists());
+    run_ok(dir, &finalize);
+    assert!(!dir.join("user.state").exists());
+    assert!(!fs::read(dir.join("sig.der")).unwrap().is_empty());
+
+    let out = run(dir, &["verify", "--pubkey", "signer.pub", "--msg", "msg.txt", "--sig", "sig.json"]);
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "OK");
+
+    let out = run(dir, &["signer", "check-opening", "--receipt", "receipt.json", "--opening", "opening.json", "--sig", "sig.json"]);
+    assert!(out.status.success(), "{}", String::from_utf8_lossy(&out.stderr));
+    assert_eq!(String::from_utf8_lossy(&out.stdout).trim(), "OK");
+}
+
+#[test]
+fn full_session() {
+    let dir = workdir("session");
+    sign(&dir, false);
+
+    fs::write(dir.join("other.txt"), b"something else").unwrap();
+    let out = run(&dir, &["verify", "--pubkey", "signer.pub", "--msg", "other.txt", "--sig", "sig.json"]);
+    assert!(!out.status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn full_session_with_proof() {
+    let dir = workdir("proof");
+    sign(&dir, true);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn key_file_mode_is_reset() {
+    use std::os::unix::fs::PermissionsExt;
+    let dir = workdir("mode");
+    let key = dir.join("signer.key");
+    fs::write(&key, b"").unwrap();
+    fs::set_permissions(&key, fs::Permissions::from_mode(0o644)).unwrap();
+    run_ok(&dir, &["signer", "keygen", "--key", "signer.key"]);
+    assert_owner_only(&key);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn spent_state_is_rejected() {
+    let dir = workdir("spent");
+    sign(&dir, false);
+    // signer.state was removed by respond, so a second answer is refused
+    let out = run(&dir, &["signer", "respond", "--key", "signer.key", "--state", "signer.state", "--round2", "round2.json", "--receipt", "receipt.json"]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).starts_with("bld-sig: "));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn opening_for_another_signature_is_rejected() {
+    let dir = workdir("opening");
+    sign(&dir, false);
+    fs::rename(dir.join("sig.json"), dir.join("first.json")).unwrap();
+    fs::rename(dir.join("receipt.json"), dir.join("first-receipt.json")).unwrap();
+    sign(&dir, false);
+    let out = run(&dir, &["signer", "check-opening", "--receipt", "first-receipt.json", "--opening", "opening.json", "--sig", "sig.json"]);
+    assert!(!out.status.success());
+    let out = run(&dir, &["signer", "check-opening", "--receipt", "receipt.json", "--opening", "opening.json", "--sig", "first.json"]);
+    assert!(!out.status.success());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn committed_session() {
+    let dir = workdir("committed");
+    fs::write(dir.join("msg.txt"), b"blind me").unwrap();
+    run_ok(&dir, &["signer", "keygen", "--key", "signer.key", "--pubkey", "signer.pub"]);
+    run_ok(&dir, &["signer", "nonce", "--committed", "--key", "signer.key", "--backend", "mpaillier", "--state", "signer.state", "--out", "commit.json"]);
+    run_ok(&dir, &["user", "contribute", "--commit", "commit.json", "--state", "user.committed", "--out", "contribution.json"]);
+    assert_owner_only(&dir.join("user.committed"));
+    run_ok(&dir, &["signer", "reveal", "--state", "signer.state", "--contribution", "contribution.json", "--out", "reveal.json"]);
+    assert_owner_only(&dir.join("signer.state"));
+    // one contribution per session
+    assert!(!run(&dir, &["signer", "reveal", "--state", "signer.state", "--contribution", "contribution.json"]).status.success());
+    run_ok(&dir, &[
+        "user", "request", "--committed", "user.committed", "--reveal", "reveal.json", "--pubkey", "signer.pub", "--msg", "msg.txt",
+        "--bitsize", "1024", "--state", "user.state", "--out", "round2.json",
+    ]);
+    assert!(!dir.join("user.committed").exists());
+    run_ok(&dir, &[
+        "signer", "respond", "--key", "signer.key", "--state", "signer.state", "--round2", "round2.json", "--receipt", "receipt.json", "--out",
+        "round3.json",
+    ]);
+    run_ok(&dir, &["user", "finalize", "--state", "user.state", "--round3", "round3.json", "--out", "sig.json", "--opening", "opening.json"]);
+    let out = run(&dir, &["verify", "--pubkey", "signer.pub", "--msg", "msg.txt", "--sig", "sig.json"]);
+    assert!(out.status.success());
+    run_ok(&dir, &["signer", "check-opening", "--receipt", "receipt.json", "--opening", "opening.json", "--sig", "sig.json"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
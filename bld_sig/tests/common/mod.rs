@@ -0,0 +1,150 @@
+// Fixed keys and seeded proofs shared by the vector and mutation tests
+#![allow(dead_code)]
+
+use bld_sig::protocols::asiaccs_zk::ZK_AsiaCCS_19;
+use bld_sig::protocols::ggm_zk::{zkPoKEncProof, zkPoKEncProof_v0};
+use bld_sig::protocols::hsmcl_zk::{CLDLProof_modified, Witness};
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
+use bld_sig::protocols::rng::{hsmcl_keygen_with_setup, sample_below};
+use class_group::primitives::cl_dl_lcm::{Ciphertext, HSMCL};
+use curv::arithmetic::traits::*;
+use curv::cryptographic_primitives::hashing::hash_sha256::HSha256;
+use curv::cryptographic_primitives::hashing::traits::Hash;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+// primes of the fixed modified Paillier key (N = P * q * T)
+pub const P_HEX: &str = "8000000000000000000000000000000000000000000000000000000000000000000000000000000000000000012346df";
+pub const T_HEX: &str = "800000000000000000000000000000000000000000000000000000000000000000000000000000000000000089abcfaf";
+
+// discriminant size of the HSM-CL keys (112-bit security)
+pub const LAM: usize = 1348;
+
+// repetition parameter c of the CLDLProof_modified proofs
+pub const CLDL_C: usize = 10;
+
+pub fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+pub fn paillier_key() -> Pallier_AsiaCCS_19 {
+    Pallier_AsiaCCS_19::from_secret_for_test(BigInt::from_hex(P_HEX), BigInt::from_hex(T_HEX))
+}
+
+pub fn hsmcl_seed() -> BigInt {
+    // digits of pi, as in blind_ecdsa::user
+    str::parse(
+        "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
+    ).unwrap()
+}
+
+pub fn hsmcl_key() -> HSMCL {
+    hsmcl_keygen_with_setup(&mut seeded(11), &FE::q(), &LAM, &hsmcl_seed())
+}
+
+// h and Kx of the proofs and transcripts
+pub fn message_hash() -> BigInt {
+    HSha256::create_hash(&[&BigInt::from(111)]).mod_floor(&FE::q())
+}
+
+pub fn nonce_x() -> BigInt {
+    HSha256::create_hash(&[&BigInt::from(222)]).mod_floor(&FE::q())
+}
+
+// proof that C = Enc(h, r) under paillier_key(), with its m and r
+pub fn asiaccs_proof(seed: u8) -> (BigInt, BigInt, ZK_AsiaCCS_19) {
+    let pp = PrecomputedPaillier::from_key(&paillier_key());
+    let m = message_hash();
+    let r = BigInt::from_hex("c0ffee");
+    let c = pp.encrypt(&m, &r);
+    let proof = ZK_AsiaCCS_19::prove_precomputed_with_rng(&pp, c, m.clone(), r.clone(), &mut seeded(seed));
+    (m, r, proof)
+}
+
+pub fn cldl_proof(seed: u8) -> CLDLProof_modified {
+    let hsmcl = hsmcl_key();
+    let ppk = PrecomputedPK::new(&hsmcl.pk);
+    let mut rng = seeded(seed);
+    let x = message_hash();
+    let r = sample_below(&mut rng, &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+    let c = ppk.encrypt_predefined_randomness(&x, &r);
+    let x_fe: FE = ECScalar::from(&x);
+    let gx = GE::generator() * x_fe;
+    let witness = Witness { x, r };
+    CLDLProof_modified::prove_precomputed_with_rng(witness, &ppk, c, gx, hsmcl_seed(), CLDL_C, &mut rng)
+}
+
+// encryptions of h and Kx with the bounds used by blind_ecdsa::user
+pub struct GgmInputs {
+    pub hsmcl: HSMCL,
+    pub ppk: PrecomputedPK,
+    pub r1: BigInt,
+    pub r2: BigInt,
+    pub enc_h: Ciphertext,
+    pub enc_kx: Ciphertext,
+    pub b: BigInt,
+    pub minus_b: BigInt,
+}
+
+pub fn ggm_inputs(rng: &mut ChaChaRng) -> GgmInputs {
+    let hsmcl = hsmcl_key();
+    let ppk = PrecomputedPK::new(&hsmcl.pk);
+    let r1 = sample_below(rng, &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+    let r2 = sample_below(rng, &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+    let enc_h = ppk.encrypt_predefined_randomness(&message_hash(), &r1);
+    let enc_kx = ppk.encrypt_predefined_randomness(&nonce_x(), &r2);
+    let b = BigInt::ui_pow_ui(2, 80 + 80 + 2) * &hsmcl.pk.stilde;
+    let minus_b = BigInt::from(-1) * &b;
+    GgmInputs { hsmcl, ppk, r1, r2, enc_h, enc_kx, b, minus_b }
+}
+
+pub fn ggm_v0_proof(seed: u8) -> zkPoKEncProof_v0 {
+    let mut rng = seeded(seed);
+    let i = ggm_inputs(&mut rng);
+    zkPoKEncProof_v0::prove_precomputed_with_rng(
+        &i.hsmcl,
+        &i.ppk,
+        message_hash(),
+        nonce_x(),
+        i.r1,
+        i.r2,
+        i.enc_h.c1,
+        i.enc_h.c2,
+        i.enc_kx.c1,
+        i.enc_kx.c2,
+        i.b,
+        i.minus_b,
+        hsmcl_seed(),
+        &mut rng,
+    )
+}
+
+pub fn ggm_proof(seed: u8) -> zkPoKEncProof {
+    let mut rng = seeded(seed);
+    let i = ggm_inputs(&mut rng);
+    let sk_fe: FE = ECScalar::from(&BigInt::from_hex("5eed"));
+    let pk = GE::generator() * sk_fe;
+    zkPoKEncProof::prove_precomputed_with_rng(
+        GE::generator(),
+        &i.hsmcl,
+        &i.ppk,
+        message_hash(),
+        nonce_x(),
+        i.r1,
+        i.r2,
+        i.enc_h.c1,
+        i.enc_h.c2,
+        i.enc_kx.c1,
+        i.enc_kx.c2,
+        pk,
+        sk_fe.to_big_int(),
+        i.b,
+        i.minus_b,
+        hsmcl_seed(),
+        &mut rng,
+    )
+}
@@ -0,0 +1,62 @@
+// Known answers for the message digests and the bits2int conversion
+
+use bld_sig::digest::{bits2int, bits2int_mod_q, MessageDigest};
+use curv::arithmetic::traits::*;
+use curv::BigInt;
+
+fn hex(d: &[u8]) -> String {
+    d.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn digests_match_known_answers() {
+    assert_eq!(
+        hex(&MessageDigest::Sha256.digest(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        hex(&MessageDigest::DoubleSha256.digest(b"abc")),
+        "4f8b42c22dd3729b519ba6f68d2da7cc5b2d606d05daed5ad5128cc03e6c6358"
+    );
+    assert_eq!(
+        hex(&MessageDigest::Keccak256.digest(b"")),
+        "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+    );
+    assert_eq!(
+        hex(&MessageDigest::Keccak256.digest(b"abc")),
+        "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+    );
+}
+
+#[test]
+fn hash_to_scalar_reduces_mod_q() {
+    let h = MessageDigest::Sha256.hash_to_scalar(b"abc");
+    assert_eq!(h, BigInt::from_hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+    // 2^256 - 1 is above q
+    assert_eq!(bits2int_mod_q(&[0xff; 32]), BigInt::from_hex("14551231950b75fc4402da1732fc9bebe"));
+}
+
+#[test]
+fn bits2int_keeps_leftmost_bits() {
+    // SHA-512("abc"), 512 bits truncated to the 256 of q
+    let sha512 = BigInt::from_hex(
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+         2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+    );
+    let bytes = BigInt::to_vec(&sha512);
+    assert_eq!(
+        bits2int(&bytes),
+        BigInt::from_hex("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a")
+    );
+    // shorter digests are taken as they are
+    assert_eq!(bits2int(&[1, 2]), BigInt::from(0x0102));
+}
+
+#[test]
+fn names_round_trip() {
+    for d in [MessageDigest::Sha256, MessageDigest::DoubleSha256, MessageDigest::Keccak256].iter() {
+        assert_eq!(d.to_string().parse::<MessageDigest>(), Ok(*d));
+    }
+    assert_eq!(MessageDigest::default(), MessageDigest::Sha256);
+    assert!("sha3".parse::<MessageDigest>().is_err());
+}
@@ -0,0 +1,68 @@
+// Proofs of knowledge of the nonce shares
+
+use bld_sig::digest::MessageDigest;
+use bld_sig::protocols::blind_ecdsa::session::{Backend, SignerSession, UserSession};
+use bld_sig::protocols::blind_ecdsa::signer::{Signer_EcKeyPair, Signer_SigningKey};
+use bld_sig::protocols::blind_ecdsa::user::User_EcKeyPair;
+use bld_sig::protocols::dlog::DLogProofWithBase;
+use bld_sig::Error;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::{FE, GE};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+#[test]
+fn signer_proof_for_k1() {
+    let k1 = Signer_EcKeyPair::s1_generate_K1_with_rng(&mut seeded(1));
+    let proof = k1.s1_prove_K1();
+    assert!(User_EcKeyPair::s2_verify_K1(&k1.public_share, &proof).is_ok());
+    // a proof for another point
+    let other = Signer_EcKeyPair::s1_generate_K1_with_rng(&mut seeded(2));
+    assert_eq!(User_EcKeyPair::s2_verify_K1(&other.public_share, &proof), Err(Error::InvalidKey));
+    let mut forged = other.s1_prove_K1();
+    forged.pk = k1.public_share.clone();
+    assert_eq!(User_EcKeyPair::s2_verify_K1(&k1.public_share, &forged), Err(Error::InvalidProof));
+}
+
+#[test]
+fn user_proof_for_k() {
+    let k1 = Signer_EcKeyPair::s1_generate_K1_with_rng(&mut seeded(3));
+    let K1 = k1.public_share;
+    let k2 = User_EcKeyPair::s2_generate_K_with_rng(&K1, &mut seeded(4));
+    let proof = k2.s2_prove_K(&K1);
+    assert_eq!(proof.pk, k2.public_share);
+    assert!(proof.verify().is_ok());
+    // against another base, or with another response
+    let G: GE = ECPoint::generator();
+    let mut wrong_base = proof.clone();
+    wrong_base.base = G;
+    assert!(wrong_base.verify().is_err());
+    let mut wrong_response = proof.clone();
+    let one: FE = ECScalar::from(&curv::BigInt::from(1));
+    wrong_response.response = proof.response.add(&one.get_element());
+    assert!(wrong_response.verify().is_err());
+}
+
+#[test]
+fn deterministic_with_seeded_rng() {
+    let G: GE = ECPoint::generator();
+    let x: FE = ECScalar::from(&curv::BigInt::from(7));
+    let p = DLogProofWithBase::prove_with_rng(&G, &x, &mut seeded(5));
+    assert_eq!(p, DLogProofWithBase::prove_with_rng(&G, &x, &mut seeded(5)));
+    assert!(p.verify().is_ok());
+}
+
+// the session rejects a round 1 whose proof does not match K1
+#[test]
+fn session_checks_k1_proof() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(6));
+    let (_, mut round1) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(7));
+    let (_, other) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(8));
+    round1.K1_proof = other.K1_proof;
+    let res = UserSession::request_with_rng(&key.public_key, &round1, b"m", MessageDigest::Sha256, 1024, &mut seeded(9));
+    assert_eq!(res.err(), Some(Error::InvalidKey));
+}
@@ -0,0 +1,129 @@
+// zkPoKEncVecProof: well-formedness of any number of HSM-CL ciphertexts under
+// one key, with one challenge and one prime l
+
+mod common;
+
+use bld_sig::protocols::ggm_zk::zkPoKEncVecProof;
+use bld_sig::protocols::rng::sample_below;
+use common::*;
+use curv::arithmetic::traits::Converter;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use serde_json::Value;
+
+fn key_pair() -> (GE, BigInt) {
+    let sk_fe: FE = ECScalar::from(&BigInt::from_hex("5eed"));
+    (GE::generator() * sk_fe, sk_fe.to_big_int())
+}
+
+fn vec_proof(values: &[BigInt], seed: u8) -> zkPoKEncVecProof {
+    let mut rng = seeded(seed);
+    let i = ggm_inputs(&mut rng);
+    let stilde80 = &i.hsmcl.pk.stilde * BigInt::from(2).pow(80);
+    let r = values.iter().map(|_| sample_below(&mut rng, &stilde80)).collect::<Vec<BigInt>>();
+    let ciphertexts = values
+        .iter()
+        .zip(r.iter())
+        .map(|(m, r)| i.ppk.encrypt_predefined_randomness(m, r))
+        .collect();
+    let (PK, SK) = key_pair();
+    zkPoKEncVecProof::prove_precomputed_with_rng(
+        GE::generator(),
+        &i.hsmcl,
+        &i.ppk,
+        values.to_vec(),
+        r,
+        ciphertexts,
+        PK,
+        SK,
+        i.b,
+        i.minus_b,
+        hsmcl_seed(),
+        &mut rng,
+    )
+    .unwrap()
+}
+
+#[test]
+fn any_number_of_ciphertexts() {
+    let one = vec_proof(&[message_hash()], 40);
+    assert!(one.verify().is_ok());
+    let three = vec_proof(&[message_hash(), nonce_x(), BigInt::from(12345)], 41);
+    assert!(three.verify().is_ok());
+    assert_eq!(three.ciphertexts.len(), 3);
+}
+
+#[test]
+fn bound_to_every_ciphertext() {
+    let proof = vec_proof(&[message_hash(), nonce_x(), BigInt::from(12345)], 42);
+
+    let mut swapped = proof.clone();
+    swapped.ciphertexts.swap(0, 2);
+    assert!(swapped.verify().is_err());
+
+    let mut dropped = proof.clone();
+    dropped.ciphertexts.pop();
+    assert!(dropped.verify().is_err());
+
+    let mut changed = proof.clone();
+    changed.ciphertexts[1].c2 = proof.ciphertexts[2].c2.clone();
+    assert!(changed.verify().is_err());
+}
+
+// for (h, Kx) the vector proof runs the same protocol as zkPoKEncProof
+#[test]
+fn two_ciphertexts_match_zk_pok_enc_proof() {
+    let proof = ggm_proof(16);
+    let mut rng = seeded(16);
+    let i = ggm_inputs(&mut rng);
+    let (PK, SK) = key_pair();
+    let vec_proof = zkPoKEncVecProof::prove_precomputed_with_rng(
+        GE::generator(),
+        &i.hsmcl,
+        &i.ppk,
+        vec![message_hash(), nonce_x()],
+        vec![i.r1, i.r2],
+        vec![i.enc_h, i.enc_kx],
+        PK,
+        SK,
+        i.b,
+        i.minus_b,
+        hsmcl_seed(),
+        &mut rng,
+    )
+    .unwrap();
+    assert!(vec_proof.verify().is_ok());
+
+    let single: Value = serde_json::to_value(&proof).unwrap();
+    let vector: Value = serde_json::to_value(&vec_proof).unwrap();
+    assert_eq!(single["S_hat"], vector["S_hat"]);
+    assert_eq!(single["u_rho"], vector["u_rho"]);
+    for (k, name) in ["S1", "S2", "S3", "S4", "S5"].iter().enumerate() {
+        assert_eq!(single[*name], vector["S"][k]);
+    }
+    assert_eq!(single["u_h"], vector["z"]["u_m"][0]);
+    assert_eq!(single["u_x"], vector["z"]["u_m"][1]);
+}
+
+#[test]
+fn mismatched_witness_lengths() {
+    let mut rng = seeded(17);
+    let i = ggm_inputs(&mut rng);
+    let (PK, SK) = key_pair();
+    let result = zkPoKEncVecProof::prove_precomputed_with_rng(
+        GE::generator(),
+        &i.hsmcl,
+        &i.ppk,
+        vec![message_hash()],
+        vec![i.r1, i.r2],
+        vec![i.enc_h, i.enc_kx],
+        PK,
+        SK,
+        i.b,
+        i.minus_b,
+        hsmcl_seed(),
+        &mut rng,
+    );
+    assert!(result.is_err());
+}
@@ -0,0 +1,138 @@
+// Homomorphic correctness of both encryption schemes, the identity that step 3
+// of the blind signature relies on:
+//   Dec(Enc(m1) * Enc(m2)^k) == m1 + k * m2 mod q
+
+use std::sync::OnceLock;
+
+use bld_sig::pari;
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
+use bld_sig::protocols::rng::{hsmcl_keygen_with_setup, sample_below};
+use class_group::primitives::cl_dl_lcm::HSMCL;
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::FE;
+use proptest::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+// discriminant size of the HSM-CL key (112-bit security)
+const LAM: usize = 1348;
+
+fn paillier_key() -> &'static Pallier_AsiaCCS_19 {
+    static KEY: OnceLock<Pallier_AsiaCCS_19> = OnceLock::new();
+    KEY.get_or_init(|| Pallier_AsiaCCS_19::keygen_with_rng(1024, &mut ChaChaRng::from_seed([31; 32])))
+}
+
+fn hsmcl_key() -> &'static HSMCL {
+    static KEY: OnceLock<HSMCL> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let seed = str::parse(
+            "314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848"
+        ).unwrap();
+        hsmcl_keygen_with_setup(&mut ChaChaRng::from_seed([32; 32]), &FE::q(), &LAM, &seed)
+    })
+}
+
+// residues mod q, with 0 and q - 1 drawn as often as the rest
+fn scalar() -> impl Strategy<Value = BigInt> {
+    prop_oneof![
+        Just(BigInt::zero()),
+        Just(FE::q() - BigInt::one()),
+        any::<[u8; 32]>().prop_map(|b| BigInt::from(&b[..]).mod_floor(&FE::q())),
+    ]
+}
+
+// small signed values, for inputs given as negative integers
+fn signed() -> impl Strategy<Value = BigInt> {
+    any::<i64>().prop_map(BigInt::from)
+}
+
+fn paillier_encrypt(pp: &PrecomputedPaillier, m: &BigInt, seed: u64) -> BigInt {
+    let mut seed_bytes = [0u8; 32];
+    seed_bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    let r = sample_below(&mut ChaChaRng::from_seed(seed_bytes), &pp.N);
+    Pallier_AsiaCCS_19::encrypt(m, &r, &pp.N, &pp.N_square, &pp.g, &pp.q)
+}
+
+// Dec(Enc(m1) * Enc(m2)^k) under the modified Paillier key
+fn paillier_eval(m1: &BigInt, m2: &BigInt, k: &BigInt, seed: u64) -> BigInt {
+    let key = paillier_key();
+    let pp = PrecomputedPaillier::from_key(key);
+    let c1 = paillier_encrypt(&pp, m1, seed);
+    let c2 = paillier_encrypt(&pp, m2, seed.wrapping_add(1));
+    let c = (&c1 * &c2.powm(k, &pp.N_square)).mod_floor(&pp.N_square);
+    Pallier_AsiaCCS_19::decrypt(&c, key.clone())
+}
+
+// the same with HSM-CL, through class_group's eval_scal and eval_sum
+fn hsmcl_eval(m1: &BigInt, m2: &BigInt, k: &BigInt, seed: u64) -> BigInt {
+    let _guard = pari::lock();
+    let hsmcl = hsmcl_key();
+    let ppk = PrecomputedPK::plain(&hsmcl.pk);
+    let mut seed_bytes = [0u8; 32];
+    seed_bytes[..8].copy_from_slice(&seed.to_le_bytes());
+    let mut rng = ChaChaRng::from_seed(seed_bytes);
+    let bound = &hsmcl.pk.stilde * BigInt::from(2).pow(80);
+    let c1 = ppk.encrypt_predefined_randomness(m1, &sample_below(&mut rng, &bound));
+    let c2 = ppk.encrypt_predefined_randomness(m2, &sample_below(&mut rng, &bound));
+    let c = HSMCL::eval_sum(&c1, &HSMCL::eval_scal(&c2, k));
+    hsmcl.decrypt(&c)
+}
+
+fn expected(m1: &BigInt, m2: &BigInt, k: &BigInt) -> BigInt {
+    (m1 + &(k * m2)).mod_floor(&FE::q())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn paillier_homomorphic(m1 in scalar(), m2 in scalar(), k in scalar(), seed in any::<u64>()) {
+        prop_assert_eq!(paillier_eval(&m1, &m2, &k, seed), expected(&m1, &m2, &k));
+    }
+
+    #[test]
+    fn paillier_homomorphic_signed(m1 in signed(), m2 in signed(), k in signed(), seed in any::<u64>()) {
+        prop_assert_eq!(paillier_eval(&m1, &m2, &k, seed), expected(&m1, &m2, &k));
+    }
+}
+
+proptest! {
+    // every HSM-CL case costs a few class group exponentiations
+    #![proptest_config(ProptestConfig::with_cases(8))]
+
+    #[test]
+    fn hsmcl_homomorphic(m1 in scalar(), m2 in scalar(), k in scalar(), seed in any::<u64>()) {
+        prop_assert_eq!(hsmcl_eval(&m1, &m2, &k, seed), expected(&m1, &m2, &k));
+    }
+
+    #[test]
+    fn hsmcl_homomorphic_signed(m1 in signed(), m2 in signed(), k in signed(), seed in any::<u64>()) {
+        prop_assert_eq!(hsmcl_eval(&m1, &m2, &k, seed), expected(&m1, &m2, &k));
+    }
+}
+
+#[test]
+fn edge_cases() {
+    let q = FE::q();
+    let q_minus_1 = &q - &BigInt::one();
+    let zero = BigInt::zero();
+    let one = BigInt::one();
+    let minus_one = BigInt::from(-1);
+    let cases = [
+        (&zero, &zero, &zero),
+        (&zero, &q_minus_1, &one),
+        (&q_minus_1, &q_minus_1, &q_minus_1),
+        (&q_minus_1, &one, &one), // wraps to 0
+        (&minus_one, &one, &one),
+        (&one, &minus_one, &q_minus_1),
+        (&q_minus_1, &q_minus_1, &minus_one),
+    ];
+    for (i, (m1, m2, k)) in cases.iter().enumerate() {
+        let want = expected(m1, m2, k);
+        assert_eq!(paillier_eval(m1, m2, k, i as u64), want, "paillier case {}", i);
+        assert_eq!(hsmcl_eval(m1, m2, k, i as u64), want, "hsmcl case {}", i);
+    }
+}
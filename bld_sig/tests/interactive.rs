@@ -0,0 +1,131 @@
+// Interactive runs of the proofs, with the challenge chosen by the verifier
+
+use bld_sig::protocols::asiaccs_zk::ZK_AsiaCCS_19;
+use bld_sig::protocols::dlog::{DLogProofWithBase, DLogWithBase};
+use bld_sig::protocols::eval_zk::EvalProofPaillier;
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::precomp::PrecomputedPaillier;
+use bld_sig::protocols::range_zk::{PaillierRangeProof, RANGE_SLACK};
+use bld_sig::protocols::rng::sample_below;
+use bld_sig::protocols::sigma::{run_in_memory, Prover, SigmaProtocol, Verifier};
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+fn point(x: &FE) -> GE {
+    GE::generator() * x.clone()
+}
+
+fn paillier(seed: u8) -> (PrecomputedPaillier, BigInt) {
+    let key = Pallier_AsiaCCS_19::keygen_with_rng(1024, &mut seeded(seed));
+    let r = sample_below(&mut seeded(seed + 1), &key.N);
+    (PrecomputedPaillier::from_key(&key), r)
+}
+
+#[test]
+fn verifier_sampled_challenges() {
+    let x: FE = ECScalar::from(&BigInt::from(99));
+    let G: GE = ECPoint::generator();
+    let pk = point(&x);
+    let statement = DLogWithBase { base: &G, pk: &pk };
+    let mut verifier_rng = seeded(1);
+    let (t, accepted) = run_in_memory(&statement, &x, &mut seeded(2), |v, _| v.challenge_with_rng(&mut verifier_rng));
+    assert!(accepted);
+    assert!(t.challenge < statement.challenge_bound());
+    // the response only fits the challenge the verifier sent
+    let mut other = Verifier::new(&statement);
+    other.challenge_with_rng(&mut seeded(3));
+    assert!(!other.verify(&t.commitment, &t.response));
+}
+
+#[test]
+fn session_challenges() {
+    let x: FE = ECScalar::from(&BigInt::from(5));
+    let G: GE = ECPoint::generator();
+    let pk = point(&x);
+    let statement = DLogWithBase { base: &G, pk: &pk };
+    let session = BigInt::from(271828);
+    let (t, accepted) = run_in_memory(&statement, &x, &mut seeded(4), |v, a| v.challenge_from_session(&session, a));
+    assert!(accepted);
+    // the same session and commitment give the same challenge, another session does not
+    let mut again = Verifier::new(&statement);
+    assert_eq!(again.challenge_from_session(&session, &t.commitment), t.challenge);
+    let mut other = Verifier::new(&statement);
+    assert!(other.challenge_from_session(&BigInt::from(1), &t.commitment) != t.challenge);
+}
+
+#[test]
+fn one_response_per_commitment() {
+    let x: FE = ECScalar::from(&BigInt::from(7));
+    let G: GE = ECPoint::generator();
+    let pk = point(&x);
+    let statement = DLogWithBase { base: &G, pk: &pk };
+    let mut prover = Prover::new(&statement, &x);
+    assert!(prover.respond(&BigInt::one()).is_none());
+    prover.commit(&mut seeded(5));
+    assert!(prover.respond(&BigInt::one()).is_some());
+    assert!(prover.respond(&BigInt::from(2)).is_none());
+    // no challenge, no acceptance
+    let a = prover.commit(&mut seeded(6));
+    let z = prover.respond(&BigInt::zero()).unwrap();
+    assert!(!Verifier::new(&statement).verify(&a, &z));
+}
+
+#[test]
+fn fiat_shamir_is_the_interactive_run() {
+    let x: FE = ECScalar::from(&BigInt::from(1234));
+    let G: GE = ECPoint::generator();
+    let pk = point(&x);
+    let statement = DLogWithBase { base: &G, pk: &pk };
+    let (t, accepted) = run_in_memory(&statement, &x, &mut seeded(7), |v, a| v.fiat_shamir(a));
+    assert!(accepted);
+    let proof = DLogProofWithBase::prove_with_rng(&G, &x, &mut seeded(7));
+    assert_eq!((proof.commitment, proof.response), (t.commitment, t.response));
+}
+
+#[test]
+fn paillier_proofs() {
+    let (pp, r) = paillier(10);
+    let m = BigInt::from(4242);
+    let c = pp.encrypt(&m, &r);
+    let w = (m, r);
+    let mut verifier_rng = seeded(12);
+
+    let rounds = ZK_AsiaCCS_19::sigma(&pp, &c);
+    let (_, accepted) = run_in_memory(&rounds, &w, &mut seeded(13), |v, _| v.challenge_with_rng(&mut verifier_rng));
+    assert!(accepted);
+
+    let range = PaillierRangeProof::sigma(&pp, &c, RANGE_SLACK);
+    let (_, accepted) = run_in_memory(&range, &w, &mut seeded(15), |v, _| v.challenge_with_rng(&mut verifier_rng));
+    assert!(accepted);
+    // a verifier without slack rejects the honest masks
+    let tight = PaillierRangeProof::sigma(&pp, &c, 0);
+    let (_, accepted) = run_in_memory(&tight, &w, &mut seeded(16), |v, _| v.challenge_with_rng(&mut verifier_rng));
+    assert!(!accepted);
+}
+
+#[test]
+fn eval_proof() {
+    let (pp, r) = paillier(20);
+    let C1 = pp.encrypt(&BigInt::from(11), &r);
+    let C2 = pp.encrypt(&BigInt::from(13), &r);
+    let (a, b) = (BigInt::from(5), BigInt::from(7));
+    let c3 = (C1.powm(&a, &pp.N_square) * C2.powm(&b, &pp.N_square)).mod_floor(&pp.N_square);
+    let proof = EvalProofPaillier::prove_with_rng(&C1, &C2, &c3, &pp.N, &pp.N_square, &a, &b, &mut seeded(21)).unwrap();
+    assert!(proof.verify());
+
+    let rounds = EvalProofPaillier::sigma(&C1, &C2, &c3, &pp.N, &pp.N_square, &proof.K1, &proof.pk);
+    let session = BigInt::from(31337);
+    let (_, accepted) = run_in_memory(&rounds, &(a.clone(), b.clone()), &mut seeded(22), |v, t| v.challenge_from_session(&session, t));
+    assert!(accepted);
+    // a wrong witness is caught by a verifier-chosen challenge
+    let mut verifier_rng = seeded(23);
+    let (_, accepted) = run_in_memory(&rounds, &(a, b + BigInt::one()), &mut seeded(24), |v, _| v.challenge_with_rng(&mut verifier_rng));
+    assert!(!accepted);
+}
@@ -0,0 +1,99 @@
+// Straus multi-exponentiation against the product of single exponentiations
+
+use bld_sig::pari;
+use bld_sig::protocols::multiexp::{multi_exp_mod, multi_exp_qf};
+use class_group::BinaryQF;
+use curv::arithmetic::traits::*;
+use curv::BigInt;
+use proptest::prelude::*;
+
+// forms of discriminant -47, whose class group is cyclic of order 5
+fn forms() -> Vec<BinaryQF> {
+    [(2, 1, 6), (3, 1, 4), (2, -1, 6), (3, -1, 4)]
+        .iter()
+        .map(|&(a, b, c)| BinaryQF { a: BigInt::from(a), b: BigInt::from(b), c: BigInt::from(c) })
+        .collect()
+}
+
+fn modulus() -> BigInt {
+    BigInt::from(1_000_003) * BigInt::from(999_983)
+}
+
+fn product_qf(bases: &[&BinaryQF], exps: &[&BigInt]) -> BinaryQF {
+    let mut acc = bases[0].exp(&BigInt::zero());
+    for (b, e) in bases.iter().zip(exps.iter()) {
+        acc = acc.compose(&b.exp(e)).reduce();
+    }
+    acc
+}
+
+fn product_mod(bases: &[&BigInt], exps: &[&BigInt], n: &BigInt) -> BigInt {
+    bases
+        .iter()
+        .zip(exps.iter())
+        .fold(BigInt::one(), |acc, (b, e)| (acc * b.powm(e, n)).mod_floor(n))
+}
+
+fn signed_exponent() -> impl Strategy<Value = BigInt> {
+    prop_oneof![
+        Just(BigInt::zero()),
+        any::<i64>().prop_map(BigInt::from),
+        prop::collection::vec(any::<u8>(), 1..40).prop_map(|bytes| BigInt::from(&bytes[..])),
+    ]
+}
+
+#[test]
+fn edge_cases_qf() {
+    let _guard = pari::lock();
+    let f = forms();
+    let zero = BigInt::zero();
+    let minus_one = BigInt::from(-1);
+    let big = BigInt::one() << 300;
+    assert!(multi_exp_qf(&[], &[]).is_none());
+    assert!(multi_exp_qf(&[&f[0], &f[1]], &[&zero]).is_none());
+    assert!(multi_exp_qf(&[&f[0]], &[&zero, &zero]).is_none());
+    // all exponents zero gives the identity
+    assert_eq!(multi_exp_qf(&[&f[0], &f[1]], &[&zero, &zero]), Some(f[0].exp(&zero)));
+    let cases: [(&[&BinaryQF], &[&BigInt]); 3] = [
+        (&[&f[0]], &[&minus_one]),
+        (&[&f[0], &f[1], &f[2]], &[&big, &zero, &minus_one]),
+        (&[&f[3], &f[3]], &[&big, &minus_one]),
+    ];
+    for (bases, exps) in cases.iter() {
+        assert_eq!(multi_exp_qf(bases, exps), Some(product_qf(bases, exps)));
+    }
+}
+
+#[test]
+fn edge_cases_mod() {
+    let n = modulus();
+    let (zero, minus_one) = (BigInt::zero(), BigInt::from(-1));
+    let (a, b) = (BigInt::from(3), BigInt::from(1_000_003)); // b is not invertible
+    assert_eq!(multi_exp_mod(&[], &[], &n), Some(BigInt::one()));
+    assert!(multi_exp_mod(&[&a, &b], &[&zero], &n).is_none());
+    assert!(multi_exp_mod(&[&a], &[&zero, &zero], &n).is_none());
+    assert!(multi_exp_mod(&[&a, &b], &[&minus_one, &minus_one], &n).is_none());
+    assert_eq!(multi_exp_mod(&[&a, &b], &[&minus_one, &zero], &n), Some(product_mod(&[&a], &[&minus_one], &n)));
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn qf_matches_product(exps in prop::collection::vec(signed_exponent(), 1..5)) {
+        let _guard = pari::lock();
+        let f = forms();
+        let bases = (0..exps.len()).map(|i| &f[i % f.len()]).collect::<Vec<_>>();
+        let exps_ref = exps.iter().collect::<Vec<_>>();
+        prop_assert_eq!(multi_exp_qf(&bases, &exps_ref), Some(product_qf(&bases, &exps_ref)));
+    }
+
+    #[test]
+    fn mod_matches_product(exps in prop::collection::vec(signed_exponent(), 0..5)) {
+        let n = modulus();
+        let values = (0..exps.len()).map(|i| BigInt::from(2 + 3 * i as u64)).collect::<Vec<_>>();
+        let bases = values.iter().collect::<Vec<_>>();
+        let exps_ref = exps.iter().collect::<Vec<_>>();
+        prop_assert_eq!(multi_exp_mod(&bases, &exps_ref, &n), Some(product_mod(&bases, &exps_ref, &n)));
+    }
+}
@@ -0,0 +1,295 @@
+// Mutation tests: a valid proof of each kind is serialized, every field is
+// perturbed in turn, and the verifier must reject each perturbed proof.
+// A proof that no longer deserializes (e.g. a point off the curve) counts as
+// rejected. A verifier that panics on it fails the test: malformed input must
+// be rejected with false or Err.
+
+mod common;
+
+use std::panic::{self, AssertUnwindSafe};
+
+use bld_sig::pari;
+use bld_sig::protocols::asiaccs_zk::ZK_AsiaCCS_19;
+use bld_sig::protocols::blind_ecdsa::signer::{s3_verify_batch_hsmcl_ggm, s3_verify_batch_mpaillier};
+use bld_sig::protocols::blind_ecdsa::user::{User_Enc_and_NIZK_hsmcl_GGM, User_Enc_and_NIZK_mpaillier_version};
+use bld_sig::protocols::ggm_zk::{zkPoKEncProof, zkPoKEncProof_v0, GgmWitness};
+use bld_sig::protocols::hsmcl_zk::CLDLProof_modified;
+use bld_sig::protocols::precomp::PrecomputedPaillier;
+use bld_sig::protocols::sigma::{fs_challenge, SigmaProtocol};
+use bld_sig::protocols::validate::valid_group_element;
+use class_group::primitives::cl_dl_lcm::PK;
+use class_group::BinaryQF;
+use common::*;
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+// A BinaryQF serializes as an object with exactly the fields a, b, c
+fn is_qf(v: &Value) -> bool {
+    match v.as_object() {
+        Some(o) => o.len() == 3 && o.contains_key("a") && o.contains_key("b") && o.contains_key("c"),
+        None => false,
+    }
+}
+
+fn collect_qfs(v: &Value, out: &mut Vec<Value>) {
+    if is_qf(v) {
+        if !out.contains(v) {
+            out.push(v.clone());
+        }
+        return;
+    }
+    match v {
+        Value::Array(a) => a.iter().for_each(|x| collect_qfs(x, out)),
+        Value::Object(o) => o.values().for_each(|x| collect_qfs(x, out)),
+        _ => {}
+    }
+}
+
+// changes the last digit, valid in both decimal and hex encodings of a BigInt
+fn perturb_string(s: &str) -> String {
+    let mut t = s.to_string();
+    let last = t.pop().unwrap_or('0');
+    t.push(if last == '0' { '1' } else { '0' });
+    t
+}
+
+// All single-field mutations of v as (path, mutated value). Vectors are
+// mutated as a whole (swap, truncate) and through their first and last
+// element only, since every element is checked by the same equation.
+fn mutations(v: &Value, qfs: &[Value]) -> Vec<(String, Value)> {
+    let mut out = Vec::new();
+    if is_qf(v) {
+        // swap in another valid form of the proof
+        if let Some(other) = qfs.iter().find(|qf| *qf != v) {
+            out.push(("<swap form>".to_string(), other.clone()));
+        }
+        return out;
+    }
+    match v {
+        Value::String(s) => out.push((String::new(), Value::String(perturb_string(s)))),
+        Value::Number(n) => {
+            let n = n.as_u64().unwrap_or(0) + 1;
+            out.push((String::new(), Value::from(n)));
+        }
+        Value::Bool(b) => out.push((String::new(), Value::Bool(!b))),
+        Value::Array(a) => {
+            if a.len() >= 2 && a[0] != a[1] {
+                let mut swapped = a.clone();
+                swapped.swap(0, 1);
+                out.push(("<swap 0 1>".to_string(), Value::Array(swapped)));
+            }
+            if !a.is_empty() {
+                let mut truncated = a.clone();
+                truncated.pop();
+                out.push(("<truncate>".to_string(), Value::Array(truncated)));
+            }
+            let mut indices = vec![0, a.len().saturating_sub(1)];
+            indices.dedup();
+            for &i in indices.iter().filter(|&&i| i < a.len()) {
+                for (path, m) in mutations(&a[i], qfs) {
+                    let mut mutated = a.clone();
+                    mutated[i] = m;
+                    out.push((format!("[{}]{}", i, path), Value::Array(mutated)));
+                }
+            }
+        }
+        Value::Object(o) => {
+            for (key, field) in o.iter() {
+                for (path, m) in mutations(field, qfs) {
+                    let mut mutated = o.clone();
+                    mutated.insert(key.clone(), m);
+                    out.push((format!(".{}{}", key, path), Value::Object(mutated)));
+                }
+            }
+        }
+        Value::Null => {}
+    }
+    out
+}
+
+// asserts that verify accepts item and rejects every mutation of it
+fn assert_mutations_rejected<T, F>(item: &T, verify: F)
+where
+    T: Serialize + DeserializeOwned,
+    F: Fn(&T) -> bool,
+{
+    assert!(verify(item), "the unmodified proof must verify");
+    let v = serde_json::to_value(item).unwrap();
+    let mut qfs = Vec::new();
+    collect_qfs(&v, &mut qfs);
+    let (mut accepted, mut panicked) = (Vec::new(), Vec::new());
+    for (path, mutated) in mutations(&v, &qfs) {
+        let t = match serde_json::from_value::<T>(mutated) {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+        // caught so that every mutation is reported, not just the first
+        match panic::catch_unwind(AssertUnwindSafe(|| verify(&t))) {
+            Ok(true) => accepted.push(path),
+            Ok(false) => {}
+            Err(_) => panicked.push(path),
+        }
+    }
+    assert!(accepted.is_empty(), "mutations accepted: {:?}", accepted);
+    assert!(panicked.is_empty(), "verifier panicked on: {:?}", panicked);
+}
+
+#[test]
+fn asiaccs_zk_mutations() {
+    let _guard = pari::lock();
+    let (_, _, proof) = asiaccs_proof(21);
+    assert_mutations_rejected(&proof, |p: &ZK_AsiaCCS_19| p.verify());
+    assert_mutations_rejected(&proof, |p: &ZK_AsiaCCS_19| ZK_AsiaCCS_19::verify_batch(&[p]).is_ok());
+}
+
+#[test]
+fn cldl_zk_mutations() {
+    let _guard = pari::lock();
+    let proof = cldl_proof(22);
+    assert_mutations_rejected(&proof, |p: &CLDLProof_modified| p.verify(CLDL_C).is_ok());
+}
+
+#[test]
+fn ggm_zk_v0_mutations() {
+    let _guard = pari::lock();
+    let proof = ggm_v0_proof(23);
+    assert_mutations_rejected(&proof, |p: &zkPoKEncProof_v0| p.verify().is_ok());
+}
+
+#[test]
+fn ggm_zk_mutations() {
+    let _guard = pari::lock();
+    let proof = ggm_proof(24);
+    assert_mutations_rejected(&proof, |p: &zkPoKEncProof| p.verify().is_ok());
+    assert_mutations_rejected(&proof, |p: &zkPoKEncProof| zkPoKEncProof::verify_batch(&[p]).is_ok());
+}
+
+// ciphertexts of a step-2 request that do not match its proofs
+#[test]
+fn mismatched_ciphertexts_rejected() {
+    let _guard = pari::lock();
+    let h = message_hash();
+    let kx = nonce_x();
+    let request = User_Enc_and_NIZK_mpaillier_version::s2_mpaillier_encrypt_and_nizk_with_rng(&h, &kx, &1024, &mut seeded(25));
+    assert!(s3_verify_batch_mpaillier(&[request.clone()]).is_ok());
+    let mut swapped = request.clone();
+    swapped.C1 = request.C2.clone();
+    swapped.C2 = request.C1.clone();
+    assert_eq!(s3_verify_batch_mpaillier(&[request.clone(), swapped]), Err(vec![1]));
+
+    let request = User_Enc_and_NIZK_hsmcl_GGM::s2_hsmcl_encrypt_and_ggm_nizk_with_rng(&h, &kx, &LAM, &mut seeded(26));
+    assert!(s3_verify_batch_hsmcl_ggm(&[request.clone()]).is_ok());
+    let mut swapped = request.clone();
+    swapped.Enc_h = request.Enc_Kx.clone();
+    swapped.Enc_Kx = request.Enc_h.clone();
+    assert_eq!(s3_verify_batch_hsmcl_ggm(&[swapped, request]), Err(vec![0]));
+}
+
+// A prover that multiplies one commitment by an element of order 2 before
+// hashing it, and answers with the original masks, fails one equation by that
+// element. The batch weights are random, so the batch verifiers must reject
+// such a proof on every run, not on about half of them.
+const BATCH_RUNS: usize = 32;
+
+// (q^i, q^i, c), the reduced ambiguous form of order 2 for the first power of
+// q that gives a valid element of Cl(delta_q)
+fn ambiguous_form(pk: &PK) -> BinaryQF {
+    (1..4)
+        .map(|i| {
+            let a = pk.q.pow(i);
+            let c = (&a * &a - &pk.delta_q) / (BigInt::from(4) * &a);
+            BinaryQF { a: a.clone(), b: a, c }
+        })
+        .find(|f| valid_group_element(f, &pk.delta_q))
+        .unwrap()
+}
+
+fn set_fields<T: Serialize + DeserializeOwned>(item: &T, fields: Vec<(&str, Value)>) -> T {
+    let mut v = serde_json::to_value(item).unwrap();
+    for (key, value) in fields {
+        v[key] = value;
+    }
+    serde_json::from_value(v).unwrap()
+}
+
+fn json<T: Serialize>(t: &T) -> Value {
+    serde_json::to_value(t).unwrap()
+}
+
+#[test]
+fn asiaccs_zk_order_two_commitment_rejected() {
+    let _guard = pari::lock();
+    let (m, r, proof) = asiaccs_proof(27);
+    let pp = PrecomputedPaillier::from_key(&paillier_key());
+    let rounds = ZK_AsiaCCS_19::sigma(&pp, &proof.C);
+    let w = (m, r);
+    let (mut C1_vec, state) = rounds.commit(&w, &mut seeded(28));
+    C1_vec[0] = &pp.N_square - &C1_vec[0]; // -C' mod N^2
+    let e = fs_challenge(&rounds, &C1_vec);
+    let responses = rounds.respond(&w, state, &e);
+    let tampered = set_fields(&proof, vec![("C1_vec", json(&C1_vec)), ("Response_vec", json(&responses))]);
+
+    assert!(!tampered.verify());
+    for _ in 0..BATCH_RUNS {
+        assert_eq!(ZK_AsiaCCS_19::verify_batch(&[&tampered]), Err(vec![0]));
+        assert_eq!(ZK_AsiaCCS_19::verify_batch(&[&proof, &tampered]), Err(vec![1]));
+    }
+}
+
+#[test]
+fn ggm_zk_order_two_commitment_rejected() {
+    let _guard = pari::lock();
+    let proof = ggm_proof(29);
+    let i = ggm_inputs(&mut seeded(29));
+    let A = ambiguous_form(&i.hsmcl.pk);
+    assert_eq!(A.compose(&A).reduce(), A.exp(&BigInt::zero()));
+
+    let G = GE::generator();
+    let seed = hsmcl_seed();
+    let (h, kx) = (i.enc_h, i.enc_kx);
+    let statement = zkPoKEncProof::sigma(&i.ppk, &seed, &h.c1, &h.c2, &kx.c1, &kx.c2, &G, &proof.PK);
+    let sk: FE = ECScalar::from(&BigInt::from_hex("5eed"));
+    let w = GgmWitness {
+        r: vec![i.r1.clone(), i.r2.clone()],
+        m: vec![message_hash(), nonce_x()],
+        sk: Some(i.hsmcl.sk.clone()),
+        B: i.b.clone(),
+        minus_B: i.minus_b.clone(),
+    };
+    let w = (w, sk);
+    // S1 appears in the equations of both step 4 and step 6
+    let ((mut S, S_hat), state) = statement.commit(&w, &mut seeded(30));
+    S[0] = S[0].compose(&A).reduce();
+    let e = fs_challenge(&statement, &(S.clone(), S_hat));
+    let (z, u_rho) = statement.respond(&w, state, &e);
+    let mut fields = vec![("S_hat", json(&S_hat)), ("u_rho", json(&u_rho.to_big_int()))];
+    for (k, name) in ["S1", "S2", "S3", "S4", "S5"].iter().enumerate() {
+        fields.push((*name, json(&S[k])));
+    }
+    for (k, name) in ["D1", "D2", "D3", "D4", "D5"].iter().enumerate() {
+        fields.push((*name, json(&z.D[k])));
+    }
+    for (k, name) in ["Q1", "Q2", "Q3", "Q4", "Q5"].iter().enumerate() {
+        fields.push((*name, json(&z.Q[k])));
+    }
+    for (k, name) in ["e_1", "e_2", "e_k"].iter().enumerate() {
+        fields.push((*name, json(&z.e[k])));
+    }
+    for (k, name) in ["gamma_1", "gamma_2", "gamma_k"].iter().enumerate() {
+        fields.push((*name, json(&z.gamma[k])));
+    }
+    fields.push(("u_h", json(&z.u_m[0])));
+    fields.push(("u_x", json(&z.u_m[1])));
+    let tampered = set_fields(&proof, fields);
+
+    assert!(tampered.verify().is_err());
+    for _ in 0..BATCH_RUNS {
+        assert_eq!(zkPoKEncProof::verify_batch(&[&tampered]), Err(vec![0]));
+        assert_eq!(zkPoKEncProof::verify_batch(&[&proof, &tampered]), Err(vec![1]));
+    }
+}
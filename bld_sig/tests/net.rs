@@ -0,0 +1,159 @@
+// The signer service and client on localhost
+
+use std::time::Duration;
+
+use bld_sig::digest::MessageDigest;
+use bld_sig::net::client::Client;
+use bld_sig::net::server::{Server, ServerConfig};
+use bld_sig::net::NetError;
+use bld_sig::protocols::blind_ecdsa::session::{Backend, UserCommitted, UserSession};
+use bld_sig::protocols::blind_ecdsa::signer::Signer_SigningKey;
+use bld_sig::signature::verify;
+use curv::GE;
+use tokio::net::TcpListener;
+
+// starts a server on a free port and returns its address and public key
+async fn spawn_server(config: ServerConfig) -> (String, GE, Server) {
+    let key = Signer_SigningKey::keygen();
+    let public_key = key.public_key.clone();
+    let server = Server::new(key, config);
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(server.clone().serve(listener, |e| panic!("accept: {}", e)));
+    (addr, public_key, server)
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn signs_over_tcp() {
+    let (addr, public_key, server) = spawn_server(ServerConfig::default()).await;
+    let mut client = Client::connect(&addr).await.unwrap();
+    assert_eq!(client.public_key().await.unwrap(), public_key);
+    let sig = client
+        .sign(&public_key, b"over the wire", MessageDigest::Sha256, Backend::Mpaillier, 1024)
+        .await
+        .unwrap();
+    let h = MessageDigest::Sha256.hash_to_scalar(b"over the wire");
+    assert!(verify(&public_key, &h, &sig).is_ok());
+    assert_eq!(server.session_count(), 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn opening_checked_against_receipt() {
+    let (addr, public_key, server) = spawn_server(ServerConfig::default()).await;
+    let mut client = Client::connect(&addr).await.unwrap();
+    let (sig, opening) = client
+        .sign_with_opening(&public_key, b"open me", MessageDigest::Sha256, Backend::Mpaillier, 1024)
+        .await
+        .unwrap();
+    let (other_sig, _) = client
+        .sign_with_opening(&public_key, b"other", MessageDigest::Sha256, Backend::Mpaillier, 1024)
+        .await
+        .unwrap();
+    assert_eq!(server.receipt_count(), 2);
+    // an opening with the wrong signature leaves the receipt in place
+    match client.open(opening.clone(), other_sig).await {
+        Err(NetError::Server(_)) => {}
+        other => panic!("wrong signature accepted: {:?}", other),
+    }
+    assert_eq!(server.receipt_count(), 2);
+    client.open(opening.clone(), sig.clone()).await.unwrap();
+    assert_eq!(server.receipt_count(), 1);
+    // the receipt is spent
+    assert!(client.open(opening, sig).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn committed_round1_over_tcp() {
+    let config = ServerConfig {
+        require_committed: true,
+        ..ServerConfig::default()
+    };
+    let (addr, public_key, server) = spawn_server(config).await;
+    let mut client = Client::connect(&addr).await.unwrap();
+    match client.start(Backend::Mpaillier).await {
+        Err(NetError::Server(_)) => {}
+        other => panic!("uncommitted round 1 started: {:?}", other.map(|_| ())),
+    }
+    client.set_committed(true);
+    let (sig, opening) = client
+        .sign_with_opening(&public_key, b"committed", MessageDigest::Sha256, Backend::Mpaillier, 1024)
+        .await
+        .unwrap();
+    assert!(verify(&public_key, &MessageDigest::Sha256.hash_to_scalar(b"committed"), &sig).is_ok());
+    assert_eq!(server.session_count(), 0);
+    client.open(opening, sig).await.unwrap();
+
+    // one contribution per session
+    let commit = client.commit(Backend::Mpaillier).await.unwrap();
+    let (_, contribution) = UserCommitted::contribute(&commit);
+    client.contribute(contribution.clone()).await.unwrap();
+    assert!(client.contribute(contribution).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent_sessions() {
+    let (addr, public_key, server) = spawn_server(ServerConfig::default()).await;
+    let mut c1 = Client::connect(&addr).await.unwrap();
+    let mut c2 = Client::connect(&addr).await.unwrap();
+    // both sessions open at once, finished in the opposite order
+    let r1 = c1.start(Backend::Mpaillier).await.unwrap();
+    let r2 = c2.start(Backend::Mpaillier).await.unwrap();
+    assert_eq!(server.session_count(), 2);
+    let (u2, req2) = UserSession::request(&public_key, &r2, b"second", MessageDigest::Sha256, 1024).unwrap();
+    let (u1, req1) = UserSession::request(&public_key, &r1, b"first", MessageDigest::Sha256, 1024).unwrap();
+    let sig2 = u2.finalize(&c2.finish(req2).await.unwrap()).unwrap();
+    let sig1 = u1.finalize(&c1.finish(req1.clone()).await.unwrap()).unwrap();
+    assert!(verify(&public_key, &MessageDigest::Sha256.hash_to_scalar(b"first"), &sig1).is_ok());
+    assert!(verify(&public_key, &MessageDigest::Sha256.hash_to_scalar(b"second"), &sig2).is_ok());
+    // a session answers once
+    match c1.finish(req1).await {
+        Err(NetError::Server(_)) => {}
+        other => panic!("replayed request answered: {:?}", other.map(|_| ())),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn abandoned_sessions_expire() {
+    let config = ServerConfig {
+        session_timeout: Duration::from_millis(200),
+        ..ServerConfig::default()
+    };
+    let (addr, public_key, server) = spawn_server(config).await;
+    let mut client = Client::connect(&addr).await.unwrap();
+    let round1 = client.start(Backend::Mpaillier).await.unwrap();
+    assert_eq!(server.session_count(), 1);
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(server.session_count(), 0);
+    let (_, round2) = UserSession::request(&public_key, &round1, b"late", MessageDigest::Sha256, 1024).unwrap();
+    match client.finish(round2).await {
+        Err(NetError::Server(_)) => {}
+        other => panic!("expired session answered: {:?}", other.map(|_| ())),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn idle_connections_closed() {
+    let config = ServerConfig {
+        io_timeout: Duration::from_millis(200),
+        ..ServerConfig::default()
+    };
+    let (addr, _, _) = spawn_server(config).await;
+    let mut client = Client::connect(&addr).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(client.start(Backend::Mpaillier).await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn zero_session_timeout() {
+    let config = ServerConfig {
+        session_timeout: Duration::from_secs(0),
+        ..ServerConfig::default()
+    };
+    let (addr, public_key, server) = spawn_server(config).await;
+    let mut client = Client::connect(&addr).await.unwrap();
+    client.start(Backend::Mpaillier).await.unwrap();
+    // the expiry sweep still runs and drops the session
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(server.session_count(), 0);
+    assert_eq!(client.public_key().await.unwrap(), public_key);
+}
@@ -0,0 +1,85 @@
+// The async session steps: they keep the reactor free and can be cancelled
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bld_sig::digest::MessageDigest;
+use bld_sig::pari;
+use bld_sig::nonblocking::{finalize, request, respond, run_blocking, AsyncError};
+use bld_sig::protocols::blind_ecdsa::session::{Backend, SignerSession};
+use bld_sig::protocols::blind_ecdsa::signer::Signer_SigningKey;
+use bld_sig::signature::verify;
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn session_through_async_steps() {
+    let cancel = CancellationToken::new();
+    let key = Signer_SigningKey::keygen();
+    let (signer, round1) = SignerSession::start(&key, Backend::Mpaillier);
+    let (user, round2) = request(&key.public_key, &round1, b"async", MessageDigest::Sha256, 1024, &cancel).await.unwrap();
+    let round3 = respond(signer, &key, &round2, &cancel).await.unwrap();
+    let sig = finalize(user, &round3, &cancel).await.unwrap();
+    assert!(verify(&key.public_key, &MessageDigest::Sha256.hash_to_scalar(b"async"), &sig).is_ok());
+}
+
+// on a single-threaded runtime, timers keep firing while a step runs
+#[tokio::test(flavor = "current_thread")]
+async fn reactor_not_blocked() {
+    let cancel = CancellationToken::new();
+    let ticker = tokio::spawn(async {
+        let mut ticks = 0;
+        for _ in 0..5 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            ticks += 1;
+        }
+        ticks
+    });
+    run_blocking(|| thread::sleep(Duration::from_millis(300)), &cancel).await.unwrap();
+    assert_eq!(ticker.await.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn cancelled_before_start() {
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let key = Signer_SigningKey::keygen();
+    let (_, round1) = SignerSession::start(&key, Backend::Mpaillier);
+    let res = request(&key.public_key, &round1, b"never", MessageDigest::Sha256, 1024, &cancel).await;
+    assert_eq!(res.err(), Some(AsyncError::Cancelled));
+}
+
+#[tokio::test]
+async fn cancelled_while_running() {
+    let cancel = CancellationToken::new();
+    let trigger = cancel.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        trigger.cancel();
+    });
+    let start = Instant::now();
+    let res = run_blocking(|| thread::sleep(Duration::from_secs(2)), &cancel).await;
+    assert_eq!(res, Err(AsyncError::Cancelled));
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn panic_reported() {
+    let res: Result<(), AsyncError> = run_blocking(|| panic!("boom"), &CancellationToken::new()).await;
+    assert_eq!(res, Err(AsyncError::Panicked));
+}
+
+// Paillier steps do not wait for class group work on other threads
+#[tokio::test(flavor = "multi_thread")]
+async fn paillier_steps_skip_the_pari_lock() {
+    let cancel = CancellationToken::new();
+    let key = Signer_SigningKey::keygen();
+    let (signer, round1) = SignerSession::start(&key, Backend::Mpaillier);
+    let _guard = pari::lock();
+    let steps = async {
+        let (user, round2) = request(&key.public_key, &round1, b"parallel", MessageDigest::Sha256, 1024, &cancel).await.unwrap();
+        let round3 = respond(signer, &key, &round2, &cancel).await.unwrap();
+        finalize(user, &round3, &cancel).await.unwrap()
+    };
+    let sig = tokio::time::timeout(Duration::from_secs(60), steps).await.expect("a Paillier step waited for the PARI lock");
+    assert!(verify(&key.public_key, &MessageDigest::Sha256.hash_to_scalar(b"parallel"), &sig).is_ok());
+}
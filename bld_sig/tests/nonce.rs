@@ -0,0 +1,90 @@
+use bld_sig::protocols::blind_ecdsa::nonce::*;
+use bld_sig::protocols::blind_ecdsa::signer::Signer_EcKeyPair;
+use bld_sig::protocols::blind_ecdsa::user::User_EcKeyPair;
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+use curv::arithmetic::traits::Converter;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+
+fn sha256(msg: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input(msg);
+    let mut out = [0u8; 32];
+    hasher.result(&mut out);
+    out
+}
+
+// signer key used by the hedged derivation vectors
+fn test_sk() -> BigInt {
+    BigInt::from_hex("7c2a3f52e0e2c4b6d8a1f3e5c7b9d1f3a5c7e9b1d3f5a7c9e1b3d5f7a9c1e3f5")
+}
+
+#[test]
+fn rfc6979_secp256k1_vectors() {
+    let one = BigInt::from(1);
+    let q_minus_1 = FE::q() - BigInt::from(1);
+    let vectors = [
+        (
+            &one,
+            "Satoshi Nakamoto",
+            "8f8a276c19f4149656b280621e358cce24f5f52542772691ee69063b74f15d15",
+        ),
+        (
+            &q_minus_1,
+            "Satoshi Nakamoto",
+            "33a19b60e25fb6f4435af53a3d42d493644827367e6453928554f43e49aa6f90",
+        ),
+        (
+            &one,
+            "All those moments will be lost in time, like tears in rain. Time to die...",
+            "38aa22d72376b4dbc472e06c3ba403ee0a394da63fc58d88686c611aba98d6b3",
+        ),
+    ];
+    for (x, msg, k) in vectors.iter() {
+        let nonce = rfc6979_nonce(x, &sha256(msg.as_bytes()), &[]);
+        assert_eq!(nonce, BigInt::from_hex(k));
+    }
+}
+
+#[test]
+fn hedged_k1_vectors() {
+    let sk = test_sk();
+    let k1 = Signer_EcKeyPair::s1_derive_K1(&sk, b"session-0001", &[0u8; 32]);
+    assert_eq!(
+        k1.expose_secret().to_big_int(),
+        BigInt::from_hex("295654a98e5f9095c57320781ed96804d6401813cab50e499782e894f1716d37")
+    );
+    let k1 = Signer_EcKeyPair::s1_derive_K1(&sk, b"session-0001", &[0xffu8; 32]);
+    assert_eq!(
+        k1.expose_secret().to_big_int(),
+        BigInt::from_hex("4ed808e5b2ce1203589b1bac31aa15c26f674ee4638a1f2eed6defbc7dd2510f")
+    );
+    let base: GE = ECPoint::generator();
+    assert_eq!(k1.public_share, base.scalar_mul(&k1.expose_secret().get_element()));
+}
+
+#[test]
+fn hedged_k2_vector() {
+    let K1: GE = ECPoint::generator();
+    let k2 = User_EcKeyPair::s2_derive_K(&K1, &test_sk(), b"session-0001", &[0u8; 32]);
+    assert_eq!(
+        k2.expose_secret().to_big_int(),
+        BigInt::from_hex("3b35063c0c8e6e017187a7742ad4fe42bd90db7db8607c38865dedc3d2556f55")
+    );
+}
+
+#[test]
+fn derivation_depends_on_every_input() {
+    let sk = test_sk();
+    let other_sk = &sk + &BigInt::one();
+    let randomness = [7u8; 32];
+    let k = derive_nonce(&sk, b"session-0001", K1_LABEL, &randomness);
+    assert_eq!(k, derive_nonce(&sk, b"session-0001", K1_LABEL, &randomness));
+    assert!(k != derive_nonce(&other_sk, b"session-0001", K1_LABEL, &randomness));
+    assert!(k != derive_nonce(&sk, b"session-0002", K1_LABEL, &randomness));
+    assert!(k != derive_nonce(&sk, b"session-0001", K2_LABEL, &randomness));
+    assert!(k != derive_nonce(&sk, b"session-0001", K1_LABEL, &[8u8; 32]));
+    assert!(k != derive_nonce(&sk, b"session-0001", K1_LABEL, &fresh_randomness()));
+}
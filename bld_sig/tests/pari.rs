@@ -0,0 +1,57 @@
+// PARI is set up once per process; this file is its own test binary, so the
+// steps below see PARI before and after its first use
+
+use bld_sig::pari::{self, PariError, DEFAULT_STACK_SIZE, MIN_STACK_SIZE};
+use bld_sig::protocols::rng::hsmcl_keygen_with_setup;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::FE;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn configure_then_init_once() {
+    assert!(!pari::is_initialized());
+    assert_eq!(pari::stack_size(), DEFAULT_STACK_SIZE);
+    assert_eq!(pari::configure(MIN_STACK_SIZE - 1), Err(PariError::StackTooSmall(MIN_STACK_SIZE - 1)));
+    let size = 2 * DEFAULT_STACK_SIZE;
+    assert_eq!(pari::configure(size), Ok(()));
+
+    // first use from several threads at once
+    let handles = (0..4)
+        .map(|_| thread::spawn(pari::init))
+        .collect::<Vec<_>>();
+    handles.into_iter().for_each(|h| assert_eq!(h.join().unwrap(), Ok(())));
+    assert!(pari::is_initialized());
+    assert_eq!(pari::stack_size(), size);
+
+    // the size can no longer change
+    assert_eq!(pari::configure(size), Ok(()));
+    assert_eq!(pari::configure(DEFAULT_STACK_SIZE), Err(PariError::AlreadyInitialized(size)));
+
+    // and the library works without any init or lock of its own by the caller
+    let seed = str::parse("314159265358979323846264338327950288419716939937510582097494459230781640628620899862803482534211706798214808651328230664709384460955058223172535940812848").unwrap();
+    let hsmcl = hsmcl_keygen_with_setup(&mut ChaChaRng::from_seed([1; 32]), &FE::q(), &1348, &seed);
+    // BinaryQF::exp is class_group's own, not an entry point of this crate
+    let _guard = pari::lock();
+    assert_eq!(hsmcl.pk.gq.exp(&hsmcl.sk), hsmcl.pk.h);
+}
+
+#[test]
+fn lock_nests_on_one_thread() {
+    let outer = pari::lock();
+    let inner = pari::lock(); // would deadlock if the lock did not nest
+    drop(inner);
+    // still held by outer, so another thread waits
+    let (tx, rx) = mpsc::channel();
+    let other = thread::spawn(move || {
+        let _guard = pari::lock();
+        tx.send(()).unwrap();
+    });
+    assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    drop(outer);
+    rx.recv_timeout(Duration::from_secs(10)).unwrap();
+    other.join().unwrap();
+}
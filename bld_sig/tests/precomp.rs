@@ -0,0 +1,115 @@
+// The fixed-base tables give the same results as plain exponentiation, for
+// exponents inside the table, at its window boundaries and beyond it
+
+mod common;
+
+use bld_sig::pari;
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::precomp::{FixedBaseMod, FixedBaseQF, PrecomputedPK, PrecomputedPaillier, WINDOW};
+use bld_sig::protocols::rng::sample_below;
+use class_group::BinaryQF;
+use common::*;
+use curv::arithmetic::traits::*;
+use curv::BigInt;
+use proptest::prelude::*;
+
+// table size of the small tables below, a multiple of WINDOW
+const MAX_BITS: usize = 6 * WINDOW;
+
+// (2, 1, 3), a generator of Cl(-23)
+fn small_form() -> BinaryQF {
+    BinaryQF { a: BigInt::from(2), b: BigInt::one(), c: BigInt::from(3) }
+}
+
+fn modulus() -> BigInt {
+    BigInt::from(1_000_003) * BigInt::from(999_983)
+}
+
+// 0, +-1, +-(2^(k * WINDOW) - 1), +-2^(k * WINDOW) and both sides of the table size
+fn boundary_exponents() -> Vec<BigInt> {
+    let mut es = vec![BigInt::zero(), BigInt::one()];
+    for k in 1..=MAX_BITS / WINDOW + 1 {
+        let p = BigInt::one() << (k * WINDOW);
+        es.push(&p - BigInt::one());
+        es.push(p.clone());
+        es.push(&p + BigInt::one());
+    }
+    es.push(BigInt::one() << (3 * MAX_BITS));
+    let negated = es.iter().map(|e| BigInt::zero() - e).collect::<Vec<_>>();
+    es.extend(negated);
+    es
+}
+
+#[test]
+fn qf_table_boundaries() {
+    let _guard = pari::lock();
+    let f = small_form();
+    let table = FixedBaseQF::new(&f, MAX_BITS);
+    for e in boundary_exponents() {
+        assert_eq!(table.exp(&e), f.exp(&e), "exponent {}", e);
+    }
+}
+
+#[test]
+fn mod_table_boundaries() {
+    let (g, n) = (BigInt::from(5), modulus());
+    let table = FixedBaseMod::new(&g, &n, MAX_BITS);
+    for e in boundary_exponents() {
+        assert_eq!(table.exp(&e), g.powm(&e, &n), "exponent {}", e);
+    }
+}
+
+#[test]
+fn precomputed_encryptions() {
+    let _guard = pari::lock();
+    let hsmcl = hsmcl_key();
+    let ppk = PrecomputedPK::new(&hsmcl.pk);
+    let mut rng = seeded(60);
+    let stilde80 = &hsmcl.pk.stilde * BigInt::from(2).pow(80);
+    // beyond the table, r falls back to BinaryQF::exp
+    let large = BigInt::one() << (stilde80.bit_length() + 300);
+    for r in [BigInt::one(), sample_below(&mut rng, &stilde80), large] {
+        let m = message_hash();
+        let c = ppk.encrypt_predefined_randomness(&m, &r);
+        let pk = &hsmcl.pk;
+        let c2 = BinaryQF::expo_f(&pk.q, &pk.delta_q, &m).compose(&pk.h.exp(&r)).reduce();
+        assert_eq!((c.c1, c.c2), (pk.gq.exp(&r), c2));
+    }
+
+    let key = paillier_key();
+    let pp = PrecomputedPaillier::from_key(&key);
+    let r = sample_below(&mut rng, &key.N);
+    for m in [BigInt::zero(), BigInt::one(), message_hash(), &key.q - BigInt::one(), key.N.clone()] {
+        let expected = Pallier_AsiaCCS_19::encrypt(&m, &r, &key.N, &key.N_square, &key.g, &key.q);
+        assert_eq!(pp.encrypt(&m, &r), expected);
+    }
+}
+
+fn exponent() -> impl Strategy<Value = BigInt> {
+    // up to twice the table size, either sign
+    (any::<bool>(), prop::collection::vec(any::<u8>(), 0..(2 * MAX_BITS / 8 + 1))).prop_map(|(neg, bytes)| {
+        let e = BigInt::from(&bytes[..]);
+        if neg {
+            BigInt::zero() - e
+        } else {
+            e
+        }
+    })
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn qf_table_matches_exp(e in exponent()) {
+        let _guard = pari::lock();
+        let f = small_form();
+        prop_assert_eq!(FixedBaseQF::new(&f, MAX_BITS).exp(&e), f.exp(&e));
+    }
+
+    #[test]
+    fn mod_table_matches_powm(e in exponent()) {
+        let (g, n) = (BigInt::from(7), modulus());
+        prop_assert_eq!(FixedBaseMod::new(&g, &n, MAX_BITS).exp(&e), g.powm(&e, &n));
+    }
+}
@@ -0,0 +1,74 @@
+// Range proofs that the plaintexts of a Paillier step-2 request lie below q
+
+use bld_sig::protocols::blind_ecdsa::signer::s3_partial_sig_mpaillier;
+use bld_sig::protocols::blind_ecdsa::user::User_Enc_and_NIZK_mpaillier_version;
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::precomp::PrecomputedPaillier;
+use bld_sig::protocols::range_zk::{PaillierRangeProof, RANGE_SLACK};
+use bld_sig::protocols::rng::sample_below;
+use bld_sig::Error;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::FE;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+fn proof_for(m: &BigInt, seed: u8) -> (PrecomputedPaillier, PaillierRangeProof) {
+    let key = Pallier_AsiaCCS_19::keygen_with_rng(1024, &mut seeded(seed));
+    let pp = PrecomputedPaillier::from_key(&key);
+    let r = sample_below(&mut seeded(seed + 1), &key.N);
+    let c = pp.encrypt(m, &r);
+    let proof = PaillierRangeProof::prove_with_rng(&pp, c, m.clone(), r, RANGE_SLACK, &mut seeded(seed + 2));
+    (pp, proof)
+}
+
+#[test]
+fn honest_proofs_verify() {
+    let q = FE::q();
+    for (m, seed) in [(BigInt::zero(), 10), (BigInt::from(12345), 20), (&q - &BigInt::one(), 30)].iter() {
+        let (pp, proof) = proof_for(m, *seed);
+        assert!(proof.verify(RANGE_SLACK));
+        assert!(proof.verify_precomputed(&pp, RANGE_SLACK));
+        // a verifier with more slack accepts as well
+        assert!(proof.verify(RANGE_SLACK + 8));
+    }
+}
+
+#[test]
+fn bound_is_enforced() {
+    let (_, proof) = proof_for(&BigInt::from(7), 40);
+    // the masks are about C + RANGE_SLACK bits longer than q
+    assert!(!proof.verify(0));
+}
+
+#[test]
+fn proof_bound_to_ciphertext() {
+    let (pp, proof) = proof_for(&BigInt::from(7), 50);
+    let mut other = proof.clone();
+    other.C = pp.encrypt(&BigInt::from(8), &BigInt::from(3));
+    assert!(!other.verify(RANGE_SLACK));
+}
+
+#[test]
+#[should_panic]
+fn plaintext_above_q_not_proved() {
+    proof_for(&FE::q(), 60);
+}
+
+#[test]
+fn request_checked_in_step_3() {
+    let h = BigInt::from(1);
+    let kx = BigInt::from(2);
+    let request = User_Enc_and_NIZK_mpaillier_version::s2_mpaillier_encrypt_and_nizk_with_rng(&h, &kx, &1024, &mut seeded(70));
+    let k1 = BigInt::from(3);
+    let sk = BigInt::from(4);
+    assert!(s3_partial_sig_mpaillier(&request, &k1, &sk).is_ok());
+    let mut swapped = request.clone();
+    swapped.range1 = request.range2.clone();
+    swapped.range2 = request.range1.clone();
+    assert_eq!(s3_partial_sig_mpaillier(&swapped, &k1, &sk), Err(Error::InvalidProof));
+}
@@ -0,0 +1,117 @@
+use bld_sig::protocols::asiaccs_zk::ZK_AsiaCCS_19;
+use bld_sig::protocols::blind_ecdsa::signer::Signer_EcKeyPair;
+use bld_sig::protocols::blind_ecdsa::user::{User_EcKeyPair, User_Enc_and_NIZK_mpaillier_version};
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::precomp::PrecomputedPaillier;
+use bld_sig::protocols::rng::*;
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::ECScalar;
+use curv::BigInt;
+use curv::FE;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+#[test]
+fn sampling_stays_in_range() {
+    let mut rng = seeded(1);
+    let upper = BigInt::from(1000);
+    let lower = BigInt::from(-1000);
+    for _ in 0..100 {
+        let n = sample_below(&mut rng, &upper);
+        assert!(n >= BigInt::zero() && n < upper);
+        let n = sample_range(&mut rng, &lower, &upper);
+        assert!(n >= lower && n < upper);
+        assert!(sample_bits(&mut rng, 13).bit_length() <= 13);
+    }
+    let p = sample_prime(&mut rng, 128);
+    assert_eq!(p.bit_length(), 128);
+    assert_eq!(
+        BigInt::from(3).powm(&(&p - &BigInt::one()), &p),
+        BigInt::one()
+    );
+}
+
+// Carmichael numbers pass the Fermat test to every base coprime to them; the
+// small ones have a factor in SMALL_PRIMES, the Chernick numbers
+// (6k + 1)(12k + 1)(18k + 1) below do not and reach Miller-Rabin, as do
+// 3215031751, a strong pseudoprime to the bases 2, 3, 5 and 7, and 2^67 - 1.
+#[test]
+fn primality_test() {
+    let mut rng = seeded(7);
+    let primes = ["2", "3", "97", "101", "7919", "2147483647", "170141183460469231731687303715884105727"];
+    let composites = [
+        "0", "1", "4", "9", "10201", "2147483649",
+        "561", "1105", "1729", "2465", "2821", "6601", "8911", "41041",
+        "56052361", "118901521", "172947529", "216821881",
+        "3215031751", "147573952589676412927",
+    ];
+    for n in primes.iter() {
+        assert!(is_probable_prime(&mut rng, &n.parse::<BigInt>().unwrap()), "{} is prime", n);
+    }
+    for n in composites.iter() {
+        assert!(!is_probable_prime(&mut rng, &n.parse::<BigInt>().unwrap()), "{} is composite", n);
+    }
+}
+
+#[test]
+fn same_seed_same_output() {
+    let q = FE::q();
+    let a = (0..10).map(|_| sample_below(&mut seeded(2), &q)).collect::<Vec<BigInt>>();
+    assert!(a.iter().all(|n| n == &a[0]));
+    assert_eq!(
+        sample_prime(&mut seeded(3), 256),
+        sample_prime(&mut seeded(3), 256)
+    );
+    assert!(sample_below(&mut seeded(2), &q) != sample_below(&mut seeded(4), &q));
+}
+
+#[test]
+fn seeded_nonce_shares_are_reproducible() {
+    let k1 = Signer_EcKeyPair::s1_generate_K1_with_rng(&mut seeded(5));
+    let k1_again = Signer_EcKeyPair::s1_generate_K1_with_rng(&mut seeded(5));
+    assert_eq!(k1.expose_secret(), k1_again.expose_secret());
+    assert_eq!(k1.public_share, k1_again.public_share);
+
+    let k2 = User_EcKeyPair::s2_generate_K_with_rng(&k1.public_share, &mut seeded(6));
+    let k2_again = User_EcKeyPair::s2_generate_K_with_rng(&k1.public_share, &mut seeded(6));
+    assert_eq!(k2.expose_secret(), k2_again.expose_secret());
+    assert_eq!(k2.Kx, k2_again.Kx);
+
+    let sk = FE::new_random().to_big_int();
+    let hedged = Signer_EcKeyPair::s1_generate_K1_hedged_with_rng(&sk, b"session", &mut seeded(7));
+    let hedged_again = Signer_EcKeyPair::s1_generate_K1_hedged_with_rng(&sk, b"session", &mut seeded(7));
+    assert_eq!(hedged.expose_secret(), hedged_again.expose_secret());
+}
+
+#[test]
+fn seeded_paillier_transcript_is_reproducible() {
+    let key = Pallier_AsiaCCS_19::keygen_with_rng(1024, &mut seeded(8));
+    let key_again = Pallier_AsiaCCS_19::keygen_with_rng(1024, &mut seeded(8));
+    assert_eq!(key.N, key_again.N);
+    assert_eq!(key.expose_secret(), key_again.expose_secret());
+
+    let pp = PrecomputedPaillier::from_key(&key);
+    let m = BigInt::from(42);
+    let r = BigInt::from(7);
+    let c = pp.encrypt(&m, &r);
+    let proof = ZK_AsiaCCS_19::prove_precomputed_with_rng(&pp, c.clone(), m.clone(), r.clone(), &mut seeded(9));
+    let proof_again = ZK_AsiaCCS_19::prove_precomputed_with_rng(&pp, c, m, r, &mut seeded(9));
+    assert!(proof.verify());
+    assert_eq!(
+        serde_json::to_string(&proof).unwrap(),
+        serde_json::to_string(&proof_again).unwrap()
+    );
+
+    let h = BigInt::from(1234);
+    let kx = BigInt::from(5678);
+    let request = User_Enc_and_NIZK_mpaillier_version::s2_mpaillier_encrypt_and_nizk_with_rng(&h, &kx, &1024, &mut seeded(10));
+    let request_again = User_Enc_and_NIZK_mpaillier_version::s2_mpaillier_encrypt_and_nizk_with_rng(&h, &kx, &1024, &mut seeded(10));
+    assert_eq!(
+        serde_json::to_string(&request).unwrap(),
+        serde_json::to_string(&request_again).unwrap()
+    );
+}
@@ -0,0 +1,203 @@
+// The rounds of a blind signing session end to end, through their JSON form
+// as the CLI passes them
+
+use bld_sig::digest::MessageDigest;
+use bld_sig::protocols::blind_ecdsa::session::{
+    Backend, EvalProof, KOpening, PartialSig, Round1, Round1Commit, Round1Contribution, Round1Reveal, Round2, Round3, SessionReceipt, SignerSession, UserCommitted,
+    UserSession,
+};
+use bld_sig::protocols::blind_ecdsa::signer::Signer_SigningKey;
+use bld_sig::signature::verify;
+use bld_sig::Error;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+// discriminant size of the HSM-CL keys (112-bit security)
+const LAM: usize = 1348;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+fn through_json<T: Serialize + DeserializeOwned>(value: &T) -> T {
+    serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+}
+
+fn sign(backend: Backend, bitsize: usize, msg: &[u8], seed: u8) {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(seed));
+    let (signer, round1) = SignerSession::start_with_rng(&key, backend, &mut seeded(seed + 1));
+    let round1: Round1 = through_json(&round1);
+    let (user, round2) = UserSession::request_with_rng(&key.public_key, &round1, msg, MessageDigest::Sha256, bitsize, &mut seeded(seed + 2)).unwrap();
+    let round2: Round2 = through_json(&round2);
+    let round3: Round3 = through_json(&through_json(&signer).respond(&key, &round2).unwrap());
+    let sig = through_json(&user).finalize(&round3).unwrap();
+    assert!(sig.is_low_s());
+    let h = MessageDigest::Sha256.hash_to_scalar(msg);
+    assert!(verify(&key.public_key, &h, &sig).is_ok());
+    assert!(verify(&key.public_key, &MessageDigest::Sha256.hash_to_scalar(b"other"), &sig).is_err());
+}
+
+#[test]
+fn mpaillier_session() {
+    sign(Backend::Mpaillier, 1024, b"blind me", 40);
+}
+
+#[test]
+fn hsmcl_sessions() {
+    sign(Backend::Hsmcl, LAM, b"blind me", 50);
+    sign(Backend::HsmclGgm, LAM, b"blind me", 60);
+}
+
+#[test]
+fn request_for_another_session_rejected() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(70));
+    let (signer, _) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(71));
+    let (_, other) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(72));
+    let (_, round2) = UserSession::request_with_rng(&key.public_key, &other, b"m", MessageDigest::Sha256, 1024, &mut seeded(73)).unwrap();
+    assert_eq!(signer.respond(&key, &round2).unwrap_err(), Error::InvalidProof);
+}
+
+#[test]
+fn committed_session() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(80));
+    let (mut signer, commit) = SignerSession::start_committed_with_rng(&key, Backend::Mpaillier, &mut seeded(81));
+    let commit: Round1Commit = through_json(&commit);
+    let (user, contribution) = UserCommitted::contribute_with_rng(&commit, &mut seeded(82));
+    let contribution: Round1Contribution = through_json(&contribution);
+    let reveal: Round1Reveal = through_json(&signer.reveal(&contribution).unwrap());
+    let (user, round2) = through_json(&user)
+        .request_with_rng(&key.public_key, &reveal, b"blind me", MessageDigest::Sha256, 1024, &mut seeded(83))
+        .unwrap();
+    let round3 = through_json(&signer).respond(&key, &round2).unwrap();
+    let sig = user.finalize(&round3).unwrap();
+    assert!(verify(&key.public_key, &MessageDigest::Sha256.hash_to_scalar(b"blind me"), &sig).is_ok());
+}
+
+#[test]
+fn committed_reveal_must_open() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(90));
+    let (mut signer, commit) = SignerSession::start_committed_with_rng(&key, Backend::Mpaillier, &mut seeded(91));
+    let (user, contribution) = UserCommitted::contribute_with_rng(&commit, &mut seeded(92));
+    let reveal = signer.reveal(&contribution).unwrap();
+    // the signer swaps in another K1 after seeing rho
+    let (_, other) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(93));
+    let mut swapped = reveal.clone();
+    swapped.round1.K1 = other.K1;
+    swapped.round1.K1_proof = other.K1_proof;
+    let res = through_json(&user).request_with_rng(&key.public_key, &swapped, b"m", MessageDigest::Sha256, 1024, &mut seeded(94));
+    assert_eq!(res.err(), Some(Error::InvalidCom));
+    let mut reblinded = reveal.clone();
+    reblinded.blind_factor = reveal.blind_factor + curv::BigInt::from(1);
+    let res = user.request_with_rng(&key.public_key, &reblinded, b"m", MessageDigest::Sha256, 1024, &mut seeded(95));
+    assert_eq!(res.err(), Some(Error::InvalidCom));
+    // one contribution per session
+    assert_eq!(signer.reveal(&contribution).unwrap_err(), Error::InvalidCom);
+}
+
+#[test]
+fn committed_session_answers_after_reveal_only() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(100));
+    let (signer, commit) = SignerSession::start_committed_with_rng(&key, Backend::Mpaillier, &mut seeded(101));
+    // a request built on the untweaked K1 of some round 1 with this session id
+    let (_, mut round1) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(102));
+    round1.session_id = commit.session_id.clone();
+    let (_, round2) = UserSession::request_with_rng(&key.public_key, &round1, b"m", MessageDigest::Sha256, 1024, &mut seeded(103)).unwrap();
+    assert_eq!(signer.respond(&key, &round2).unwrap_err(), Error::InvalidCom);
+}
+
+// signer answers with an evaluation proof, which the user requires
+// the user opens K after the signature is published, and only for its session
+#[test]
+fn k_opening_links_signature_to_session() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(105));
+    let (signer, round1) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(106));
+    let (user, round2) = UserSession::request_with_rng(&key.public_key, &round1, b"blind me", MessageDigest::Sha256, 1024, &mut seeded(107)).unwrap();
+    let receipt: SessionReceipt = through_json(&signer.receipt(&round2).unwrap());
+    let opening: KOpening = through_json(user.K_opening());
+    let round3 = signer.respond(&key, &round2).unwrap();
+    let sig = user.finalize(&round3).unwrap();
+    assert!(receipt.verify_opening(&opening, &sig).is_ok());
+
+    // another session's opening and signature
+    let (signer, round1) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(108));
+    let (user, round2) = UserSession::request_with_rng(&key.public_key, &round1, b"blind me", MessageDigest::Sha256, 1024, &mut seeded(109)).unwrap();
+    let other_opening = user.K_opening().clone();
+    let other_sig = user.finalize(&signer.respond(&key, &round2).unwrap()).unwrap();
+    assert_eq!(receipt.verify_opening(&other_opening, &other_sig).unwrap_err(), Error::InvalidKey);
+    assert_eq!(receipt.verify_opening(&opening, &other_sig).unwrap_err(), Error::InvalidSig);
+    let mut reblinded = opening.clone();
+    reblinded.blind_factor = opening.blind_factor + curv::BigInt::from(1);
+    assert_eq!(receipt.verify_opening(&reblinded, &sig).unwrap_err(), Error::InvalidCom);
+}
+
+#[test]
+fn k_opening_in_committed_session() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(110));
+    let (mut signer, commit) = SignerSession::start_committed_with_rng(&key, Backend::Mpaillier, &mut seeded(111));
+    let (user, contribution) = UserCommitted::contribute_with_rng(&commit, &mut seeded(112));
+    let reveal = signer.reveal(&contribution).unwrap();
+    let (user, round2) = user.request_with_rng(&key.public_key, &reveal, b"blind me", MessageDigest::Sha256, 1024, &mut seeded(113)).unwrap();
+    // the opening is over K1' = K1 + t * G, which the signer holds after reveal
+    let receipt = signer.receipt(&round2).unwrap();
+    assert!(receipt.K1 != reveal.round1.K1);
+    let opening = user.K_opening().clone();
+    let sig = user.finalize(&signer.respond(&key, &round2).unwrap()).unwrap();
+    assert!(receipt.verify_opening(&opening, &sig).is_ok());
+}
+
+fn sign_with_proof(backend: Backend, bitsize: usize, seed: u8) {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(seed));
+    let (signer, round1) = SignerSession::start_with_rng(&key, backend, &mut seeded(seed + 1));
+    let (user, round2) = UserSession::request_with_rng(&key.public_key, &round1, b"m", MessageDigest::Sha256, bitsize, &mut seeded(seed + 2)).unwrap();
+    let round3: Round3 = through_json(&signer.respond_with_proof(&key, &round2).unwrap());
+    let sig = user.finalize_with_proof(&round3).unwrap();
+    assert!(verify(&key.public_key, &MessageDigest::Sha256.hash_to_scalar(b"m"), &sig).is_ok());
+}
+
+#[test]
+fn mpaillier_session_with_eval_proof() {
+    sign_with_proof(Backend::Mpaillier, 1024, 110);
+}
+
+#[test]
+fn hsmcl_session_with_eval_proof() {
+    sign_with_proof(Backend::HsmclGgm, LAM, 120);
+}
+
+#[test]
+fn eval_proof_checked_before_decrypting() {
+    let key = Signer_SigningKey::keygen_with_rng(&mut seeded(130));
+    let round = |seed: u8| {
+        let (signer, round1) = SignerSession::start_with_rng(&key, Backend::Mpaillier, &mut seeded(seed));
+        let (user, round2) = UserSession::request_with_rng(&key.public_key, &round1, b"m", MessageDigest::Sha256, 1024, &mut seeded(seed + 1)).unwrap();
+        (user, signer.respond_with_proof(&key, &round2).unwrap())
+    };
+    // an answer other than the proved one
+    let (user, mut round3) = round(131);
+    let c3 = match (&round3.partial_sig, &round3.eval_proof) {
+        (PartialSig::Mpaillier(c3), Some(EvalProof::Mpaillier(proof))) => (c3 * c3).mod_floor(&proof.N_square),
+        _ => unreachable!(),
+    };
+    round3.partial_sig = PartialSig::Mpaillier(c3.clone());
+    assert_eq!(through_json(&user).finalize(&round3).unwrap_err(), Error::InvalidProof);
+    // with the proof's statement changed to match
+    if let Some(EvalProof::Mpaillier(proof)) = &mut round3.eval_proof {
+        proof.c3 = c3;
+    }
+    assert_eq!(user.finalize(&round3).unwrap_err(), Error::InvalidProof);
+    // a user that requires the proof
+    let (user, mut round3) = round(133);
+    round3.eval_proof = None;
+    assert_eq!(through_json(&user).finalize_with_proof(&round3).unwrap_err(), Error::InvalidProof);
+    assert!(user.finalize(&round3).is_ok());
+}
+
+#[test]
+fn backend_names_round_trip() {
+    for b in [Backend::Mpaillier, Backend::Hsmcl, Backend::HsmclGgm].iter() {
+        assert_eq!(b.to_string().parse::<Backend>(), Ok(*b));
+    }
+    assert!("paillier".parse::<Backend>().is_err());
+}
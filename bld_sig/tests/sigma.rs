@@ -0,0 +1,100 @@
+// The Sigma-protocol framework: Fiat-Shamir, AND-composition and parallel
+// repetition, on the statements the NIZKs are built from
+
+use bld_sig::pari;
+use bld_sig::protocols::asiaccs_zk::PaillierEnc;
+use bld_sig::protocols::dlog::{DLogProofWithBase, DLogWithBase};
+use bld_sig::protocols::hsmcl_zk::{ClDlEnc, Witness};
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
+use bld_sig::protocols::rng::{hsmcl_keygen_with_setup, sample_below};
+use bld_sig::protocols::sigma::{fs_challenge, fs_prove, fs_verify, And, Repeat, SigmaProtocol};
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+fn point(x: &FE) -> GE {
+    GE::generator() * x.clone()
+}
+
+#[test]
+fn dlog_proof_is_fiat_shamir_of_its_sigma_protocol() {
+    let x: FE = ECScalar::from(&BigInt::from(1234));
+    let G: GE = ECPoint::generator();
+    let pk = point(&x);
+    let statement = DLogWithBase { base: &G, pk: &pk };
+    let (a, z) = fs_prove(&statement, &x, &mut seeded(1));
+    assert!(fs_verify(&statement, &a, &z));
+    let proof = DLogProofWithBase::prove_with_rng(&G, &x, &mut seeded(1));
+    assert_eq!((proof.commitment, proof.response), (a.clone(), z.clone()));
+    // the response only fits its own challenge
+    let e = fs_challenge(&statement, &a);
+    assert!(!statement.verify(&a, &(e + BigInt::one()), &z));
+}
+
+#[test]
+fn and_shares_one_challenge() {
+    let x1: FE = ECScalar::from(&BigInt::from(11));
+    let x2: FE = ECScalar::from(&BigInt::from(22));
+    let G: GE = ECPoint::generator();
+    let (pk1, pk2) = (point(&x1), point(&x2));
+    let statement = And::new(DLogWithBase { base: &G, pk: &pk1 }, DLogWithBase { base: &G, pk: &pk2 });
+    let (a, z) = fs_prove(&statement, &(x1.clone(), x2.clone()), &mut seeded(2));
+    assert!(fs_verify(&statement, &a, &z));
+    let swapped = (z.1, z.0);
+    assert!(!fs_verify(&statement, &a, &swapped));
+    // a proof for the left statement alone is not a proof for both
+    let (a1, z1) = fs_prove(&statement.left, &x1, &mut seeded(3));
+    let (a2, z2) = fs_prove(&statement.right, &x2, &mut seeded(4));
+    assert!(!fs_verify(&statement, &(a1, a2), &(z1, z2)));
+}
+
+#[test]
+fn repeat_slices_the_challenge() {
+    let key = Pallier_AsiaCCS_19::keygen_with_rng(1024, &mut seeded(5));
+    let pp = PrecomputedPaillier::from_key(&key);
+    let m = BigInt::from(77);
+    let r = sample_below(&mut seeded(6), &key.N);
+    let c = pp.encrypt(&m, &r);
+    let rounds = Repeat::new(PaillierEnc { pp: &pp, C: &c }, 80);
+    assert_eq!(rounds.challenge_bound(), BigInt::from(2).pow(80));
+    let e = sample_below(&mut seeded(7), &rounds.challenge_bound());
+    for i in 0..80 {
+        assert_eq!(rounds.round_challenge(&e, i), (e.clone() >> i) & BigInt::one());
+    }
+
+    let (a, z) = fs_prove(&rounds, &(m, r), &mut seeded(8));
+    assert!(fs_verify(&rounds, &a, &z));
+    // every run is checked
+    assert!(!fs_verify(&rounds, &a[1..].to_vec(), &z[1..].to_vec()));
+    let mut z_bad = z.clone();
+    z_bad[79] = z[78].clone();
+    assert!(!fs_verify(&rounds, &a, &z_bad));
+}
+
+// the CLDL runs now take c-bit challenges, so 80 / c runs give 80 bits
+#[test]
+fn cldl_runs_take_c_bit_challenges() {
+    let _guard = pari::lock();
+    let seed = BigInt::from(314159);
+    let hsmcl = hsmcl_keygen_with_setup(&mut seeded(9), &FE::q(), &1348, &seed);
+    let ppk = PrecomputedPK::new(&hsmcl.pk);
+    let x = BigInt::from(42);
+    let r = sample_below(&mut seeded(10), &(&hsmcl.pk.stilde * BigInt::from(2).pow(80)));
+    let ciphertext = ppk.encrypt_predefined_randomness(&x, &r);
+    let x_fe: FE = ECScalar::from(&x);
+    let Q = point(&x_fe);
+    let rounds = Repeat::new(ClDlEnc { ppk: &ppk, seed: &seed, ciphertext: &ciphertext, Q: &Q, c: 10 }, 8);
+    assert_eq!(rounds.challenge_bound(), BigInt::from(2).pow(80));
+    let (a, z) = fs_prove(&rounds, &Witness { x, r }, &mut seeded(11));
+    assert!(fs_verify(&rounds, &a, &z));
+    let other = point(&ECScalar::from(&BigInt::from(43)));
+    let wrong = Repeat::new(ClDlEnc { ppk: &ppk, seed: &seed, ciphertext: &ciphertext, Q: &other, c: 10 }, 8);
+    assert!(!fs_verify(&wrong, &a, &z));
+}
@@ -0,0 +1,125 @@
+// Cross-checks the signature module against libsecp256k1
+
+use bld_sig::signature::{recover, recovery_id, verify, Signature};
+use bld_sig::Error::InvalidSig;
+use curv::arithmetic::traits::*;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+struct Case {
+    pubkey: GE,
+    msg: [u8; 32],
+    sig: secp256k1::Signature,
+    recid: u8,
+}
+
+fn cases() -> Vec<Case> {
+    let mut rng = ChaChaRng::from_seed([41; 32]);
+    (0..32)
+        .map(|_| {
+            let mut sk_bytes = [0u8; 32];
+            let mut msg = [0u8; 32];
+            rng.fill_bytes(&mut sk_bytes);
+            rng.fill_bytes(&mut msg);
+            let sk = secp256k1::SecretKey::parse(&sk_bytes).unwrap();
+            let pk = secp256k1::PublicKey::from_secret_key(&sk);
+            let (sig, recid) = secp256k1::sign(&secp256k1::Message::parse(&msg), &sk);
+            Case {
+                pubkey: ECPoint::from_bytes(&pk.serialize()[..]).unwrap(),
+                msg,
+                sig,
+                recid: recid.serialize(),
+            }
+        })
+        .collect()
+}
+
+fn ours(sig: &secp256k1::Signature) -> Signature {
+    Signature::from_compact(&sig.serialize()[..]).unwrap()
+}
+
+#[test]
+fn verifies_libsecp256k1_signatures() {
+    for case in cases() {
+        let z = BigInt::from(&case.msg[..]);
+        let sig = ours(&case.sig);
+        assert!(verify(&case.pubkey, &z, &sig).is_ok());
+        // wrong message, wrong key
+        assert!(verify(&case.pubkey, &(&z + &BigInt::one()), &sig).is_err());
+        let other: GE = ECPoint::generator();
+        assert!(verify(&other, &z, &sig).is_err());
+        // the high-s twin verifies as well and normalizes back
+        let high = Signature::new(sig.r.clone(), FE::q() - &sig.s);
+        assert!(verify(&case.pubkey, &z, &high).is_ok());
+        assert_eq!(high.normalize_s(), sig.normalize_s());
+        assert!(sig.normalize_s().is_low_s());
+    }
+}
+
+#[test]
+fn encodings_match_libsecp256k1() {
+    for case in cases() {
+        let sig = ours(&case.sig);
+        let der = sig.to_der().unwrap();
+        assert_eq!(&sig.to_compact().unwrap()[..], &case.sig.serialize()[..]);
+        assert_eq!(der, case.sig.serialize_der().as_ref().to_vec());
+        assert_eq!(Signature::from_der(&der).unwrap(), sig);
+        let parsed = secp256k1::Signature::parse_der(&der).unwrap();
+        assert_eq!(&parsed.serialize()[..], &case.sig.serialize()[..]);
+    }
+}
+
+#[test]
+fn recovery_matches_libsecp256k1() {
+    for case in cases() {
+        let z = BigInt::from(&case.msg[..]);
+        let sig = ours(&case.sig);
+        assert_eq!(recovery_id(&case.pubkey, &z, &sig), Some(case.recid));
+        assert_eq!(recover(&z, &sig, case.recid).unwrap(), case.pubkey);
+        let recid = secp256k1::RecoveryId::parse(case.recid).unwrap();
+        let pk = secp256k1::recover(&secp256k1::Message::parse(&case.msg), &case.sig, &recid).unwrap();
+        let pk: GE = ECPoint::from_bytes(&pk.serialize()[..]).unwrap();
+        assert_eq!(pk, case.pubkey);
+    }
+}
+
+#[test]
+fn rejects_malformed_encodings() {
+    let sig = Signature::new(BigInt::from(1), BigInt::from(2));
+    let der = sig.to_der().unwrap();
+    assert_eq!(der, vec![0x30, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x02]);
+    // trailing byte, wrong length, padded integer, r = 0, s = q
+    let mut trailing = der.clone();
+    trailing.push(0);
+    assert!(Signature::from_der(&trailing).is_err());
+    assert!(Signature::from_der(&der[..7]).is_err());
+    assert!(Signature::from_der(&[0x30, 0x07, 0x02, 0x02, 0x00, 0x01, 0x02, 0x01, 0x02]).is_err());
+    assert!(Signature::from_der(&[0x30, 0x06, 0x02, 0x01, 0x00, 0x02, 0x01, 0x02]).is_err());
+    let mut compact = [0u8; 64];
+    compact[63] = 1;
+    assert!(Signature::from_compact(&compact).is_err());
+    let mut high = [0u8; 64];
+    high[31] = 1;
+    high[32..].copy_from_slice(&BigInt::to_vec(&FE::q()));
+    assert!(Signature::from_compact(&high).is_err());
+}
+
+#[test]
+fn encoders_reject_out_of_range() {
+    let q = FE::q();
+    let too_long = BigInt::one() << 300;
+    for (r, s) in [
+        (BigInt::zero(), BigInt::one()),
+        (BigInt::one(), q.clone()),
+        (too_long.clone(), BigInt::one()),
+        (BigInt::one(), too_long),
+        (BigInt::from(-1), BigInt::one()),
+    ] {
+        let sig = Signature::new(r, s);
+        assert_eq!(sig.to_compact(), Err(InvalidSig));
+        assert_eq!(sig.to_der(), Err(InvalidSig));
+    }
+}
@@ -0,0 +1,289 @@
+// Special-soundness extractors and honest-verifier simulators for the Sigma
+// protocols behind the NIZKs. The extractor recovers the witness from two
+// accepting transcripts with the same commitment and different challenges,
+// the simulator produces accepting transcripts for a given challenge without
+// the witness.
+
+use bld_sig::pari;
+use bld_sig::protocols::asiaccs_zk::PaillierEnc;
+use bld_sig::protocols::dlog::DLogWithBase;
+use bld_sig::protocols::ggm_zk::{GgmEnc, GgmResponse, GgmWitness};
+use bld_sig::protocols::hsmcl_zk::{ClDlEnc, TTriplets, Witness};
+use bld_sig::protocols::mpaillier::Pallier_AsiaCCS_19;
+use bld_sig::protocols::multiexp::{multi_exp_mod, multi_exp_qf};
+use bld_sig::protocols::precomp::{PrecomputedPK, PrecomputedPaillier};
+use bld_sig::protocols::rng::{hsmcl_keygen_with_setup, sample_below, sample_range, sample_scalar};
+use bld_sig::protocols::sigma::{And, Repeat, SigmaProtocol};
+use class_group::primitives::cl_dl_lcm::U1U2;
+use class_group::BinaryQF;
+use curv::elliptic::curves::traits::{ECPoint, ECScalar};
+use curv::BigInt;
+use curv::{FE, GE};
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+// discriminant size of the HSM-CL keys (112-bit security)
+const LAM: usize = 1348;
+
+fn seeded(byte: u8) -> ChaChaRng {
+    ChaChaRng::from_seed([byte; 32])
+}
+
+fn scalar(n: &BigInt) -> FE {
+    ECScalar::from(&n.mod_floor(&FE::q()))
+}
+
+// e * P, also for e = 0 mod q
+fn mul_or_zero(P: &GE, e: &BigInt) -> Option<GE> {
+    match e.mod_floor(&FE::q()) == BigInt::zero() {
+        true => None,
+        false => Some(P.clone() * scalar(e)),
+    }
+}
+
+trait Harness: SigmaProtocol {
+    type Extracted;
+
+    // None if the two challenges are equal
+    fn extract(&self, a: &Self::Commitment, e1: &BigInt, z1: &Self::Response, e2: &BigInt, z2: &Self::Response) -> Option<Self::Extracted>;
+    fn simulate(&self, e: &BigInt, rng: &mut ChaChaRng) -> (Self::Commitment, Self::Response);
+}
+
+// a run whose two challenges differ gives the witness
+impl<P: Harness> Harness for Repeat<P> {
+    type Extracted = P::Extracted;
+
+    fn extract(&self, a: &Vec<P::Commitment>, e1: &BigInt, z1: &Vec<P::Response>, e2: &BigInt, z2: &Vec<P::Response>) -> Option<P::Extracted> {
+        (0..self.times).find_map(|i| {
+            self.inner.extract(&a[i], &self.round_challenge(e1, i), &z1[i], &self.round_challenge(e2, i), &z2[i])
+        })
+    }
+
+    fn simulate(&self, e: &BigInt, rng: &mut ChaChaRng) -> (Vec<P::Commitment>, Vec<P::Response>) {
+        (0..self.times).map(|i| self.inner.simulate(&self.round_challenge(e, i), rng)).unzip()
+    }
+}
+
+impl<P: Harness, Q: Harness> Harness for And<P, Q> {
+    type Extracted = (P::Extracted, Q::Extracted);
+
+    fn extract(&self, a: &Self::Commitment, e1: &BigInt, z1: &Self::Response, e2: &BigInt, z2: &Self::Response) -> Option<Self::Extracted> {
+        let left = self.left.extract(&a.0, e1, &z1.0, e2, &z2.0)?;
+        let right = self.right.extract(&a.1, e1, &z1.1, e2, &z2.1)?;
+        Some((left, right))
+    }
+
+    fn simulate(&self, e: &BigInt, rng: &mut ChaChaRng) -> (Self::Commitment, Self::Response) {
+        let (a1, z1) = self.left.simulate(e, rng);
+        let (a2, z2) = self.right.simulate(e, rng);
+        ((a1, a2), (z1, z2))
+    }
+}
+
+impl Harness for DLogWithBase<'_> {
+    type Extracted = FE;
+
+    // z = t - e * x, so x = (z1 - z2) / (e2 - e1) mod q
+    fn extract(&self, _a: &GE, e1: &BigInt, z1: &FE, e2: &BigInt, z2: &FE) -> Option<FE> {
+        let q = FE::q();
+        let de = (e2 - e1).mod_floor(&q).invert(&q)?;
+        let dz = z1.to_big_int() - z2.to_big_int();
+        Some(scalar(&(dz * de)))
+    }
+
+    // a = z * base + e * pk
+    fn simulate(&self, e: &BigInt, rng: &mut ChaChaRng) -> (GE, FE) {
+        let z: FE = sample_scalar(rng);
+        let zb = self.base.clone() * z.clone();
+        let a = match mul_or_zero(self.pk, e) {
+            Some(epk) => zb + epk,
+            None => zb,
+        };
+        (a, z)
+    }
+}
+
+impl Harness for PaillierEnc<'_> {
+    type Extracted = (BigInt, BigInt);
+
+    // with one-bit challenges the difference is +-1: m = m2 - m2' mod q, r = r2 / r2'
+    fn extract(&self, _a: &BigInt, e1: &BigInt, z1: &bld_sig::Response, e2: &BigInt, z2: &bld_sig::Response) -> Option<(BigInt, BigInt)> {
+        let (hi, lo) = match e2 - e1 {
+            d if d == BigInt::one() => (z2, z1),
+            d if d == BigInt::from(-1) => (z1, z2),
+            _ => return None,
+        };
+        let m = (&hi.m2 - &lo.m2).mod_floor(&self.pp.q);
+        let r = (&hi.r2 * &lo.r2.invert(&self.pp.N_square)?).mod_floor(&self.pp.N_square);
+        Some((m, r))
+    }
+
+    // C' = g^m2 * r2^N * C^(-b)
+    fn simulate(&self, b: &BigInt, rng: &mut ChaChaRng) -> (BigInt, bld_sig::Response) {
+        let pp = self.pp;
+        let m2 = sample_below(rng, &pp.q);
+        let r2 = sample_below(rng, &pp.N_square);
+        let minus_b = BigInt::zero() - b;
+        let r2NCb = multi_exp_mod(&[&r2, self.C], &[&pp.N, &minus_b], &pp.N_square).unwrap();
+        let C1 = (pp.g_exp(&m2) * r2NCb).mod_floor(&pp.N_square);
+        (C1, bld_sig::Response { m2, r2 })
+    }
+}
+
+impl Harness for ClDlEnc<'_> {
+    type Extracted = Witness;
+
+    // u1 = r1 + k * r over the integers, u2 = r2 + k * x mod q
+    fn extract(&self, _a: &TTriplets, k1: &BigInt, z1: &U1U2, k2: &BigInt, z2: &U1U2) -> Option<Witness> {
+        let dk = k2 - k1;
+        if dk == BigInt::zero() {
+            return None;
+        }
+        let du1 = &z2.u1 - &z1.u1;
+        assert_eq!(du1.mod_floor(&dk), BigInt::zero(), "u1 - u1' is a multiple of k - k'");
+        let r = du1.div_floor(&dk);
+        let q = FE::q();
+        let x = ((&z2.u2 - &z1.u2) * dk.mod_floor(&q).invert(&q)?).mod_floor(&q);
+        Some(Witness { x, r })
+    }
+
+    // t1 = gq^u1 * c1^(-k), t2 = pk^u1 * f^u2 * c2^(-k), T = u2 * G - k * Q
+    fn simulate(&self, k: &BigInt, rng: &mut ChaChaRng) -> (TTriplets, U1U2) {
+        let ppk = self.ppk;
+        let bound = &ppk.pk.stilde * BigInt::from(2).pow(160 + self.c as u32);
+        let u1 = sample_below(rng, &bound);
+        let u2 = sample_below(rng, &FE::q());
+        let minus_k = BigInt::zero() - k;
+        let t1 = ppk.gq_exp(&u1)
+            .compose(&multi_exp_qf(&[&self.ciphertext.c1], &[&minus_k]))
+            .reduce();
+        let t2 = ppk.h_exp(&u1)
+            .compose(&ppk.f_exp(&u2)).reduce()
+            .compose(&multi_exp_qf(&[&self.ciphertext.c2], &[&minus_k]))
+            .reduce();
+        let u2G = GE::generator() * scalar(&u2);
+        let T = match mul_or_zero(self.Q, &minus_k) {
+            Some(kQ) => u2G + kQ,
+            None => u2G,
+        };
+        (TTriplets { t1, t2, T }, U1U2 { u1, u2 })
+    }
+}
+
+impl Harness for GgmEnc<'_> {
+    // The plaintexts m_i. The randomness r_i (and sk) only appear in the
+    // transcript reduced mod q and mod l, with the quotients in the exponents of
+    // D and Q, so two transcripts do not give them; their extraction rests on the
+    // proof of exponentiation behind l instead.
+    type Extracted = Vec<BigInt>;
+
+    // u_m_i = s_m_i + c * m_i over the integers
+    fn extract(&self, _a: &Vec<BinaryQF>, c1: &BigInt, z1: &GgmResponse, c2: &BigInt, z2: &GgmResponse) -> Option<Vec<BigInt>> {
+        let dc = c2 - c1;
+        if dc == BigInt::zero() {
+            return None;
+        }
+        let m = z1.u_m
+            .iter()
+            .zip(z2.u_m.iter())
+            .map(|(u1, u2)| {
+                let du = u2 - u1;
+                assert_eq!(du.mod_floor(&dc), BigInt::zero());
+                du.div_floor(&dc)
+            })
+            .collect();
+        Some(m)
+    }
+
+    // gq^u_i * c1_i^(-c) and f^u_m_i * pk^u_i * c2_i^(-c) per ciphertext,
+    // gq^u_k * pk^(-c) for the key; the responses are then compressed as by the prover
+    fn simulate(&self, c: &BigInt, rng: &mut ChaChaRng) -> (Vec<BinaryQF>, GgmResponse) {
+        let ppk = self.ppk;
+        let B = BigInt::from(2).pow(162) * &ppk.pk.stilde;
+        let minus_B = BigInt::zero() - &B;
+        let k = self.ciphertexts.len();
+        let n = if self.with_key { k + 1 } else { k };
+        let u = (0..n).map(|_| sample_range(rng, &minus_B, &B)).collect::<Vec<BigInt>>();
+        let u_m = (0..k).map(|_| sample_range(rng, &minus_B, &B)).collect::<Vec<BigInt>>();
+        let minus_c = BigInt::zero() - c;
+        let mut S = Vec::new();
+        for (i, (c1, c2)) in self.ciphertexts.iter().enumerate() {
+            S.push(ppk.gq_exp(&u[i]).compose(&multi_exp_qf(&[*c1], &[&minus_c])).reduce());
+            S.push(
+                ppk.f_exp(&u_m[i]).compose(&ppk.h_exp(&u[i])).reduce()
+                    .compose(&multi_exp_qf(&[*c2], &[&minus_c])).reduce(),
+            );
+        }
+        if self.with_key {
+            S.push(ppk.gq_exp(&u[k]).compose(&ppk.h_exp(&minus_c)).reduce());
+        }
+
+        let split = |d: &[BigInt]| {
+            let mut D = d[..k].iter().flat_map(|d| vec![ppk.gq_exp(d), ppk.h_exp(d)]).collect::<Vec<BinaryQF>>();
+            if self.with_key {
+                D.push(ppk.gq_exp(&d[k]));
+            }
+            D
+        };
+        let q = &ppk.pk.q;
+        let d = u.iter().map(|u| u.div_floor(q)).collect::<Vec<BigInt>>();
+        let mut z = GgmResponse {
+            D: split(&d),
+            e: u.iter().map(|u| u.mod_floor(q)).collect(),
+            Q: Vec::new(),
+            gamma: Vec::new(),
+            u_m,
+        };
+        let l = z.ell();
+        let q_ = u.iter().map(|u| u.div_floor(&l)).collect::<Vec<BigInt>>();
+        z.Q = split(&q_);
+        z.gamma = u.iter().map(|u| u.mod_floor(&l)).collect();
+        (S, z)
+    }
+}
+
+
+fn ggm_extractor_and_simulator(with_key: bool, seed: u8) {
+    let _guard = pari::lock();
+    let setup_seed = BigInt::from(1414213562u64);
+    let hsmcl = hsmcl_keygen_with_setup(&mut seeded(seed), &FE::q(), &LAM, &setup_seed);
+    let ppk = PrecomputedPK::new(&hsmcl.pk);
+    let mut rng = seeded(seed + 1);
+    let stilde80 = &hsmcl.pk.stilde * BigInt::from(2).pow(80);
+    let (r1, r2) = (sample_below(&mut rng, &stilde80), sample_below(&mut rng, &stilde80));
+    let (h, Kx) = (BigInt::from(1111), BigInt::from(2222));
+    let x = ppk.encrypt_predefined_randomness(&h, &r1);
+    let y = ppk.encrypt_predefined_randomness(&Kx, &r2);
+    let B = BigInt::from(2).pow(162) * &hsmcl.pk.stilde;
+    let w = GgmWitness {
+        r: vec![r1, r2],
+        m: vec![h.clone(), Kx.clone()],
+        sk: if with_key { Some(hsmcl.sk.clone()) } else { None },
+        minus_B: BigInt::zero() - &B,
+        B,
+    };
+    let enc = GgmEnc { ppk: &ppk, seed: &setup_seed, ciphertexts: vec![(&x.c1, &x.c2), (&y.c1, &y.c2)], with_key };
+    if !with_key {
+        assert_eq!(extract_from_two_transcripts(&enc, &w, seed + 2), vec![h, Kx]);
+        assert_simulates(&enc, seed + 3);
+        return;
+    }
+    // zkPoKEncProof: the class group part and the Schnorr proof for PK
+    let SK = scalar(&BigInt::from(777));
+    let G = GE::generator();
+    let PK = G.clone() * SK.clone();
+    let p = And::new(enc, DLogWithBase { base: &G, pk: &PK });
+    let (m, SK_) = extract_from_two_transcripts(&p, &(w, SK.clone()), seed + 2);
+    assert_eq!((m, SK_), (vec![h, Kx], SK));
+    assert_simulates(&p, seed + 3);
+}
+
+#[test]
+fn ggm_v0_extractor_and_simulator() {
+    ggm_extractor_and_simulator(false, 20);
+}
+
+#[test]
+fn ggm_extractor_and_simulator_with_key() {
+    ggm_extractor_and_simulator(true, 30);
+}
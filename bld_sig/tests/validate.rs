@@ -0,0 +1,82 @@
+// Validation of class group elements received from the other party
+
+mod common;
+
+use bld_sig::protocols::validate::{
+    discriminant, is_primitive, is_reduced, valid_ciphertext, valid_group_element, valid_pk, valid_qf,
+};
+use class_group::BinaryQF;
+use common::*;
+use curv::arithmetic::traits::*;
+use curv::BigInt;
+
+fn form(a: i64, b: i64, c: i64) -> BinaryQF {
+    BinaryQF { a: BigInt::from(a), b: BigInt::from(b), c: BigInt::from(c) }
+}
+
+// (a, b + 2a, a + b + c), equivalent to f but not reduced
+fn unreduced(f: &BinaryQF) -> BinaryQF {
+    let two_a = &f.a + &f.a;
+    BinaryQF { a: f.a.clone(), b: &f.b + &two_a, c: &f.a + &f.b + &f.c }
+}
+
+#[test]
+fn small_forms() {
+    let delta = BigInt::from(-12);
+    assert_eq!(discriminant(&form(2, 2, 2)), delta);
+    // (2, 2, 2) is reduced but not primitive
+    assert!(is_reduced(&form(2, 2, 2)));
+    assert!(!is_primitive(&form(2, 2, 2)));
+    assert!(!valid_qf(&form(2, 2, 2), &delta));
+    // (1, 0, 3) is the identity of Cl(-12)
+    assert!(valid_qf(&form(1, 0, 3), &delta));
+    assert!(!valid_group_element(&form(1, 0, 3), &delta));
+    // (3, -2, 1) has the right discriminant, but a > c
+    assert!(!valid_qf(&form(3, -2, 1), &BigInt::from(-8)));
+    // negative definite
+    assert!(!valid_qf(&form(-1, 0, -3), &delta));
+}
+
+#[test]
+fn honest_key_and_ciphertext() {
+    let proof = cldl_proof(50);
+    assert!(valid_pk(&proof.pk));
+    assert!(valid_ciphertext(&proof.ciphertext, &proof.pk));
+    assert!(proof.verify(CLDL_C).is_ok());
+}
+
+#[test]
+fn rejects_malformed_ciphertexts() {
+    let proof = cldl_proof(51);
+    let pk = &proof.pk;
+
+    let mut not_reduced = proof.ciphertext.clone();
+    not_reduced.c1 = unreduced(&proof.ciphertext.c1);
+    assert_eq!(discriminant(&not_reduced.c1), pk.delta_q);
+    assert!(!valid_ciphertext(&not_reduced, pk));
+
+    let mut identity = proof.ciphertext.clone();
+    identity.c1 = BinaryQF { a: BigInt::one(), b: BigInt::one(), c: (BigInt::one() - &pk.delta_q) / BigInt::from(4) };
+    assert!(!valid_ciphertext(&identity, pk));
+
+    // c2 of Cl(delta_k) instead of Cl(delta_q)
+    let mut other_discriminant = proof.ciphertext.clone();
+    other_discriminant.c2 = BinaryQF { a: BigInt::one(), b: BigInt::one(), c: (BigInt::one() - &pk.delta_k) / BigInt::from(4) };
+    assert!(!valid_ciphertext(&other_discriminant, pk));
+
+    let mut tampered = proof.clone();
+    tampered.ciphertext = not_reduced;
+    assert!(tampered.verify(CLDL_C).is_err());
+}
+
+#[test]
+fn rejects_malformed_keys() {
+    let proof = cldl_proof(52);
+    let mut pk = proof.pk.clone();
+    pk.h = unreduced(&proof.pk.h);
+    assert!(!valid_pk(&pk));
+
+    let mut pk = proof.pk.clone();
+    pk.delta_q = &pk.delta_q * BigInt::from(4);
+    assert!(!valid_pk(&pk));
+}